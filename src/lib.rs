@@ -1,3 +1,11 @@
 #![no_std]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
 pub mod mail;
+pub mod prelude;
+pub mod smtp;
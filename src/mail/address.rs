@@ -1,10 +1,68 @@
-use super::{validate_part, InvalidPartError};
+use core::fmt;
 
-#[derive(Debug)]
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+use super::{
+    syntax::{forbidden, offset_of, parse_exact, Cursor},
+    Domain, Grammar, InvalidPartError, ParseDomainError, Rule, ValidationOptions,
+};
+
+/// The maximum length of the user (local part) in octets, per RFC 5321.
+const MAX_USER_LEN: usize = 64;
+
+/// The maximum length of the domain in octets, per RFC 5321.
+const MAX_DOMAIN_LEN: usize = 255;
+
+/// The maximum length of a whole address in octets, which is what fits in a 256-octet path
+/// including its angle brackets.
+const MAX_LEN: usize = 254;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ParseAddressError {
     MissingUserOrDomain,
     InvalidUser(InvalidPartError),
     InvalidDomain(InvalidPartError),
+    /// The user is longer than 64 octets.
+    UserTooLong,
+    /// The domain is longer than 255 octets.
+    DomainTooLong,
+    /// The address is longer than 254 octets.
+    TooLong,
+}
+
+impl ParseAddressError {
+    /// Moves the offset of an invalid character, for an address that was parsed from a slice of
+    /// the input.
+    pub(crate) fn offset(self, by: usize) -> Self {
+        match self {
+            Self::InvalidUser(error) => Self::InvalidUser(error.offset(by)),
+            Self::InvalidDomain(error) => Self::InvalidDomain(error.offset(by)),
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for ParseAddressError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingUserOrDomain => f.write_str("address is missing a user or domain"),
+            Self::InvalidUser(_) => f.write_str("invalid user"),
+            Self::InvalidDomain(_) => f.write_str("invalid domain"),
+            Self::UserTooLong => write!(f, "user is longer than {MAX_USER_LEN} octets"),
+            Self::DomainTooLong => write!(f, "domain is longer than {MAX_DOMAIN_LEN} octets"),
+            Self::TooLong => write!(f, "address is longer than {MAX_LEN} octets"),
+        }
+    }
+}
+
+impl core::error::Error for ParseAddressError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidUser(error) | Self::InvalidDomain(error) => Some(error),
+            _ => None,
+        }
+    }
 }
 
 /// Represents an email address
@@ -22,6 +80,21 @@ pub enum ParseAddressError {
 ///
 /// let address = Address::try_from("user@domain.com").unwrap();
 /// ```
+///
+/// Parsing follows the `addr-spec` rule of RFC 5322, so quoted local parts, escaped characters,
+/// domain literals and surrounding comments are accepted. Quotes and brackets are kept as they
+/// were written:
+/// ```
+/// use brief::mail::Address;
+///
+/// assert!(Address::try_from(r#""john doe"@example.com"#).is_ok());
+/// assert!(Address::try_from("very.common@example.com (work)").is_ok());
+/// assert!(Address::try_from("user@[192.0.2.1]").is_ok());
+/// assert!(Address::try_from("user@[IPv6:2001:db8::1]").is_ok());
+/// ```
+///
+/// Domain literals must contain an IPv4 address or an IPv6 address tagged with `IPv6:`, see
+/// [`Domain`].
 #[derive(Debug, Clone, Default, PartialEq, PartialOrd, Hash, Eq, Ord)]
 pub struct Address<'a> {
     user: &'a str,
@@ -30,20 +103,178 @@ pub struct Address<'a> {
 
 impl<'a> Address<'a> {
     /// Tries to create an address from a user and domain, returning an error if the user and/or
-    /// domain are invalid.
+    /// domain are invalid. The user must be a dot-atom or a quoted string, and the domain a
+    /// dot-atom or a domain literal. The user can be at most 64 octets long, the domain 255 and
+    /// the whole address 254, as RFC 5321 requires.
     ///
     /// ```
     /// use brief::mail::Address;
     ///
     /// let address = Address::try_new("user", "domain.com").unwrap();
+    /// let quoted = Address::try_new(r#""john doe""#, "domain.com").unwrap();
     /// ```
     pub fn try_new(user: &'a str, domain: &'a str) -> Result<Self, ParseAddressError> {
-        validate_part(user).map_err(|e| ParseAddressError::InvalidUser(e))?;
-        validate_part(domain).map_err(|e| ParseAddressError::InvalidDomain(e))?;
+        Self::new(user, domain, ValidationOptions::strict())
+    }
+
+    /// Like [`Address::try_new`], but allows UTF-8 in the user and domain, as RFC 6532 does.
+    /// Sending mail to such an address [requires SMTPUTF8](Address::requires_smtputf8).
+    ///
+    /// ```
+    /// use brief::mail::Address;
+    ///
+    /// let address = Address::try_new_international("jöran", "bücher.de").unwrap();
+    /// ```
+    pub fn try_new_international(
+        user: &'a str,
+        domain: &'a str,
+    ) -> Result<Self, ParseAddressError> {
+        Self::new(user, domain, ValidationOptions::strict().allow_utf8(true))
+    }
 
-        // TODO: can't throw 'MissingUserOrDomain' but TryFrom impl can
+    fn new(
+        user: &'a str,
+        domain: &'a str,
+        options: ValidationOptions,
+    ) -> Result<Self, ParseAddressError> {
+        let utf8 = options.allows_utf8();
+        let user = parse_exact(user, utf8, local_part(options.grammar()))
+            .map_err(ParseAddressError::InvalidUser)?;
+        let domain = parse_exact(domain, utf8, Cursor::domain)
+            .and_then(|domain| validate_domain(domain, options.grammar()))
+            .map_err(ParseAddressError::InvalidDomain)?;
 
-        Ok(Self { user, domain })
+        Self { user, domain }.validate_len()
+    }
+
+    /// Like [`Address::try_from`], but allows UTF-8 in the user and domain, as RFC 6532 does.
+    ///
+    /// ```
+    /// use brief::mail::Address;
+    ///
+    /// assert!(Address::try_from("用户@例子.广告").is_err());
+    ///
+    /// let address = Address::parse_international("用户@例子.广告").unwrap();
+    /// assert!(address.requires_smtputf8());
+    /// ```
+    pub fn parse_international(value: &'a str) -> Result<Self, ParseAddressError> {
+        Self::parse_with(value, ValidationOptions::strict().allow_utf8(true))
+    }
+
+    /// Like [`Address::try_from`], but validates against the grammar of the given options.
+    ///
+    /// ```
+    /// use brief::mail::{Address, ValidationOptions};
+    ///
+    /// assert!(Address::try_from("user@[192.0.2.1]").is_ok());
+    /// assert!(Address::parse_with("user@[192.0.2.1]", ValidationOptions::html5()).is_err());
+    /// ```
+    pub fn parse_with(
+        value: &'a str,
+        options: ValidationOptions,
+    ) -> Result<Self, ParseAddressError> {
+        Self::parse(value, options)
+    }
+
+    /// Returns the user, the part before the `@`, as it was written.
+    ///
+    /// ```
+    /// use brief::mail::Address;
+    ///
+    /// let address = Address::try_from(r#""john doe"@example.com"#).unwrap();
+    /// assert_eq!(address.user(), r#""john doe""#);
+    /// assert_eq!(address.domain(), "example.com");
+    /// ```
+    pub fn user(&self) -> &'a str {
+        self.user
+    }
+
+    /// Returns the domain, the part after the `@`, as it was written. Use [`Domain::try_from`]
+    /// to convert it to ASCII or check whether it is an address literal.
+    pub fn domain(&self) -> &'a str {
+        self.domain
+    }
+
+    /// Splits the address into its user and domain.
+    pub fn into_parts(self) -> (&'a str, &'a str) {
+        (self.user, self.domain)
+    }
+
+    /// Returns whether the address contains UTF-8, so mail to or from it can only be sent to
+    /// servers that support the SMTPUTF8 extension.
+    pub fn requires_smtputf8(&self) -> bool {
+        !self.user.is_ascii() || !self.domain.is_ascii()
+    }
+
+    fn parse(value: &'a str, options: ValidationOptions) -> Result<Self, ParseAddressError> {
+        let grammar = options.grammar();
+        let mut cursor = Cursor::new(value, options.allows_utf8());
+        // only RFC 5322 allows comments and whitespace around the parts
+        let skip_cfws = |cursor: &mut Cursor| match grammar {
+            Grammar::Rfc5322 => cursor.skip_cfws(),
+            _ => Ok(()),
+        };
+
+        let user = skip_cfws(&mut cursor)
+            .and_then(|_| local_part(grammar)(&mut cursor))
+            .and_then(|user| skip_cfws(&mut cursor).map(|_| user));
+        let user = match user {
+            Ok(user) => user,
+            Err(_) if !value.contains('@') => return Err(ParseAddressError::MissingUserOrDomain),
+            Err(error) => return Err(ParseAddressError::InvalidUser(error)),
+        };
+
+        if !cursor.eat('@') {
+            return match cursor.peek() {
+                None => Err(ParseAddressError::MissingUserOrDomain),
+                Some(c) => Err(ParseAddressError::InvalidUser(cursor.unexpected(c))),
+            };
+        }
+
+        let domain = skip_cfws(&mut cursor)
+            .and_then(|_| cursor.domain())
+            .and_then(|domain| skip_cfws(&mut cursor).map(|_| domain))
+            .and_then(|domain| {
+                validate_domain(domain, grammar).map_err(|e| e.offset(offset_of(value, domain)))
+            })
+            .map_err(ParseAddressError::InvalidDomain)?;
+
+        match cursor.peek() {
+            None => Self { user, domain }.validate_len(),
+            Some(c) => Err(ParseAddressError::InvalidDomain(cursor.unexpected(c))),
+        }
+    }
+
+    /// Checks the length limits of RFC 5321, which servers enforce.
+    fn validate_len(self) -> Result<Self, ParseAddressError> {
+        if self.user.len() > MAX_USER_LEN {
+            Err(ParseAddressError::UserTooLong)
+        } else if self.domain.len() > MAX_DOMAIN_LEN {
+            Err(ParseAddressError::DomainTooLong)
+        } else if self.user.len() + 1 + self.domain.len() > MAX_LEN {
+            Err(ParseAddressError::TooLong)
+        } else {
+            Ok(self)
+        }
+    }
+
+    /// Copies the address into an [`OwnedAddress`], which doesn't borrow its input.
+    ///
+    /// ```
+    /// use brief::mail::{Address, OwnedAddress};
+    ///
+    /// fn parse(input: String) -> OwnedAddress {
+    ///     Address::try_from(input.as_str()).unwrap().into_owned()
+    /// }
+    ///
+    /// let address = parse(String::from("user@domain.com"));
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn into_owned(self) -> OwnedAddress {
+        OwnedAddress {
+            user: self.user.into(),
+            domain: self.domain.into(),
+        }
     }
 }
 
@@ -51,24 +282,147 @@ impl<'a> TryFrom<&'a str> for Address<'a> {
     type Error = ParseAddressError;
 
     fn try_from(value: &'a str) -> Result<Self, Self::Error> {
-        if !value.contains('@') {
-            return Err(ParseAddressError::MissingUserOrDomain);
+        Self::parse(value, ValidationOptions::strict())
+    }
+}
+
+impl fmt::Display for Address<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.user, self.domain)
+    }
+}
+
+/// Returns the parser for the local part in the given grammar.
+fn local_part<'a>(grammar: Grammar) -> fn(&mut Cursor<'a>) -> Result<&'a str, InvalidPartError> {
+    match grammar {
+        Grammar::Html5 => Cursor::loose_dot_atom,
+        _ => Cursor::local_part,
+    }
+}
+
+/// Checks that a domain literal contains an IP address, which is the only kind RFC 5321 allows
+/// to deliver to, and that the domain is a host name if the grammar requires it.
+fn validate_domain(domain: &str, grammar: Grammar) -> Result<&str, InvalidPartError> {
+    let literal = domain.starts_with('[');
+    if grammar == Grammar::Rfc5322 && !literal {
+        return Ok(domain);
+    }
+
+    match Domain::try_from(domain) {
+        Ok(domain) if grammar == Grammar::Html5 && domain.is_literal() => {
+            Err(InvalidPartError::InvalidSyntax(Rule::DomainLiteral))
         }
+        Ok(domain) => Ok(domain.as_str()),
+        Err(ParseDomainError::InvalidLiteral) => {
+            Err(InvalidPartError::InvalidSyntax(Rule::DomainLiteral))
+        }
+        Err(ParseDomainError::InvalidCharacter(c)) => {
+            // the first occurrence is the invalid one, since the character is invalid anywhere
+            Err(forbidden(c, domain.find(c).unwrap_or(0)))
+        }
+        Err(_) => Err(InvalidPartError::InvalidSyntax(Rule::Hostname)),
+    }
+}
+
+/// Represents an email address that owns its user and domain
+///
+/// An `OwnedAddress` can be stored in long-lived structs, while [`Address`] borrows from the
+/// string it was parsed from. The two convert into each other:
+/// ```
+/// use brief::mail::{Address, OwnedAddress};
+///
+/// let owned = OwnedAddress::try_from("user@domain.com").unwrap();
+/// let borrowed: Address = owned.as_address();
+///
+/// assert_eq!(OwnedAddress::from(borrowed), owned);
+/// ```
+///
+/// It can also be parsed with [`str::parse`]:
+/// ```
+/// use brief::mail::OwnedAddress;
+///
+/// let address: OwnedAddress = "user@domain.com".parse().unwrap();
+/// assert_eq!(address.domain(), "domain.com");
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd, Hash, Eq, Ord)]
+pub struct OwnedAddress {
+    user: String,
+    domain: String,
+}
+
+#[cfg(feature = "alloc")]
+impl OwnedAddress {
+    /// Tries to create an address from a user and domain, returning an error if the user and/or
+    /// domain are invalid.
+    ///
+    /// ```
+    /// use brief::mail::OwnedAddress;
+    ///
+    /// let address = OwnedAddress::try_new("user", "domain.com").unwrap();
+    /// ```
+    pub fn try_new(user: &str, domain: &str) -> Result<Self, ParseAddressError> {
+        Address::try_new(user, domain).map(Address::into_owned)
+    }
+
+    pub fn user(&self) -> &str {
+        &self.user
+    }
+
+    pub fn domain(&self) -> &str {
+        &self.domain
+    }
+
+    /// Splits the address into its user and domain.
+    pub fn into_parts(self) -> (String, String) {
+        (self.user, self.domain)
+    }
+
+    /// Borrows the address as an [`Address`].
+    pub fn as_address(&self) -> Address<'_> {
+        Address {
+            user: &self.user,
+            domain: &self.domain,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<Address<'_>> for OwnedAddress {
+    fn from(value: Address<'_>) -> Self {
+        value.into_owned()
+    }
+}
 
-        let mut split = value.rsplitn(2, '@');
-        let domain = split.next().unwrap_or("");
-        let user = split.next().unwrap_or("");
+#[cfg(feature = "alloc")]
+impl<'a> From<&'a OwnedAddress> for Address<'a> {
+    fn from(value: &'a OwnedAddress) -> Self {
+        value.as_address()
+    }
+}
 
-        validate_part(user).map_err(|e| ParseAddressError::InvalidUser(e))?;
-        validate_part(domain).map_err(|e| ParseAddressError::InvalidDomain(e))?;
+#[cfg(feature = "alloc")]
+impl TryFrom<&str> for OwnedAddress {
+    type Error = ParseAddressError;
 
-        Address::try_new(user, domain)
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Address::try_from(value).map(Address::into_owned)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::str::FromStr for OwnedAddress {
+    type Err = ParseAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Address;
+    use super::{Address, ParseAddressError};
+    use crate::mail::{InvalidPartError, Rule, ValidationOptions};
 
     #[test]
     fn it_creates_an_address() {
@@ -99,4 +453,184 @@ mod tests {
         let without_domain = Address::try_from("name");
         assert!(without_domain.is_err());
     }
+
+    #[test]
+    fn it_parses_quoted_local_parts_and_comments() {
+        let quoted = Address::try_from(r#""john doe"@example.com"#).unwrap();
+        assert_eq!(
+            quoted,
+            Address::try_new(r#""john doe""#, "example.com").unwrap()
+        );
+
+        let escaped = Address::try_from(r#""a\"b@c"@example.com"#);
+        assert!(escaped.is_ok());
+
+        let commented = Address::try_from(" (home) very.common@example.com (work)").unwrap();
+        assert_eq!(
+            commented,
+            Address::try_new("very.common", "example.com").unwrap()
+        );
+
+        let literal = Address::try_from("user@[192.0.2.1]");
+        assert!(literal.is_ok());
+    }
+
+    #[test]
+    fn it_reports_which_rule_failed() {
+        assert_eq!(
+            Address::try_from("john..doe@example.com"),
+            Err(ParseAddressError::InvalidUser(
+                InvalidPartError::InvalidSyntax(Rule::DotAtom)
+            ))
+        );
+        assert_eq!(
+            Address::try_from(r#""john@example.com"#),
+            Err(ParseAddressError::InvalidUser(
+                InvalidPartError::InvalidSyntax(Rule::QuotedString)
+            ))
+        );
+        assert_eq!(
+            Address::try_from("john@example.com (work"),
+            Err(ParseAddressError::InvalidDomain(
+                InvalidPartError::InvalidSyntax(Rule::Comment)
+            ))
+        );
+        assert_eq!(
+            Address::try_from("john@[192.0.2.1"),
+            Err(ParseAddressError::InvalidDomain(
+                InvalidPartError::InvalidSyntax(Rule::DomainLiteral)
+            ))
+        );
+        assert_eq!(
+            Address::try_from("john@[300.0.2.1]"),
+            Err(ParseAddressError::InvalidDomain(
+                InvalidPartError::InvalidSyntax(Rule::DomainLiteral)
+            ))
+        );
+        assert_eq!(
+            Address::try_from("john<doe@example.com"),
+            Err(ParseAddressError::InvalidUser(
+                InvalidPartError::ContainsForbiddenCharacter { ch: '<', at: 4 }
+            ))
+        );
+    }
+
+    #[test]
+    fn it_validates_with_options() {
+        let html5 = ValidationOptions::html5();
+        assert!(Address::parse_with(".john..doe@example", html5).is_ok());
+        assert_eq!(
+            Address::parse_with(r#""john"@example.com"#, html5),
+            Err(ParseAddressError::InvalidUser(
+                InvalidPartError::ContainsForbiddenCharacter { ch: '"', at: 0 }
+            ))
+        );
+        assert_eq!(
+            Address::parse_with("john@-example.com", html5),
+            Err(ParseAddressError::InvalidDomain(
+                InvalidPartError::InvalidSyntax(Rule::Hostname)
+            ))
+        );
+
+        let smtp = ValidationOptions::smtp();
+        assert!(Address::parse_with(r#""john doe"@[IPv6:2001:db8::1]"#, smtp).is_ok());
+        assert_eq!(
+            Address::parse_with("john@ex_ample.com", smtp),
+            Err(ParseAddressError::InvalidDomain(
+                InvalidPartError::ContainsForbiddenCharacter { ch: '_', at: 7 }
+            ))
+        );
+        assert_eq!(
+            Address::parse_with(" john@example.com", smtp),
+            Err(ParseAddressError::InvalidUser(
+                InvalidPartError::ContainsForbiddenCharacter { ch: ' ', at: 0 }
+            ))
+        );
+        assert!(Address::try_from("john@ex_ample.com").is_ok());
+    }
+
+    #[test]
+    fn it_enforces_length_limits() {
+        let user = "u".repeat(64);
+        let label = "d".repeat(63);
+        let domain = [&*label, &*label, &*label].join(".");
+
+        assert!(Address::try_new(&user, "example.com").is_ok());
+        assert_eq!(
+            Address::try_new(&"u".repeat(65), "example.com"),
+            Err(ParseAddressError::UserTooLong)
+        );
+        assert_eq!(
+            Address::try_new("user", &[&*domain, &*domain].join(".")),
+            Err(ParseAddressError::DomainTooLong)
+        );
+
+        let address = [&*user, "@", &*domain].concat();
+        assert_eq!(address.len(), 256);
+        assert_eq!(
+            Address::try_from(&*address),
+            Err(ParseAddressError::TooLong)
+        );
+        assert!(Address::try_from(&address[2..]).is_ok());
+    }
+
+    #[test]
+    fn it_parses_international_addresses_only_when_asked() {
+        assert_eq!(
+            Address::try_from("jöran@bücher.de"),
+            Err(ParseAddressError::InvalidUser(
+                InvalidPartError::ContainsNonAsciiCharacter { ch: 'ö', at: 1 }
+            ))
+        );
+
+        let address = Address::parse_international("\"jöran b\"@bücher.de").unwrap();
+        assert!(address.requires_smtputf8());
+        assert!(!Address::try_from("user@domain.com")
+            .unwrap()
+            .requires_smtputf8());
+        assert!(Address::try_new_international("jöran", "bücher..de").is_err());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn it_displays_errors_with_their_source() {
+        use alloc::string::ToString;
+        use core::error::Error;
+
+        let error = Address::try_from("john<doe@example.com").unwrap_err();
+        assert_eq!(error.to_string(), "invalid user");
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            "contains forbidden character '<' at offset 4"
+        );
+
+        let error = Address::try_from("john@[192.0.2.1").unwrap_err();
+        assert_eq!(
+            error.source().unwrap().to_string(),
+            "is not a valid domain literal"
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn it_converts_between_borrowed_and_owned_addresses() {
+        use super::OwnedAddress;
+        use alloc::string::String;
+
+        let input = String::from("user@domain.com");
+        let owned = Address::try_from(input.as_str()).unwrap().into_owned();
+        drop(input);
+
+        assert_eq!(
+            owned.as_address(),
+            Address::try_new("user", "domain.com").unwrap()
+        );
+        assert_eq!(OwnedAddress::from(owned.as_address()), owned);
+        assert!(OwnedAddress::try_from("name").is_err());
+        assert_eq!("user@domain.com".parse::<OwnedAddress>(), Ok(owned));
+        assert_eq!(
+            "name".parse::<OwnedAddress>(),
+            Err(ParseAddressError::MissingUserOrDomain)
+        );
+    }
 }
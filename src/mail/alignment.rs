@@ -0,0 +1,188 @@
+use alloc::vec::Vec;
+
+use super::{
+    header::{From, ReturnPath},
+    Header, HeaderMap,
+};
+
+/// How closely a domain has to match the `From` domain to be aligned with it, as in DMARC
+/// (RFC 7489).
+#[derive(Debug, Clone, Copy)]
+pub enum Mode {
+    /// The domains must be equal, ignoring case.
+    Strict,
+    /// The domains must have the same organizational domain, ignoring case, so
+    /// `mail.example.com` aligns with `example.com`. The organizational domain is the public
+    /// suffix plus one label, and is returned by the given function, since it needs the Public
+    /// Suffix List, which brief doesn't include.
+    Relaxed(fn(&str) -> &str),
+}
+
+impl Mode {
+    /// Returns whether the two domains are aligned.
+    pub fn aligns(&self, a: &str, b: &str) -> bool {
+        match self {
+            Self::Strict => a.eq_ignore_ascii_case(b),
+            Self::Relaxed(organizational) => {
+                organizational(a).eq_ignore_ascii_case(organizational(b))
+            }
+        }
+    }
+}
+
+/// Whether a domain that authenticates a mail is aligned with its `From` domain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment<'a> {
+    Aligned(&'a str),
+    Misaligned(&'a str),
+    /// The mail doesn't have the domain, like a mail without a `Return-Path` header.
+    Missing,
+}
+
+impl Alignment<'_> {
+    pub fn is_aligned(&self) -> bool {
+        matches!(self, Self::Aligned(_))
+    }
+}
+
+/// The alignment of the domains of a mail. Created by [`check`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Verdict<'a> {
+    /// The domain of the `From` header, or `None` if it is missing or has more than one mailbox,
+    /// in which case nothing is aligned.
+    pub from: Option<&'a str>,
+    /// The alignment of the `Return-Path` domain, which SPF verifies.
+    pub return_path: Alignment<'a>,
+    /// The alignment of the `d=` domain of every `DKIM-Signature` header that has one, in order.
+    pub dkim: Vec<Alignment<'a>>,
+}
+
+impl Verdict<'_> {
+    /// Returns whether any domain is aligned. DMARC passes a mail if an aligned domain was also
+    /// verified by SPF or DKIM, which is up to the caller to check.
+    pub fn is_aligned(&self) -> bool {
+        self.return_path.is_aligned() || self.dkim.iter().any(Alignment::is_aligned)
+    }
+}
+
+/// Checks whether the `Return-Path` domain and the `d=` domains of the `DKIM-Signature`
+/// headers of a mail are aligned with its `From` domain.
+///
+/// ```
+/// use brief::mail::{
+///     alignment::{self, Alignment, Mode},
+///     Header, HeaderMap,
+/// };
+///
+/// let headers = HeaderMap::from_iter([
+///     Header::ReturnPath("bounce@mail.example.com".try_into().unwrap()),
+///     Header::Custom("DKIM-Signature", "v=1; a=rsa-sha256; d=example.com; s=2024"),
+///     Header::From("Alice <alice@example.com>".try_into().unwrap()),
+/// ]);
+///
+/// let verdict = alignment::check(&headers, Mode::Strict);
+/// assert_eq!(verdict.from, Some("example.com"));
+/// assert_eq!(verdict.return_path, Alignment::Misaligned("mail.example.com"));
+/// assert_eq!(verdict.dkim, [Alignment::Aligned("example.com")]);
+/// assert!(verdict.is_aligned());
+///
+/// // a naive organizational domain: the last two labels
+/// let relaxed = Mode::Relaxed(|domain| {
+///     let mut dots = domain.rmatch_indices('.').map(|(i, _)| i);
+///     dots.nth(1).map_or(domain, |i| &domain[i + 1..])
+/// });
+/// let verdict = alignment::check(&headers, relaxed);
+/// assert_eq!(verdict.return_path, Alignment::Aligned("mail.example.com"));
+/// ```
+pub fn check<'a>(headers: &HeaderMap<'a>, mode: Mode) -> Verdict<'a> {
+    let from = headers.typed::<From>().and_then(|list| {
+        let mut mailboxes = list.iter();
+        match (mailboxes.next(), mailboxes.next()) {
+            (Some(mailbox), None) => Some(mailbox.address.domain()),
+            _ => None,
+        }
+    });
+
+    let align = |domain: Option<&'a str>| match (from, domain) {
+        (_, None) => Alignment::Missing,
+        (Some(from), Some(domain)) if mode.aligns(from, domain) => Alignment::Aligned(domain),
+        (_, Some(domain)) => Alignment::Misaligned(domain),
+    };
+
+    Verdict {
+        from,
+        return_path: align(
+            headers
+                .typed::<ReturnPath>()
+                .map(|address| address.domain()),
+        ),
+        dkim: headers
+            .get_all("DKIM-Signature")
+            .filter_map(|header| match header {
+                Header::Custom(_, value) => signing_domain(value),
+                _ => None,
+            })
+            .map(|domain| align(Some(domain)))
+            .collect(),
+    }
+}
+
+/// Returns the value of the `d=` tag of a `DKIM-Signature` header, the signing domain.
+fn signing_domain(value: &str) -> Option<&str> {
+    value.split(';').find_map(|tag| {
+        let (name, value) = tag.split_once('=')?;
+        match name.trim() {
+            "d" => Some(value.trim()),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check, signing_domain, Alignment, Mode};
+    use crate::mail::{Header, HeaderMap};
+
+    #[test]
+    fn it_finds_the_signing_domain() {
+        assert_eq!(
+            signing_domain("v=1; a=rsa-sha256;\r\n d=Example.com ; s=x"),
+            Some("Example.com")
+        );
+        assert_eq!(signing_domain("v=1; dd=example.com"), None);
+    }
+
+    #[test]
+    fn it_aligns_nothing_without_a_single_from_mailbox() {
+        let headers = HeaderMap::from_iter([
+            Header::From("a@example.com, b@example.com".try_into().unwrap()),
+            Header::ReturnPath("bounce@example.com".try_into().unwrap()),
+        ]);
+
+        let verdict = check(&headers, Mode::Strict);
+        assert_eq!(verdict.from, None);
+        assert_eq!(verdict.return_path, Alignment::Misaligned("example.com"));
+        assert!(verdict.dkim.is_empty());
+        assert!(!verdict.is_aligned());
+    }
+
+    #[test]
+    fn it_ignores_the_case_of_domains() {
+        let headers = HeaderMap::from_iter([
+            Header::From("a@EXAMPLE.com".try_into().unwrap()),
+            Header::Custom("DKIM-Signature", "d=example.COM"),
+            Header::Custom("DKIM-Signature", "d=other.com"),
+            Header::Custom("DKIM-Signature", "v=1"),
+        ]);
+
+        let verdict = check(&headers, Mode::Strict);
+        assert_eq!(verdict.return_path, Alignment::Missing);
+        assert_eq!(
+            verdict.dkim,
+            [
+                Alignment::Aligned("example.COM"),
+                Alignment::Misaligned("other.com")
+            ]
+        );
+    }
+}
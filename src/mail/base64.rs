@@ -0,0 +1,81 @@
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Writes bytes encoded as base64, with padding.
+pub(crate) fn encode(bytes: &[u8], f: &mut impl fmt::Write) -> fmt::Result {
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | u32::from(*b) << (16 - 8 * i));
+
+        for i in 0..4 {
+            let c = match i <= chunk.len() {
+                true => ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize],
+                false => b'=',
+            };
+            f.write_char(char::from(c))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes base64, with or without padding. Returns `None` if the input is not base64.
+#[cfg(feature = "alloc")]
+pub(crate) fn decode(text: &[u8]) -> Option<Vec<u8>> {
+    let text = match text.iter().position(|b| *b == b'=') {
+        Some(i) if text[i..].iter().all(|b| *b == b'=') && text.len() % 4 == 0 => &text[..i],
+        Some(_) => return None,
+        None => text,
+    };
+    if text.len() % 4 == 1 {
+        return None;
+    }
+
+    let mut bytes = Vec::with_capacity(text.len() / 4 * 3 + 2);
+    for chunk in text.chunks(4) {
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = ALPHABET.iter().position(|a| a == c)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+
+        let len = chunk.len() - 1;
+        bytes.extend_from_slice(&n.to_be_bytes()[1..1 + len]);
+    }
+
+    Some(bytes)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::{decode, encode};
+    use alloc::string::String;
+
+    #[test]
+    fn it_encodes_and_decodes_base64() {
+        for (bytes, encoded) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"\xff\xfe", "//4="),
+        ] {
+            let mut out = String::new();
+            encode(bytes, &mut out).unwrap();
+
+            assert_eq!(out, encoded);
+            assert_eq!(decode(encoded.as_bytes()).unwrap(), bytes);
+        }
+
+        assert_eq!(decode(b"Zm8").unwrap(), b"fo");
+        assert!(decode(b"Zm9v!").is_none());
+        assert!(decode(b"Zg=v").is_none());
+    }
+}
@@ -0,0 +1,196 @@
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+#[cfg(feature = "alloc")]
+use super::html;
+
+/// LEFT-TO-RIGHT ISOLATE
+const LRI: char = '\u{2066}';
+/// RIGHT-TO-LEFT ISOLATE
+const RLI: char = '\u{2067}';
+/// FIRST STRONG ISOLATE
+const FSI: char = '\u{2068}';
+/// POP DIRECTIONAL ISOLATE
+const PDI: char = '\u{2069}';
+
+/// The direction of a piece of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Left-to-right, like English.
+    Ltr,
+    /// Right-to-left, like Arabic or Hebrew.
+    Rtl,
+    /// Determined by the first strongly directional character.
+    Auto,
+}
+
+impl Direction {
+    /// Returns the value of the HTML `dir` attribute for this direction.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Ltr => "ltr",
+            Direction::Rtl => "rtl",
+            Direction::Auto => "auto",
+        }
+    }
+}
+
+/// Text wrapped in Unicode isolate characters. Created by [`isolate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Isolated<'a> {
+    text: &'a str,
+    direction: Direction,
+}
+
+impl fmt::Display for Isolated<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let start = match self.direction {
+            Direction::Ltr => LRI,
+            Direction::Rtl => RLI,
+            Direction::Auto => FSI,
+        };
+
+        write!(f, "{}{}{}", start, self.text, PDI)
+    }
+}
+
+/// Wraps text in Unicode isolate characters, so a right-to-left name in a left-to-right
+/// sentence (or the other way around) can't reorder the text around it. Use this for plain
+/// text, and [`isolate_html`] for HTML bodies.
+///
+/// ```
+/// use brief::mail::bidi::{self, Direction};
+///
+/// let greeting = format!("Hello {}, welcome!", bidi::isolate("שלום", Direction::Auto));
+/// assert_eq!(greeting, "Hello \u{2068}שלום\u{2069}, welcome!");
+/// ```
+pub fn isolate(text: &str, direction: Direction) -> Isolated<'_> {
+    Isolated { text, direction }
+}
+
+/// Text wrapped in a `bdi` element. Created by [`isolate_html`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IsolatedHtml<'a> {
+    html: &'a str,
+    direction: Direction,
+}
+
+impl fmt::Display for IsolatedHtml<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            r#"<bdi dir="{}">{}</bdi>"#,
+            self.direction.as_str(),
+            self.html
+        )
+    }
+}
+
+/// Wraps an HTML fragment in a `bdi` element with a `dir` attribute, the HTML counterpart of
+/// [`isolate`]. The fragment is not escaped.
+///
+/// ```
+/// use brief::mail::bidi::{self, Direction};
+///
+/// let name = bidi::isolate_html("مريم", Direction::Rtl);
+/// assert_eq!(format!("<p>Hi {},</p>", name), r#"<p>Hi <bdi dir="rtl">مريم</bdi>,</p>"#);
+/// ```
+pub fn isolate_html(html: &str, direction: Direction) -> IsolatedHtml<'_> {
+    IsolatedHtml { html, direction }
+}
+
+/// Sets the base direction of an HTML body by adding or replacing the `dir` attribute of its
+/// `html` element, or of its `body` element when there is no `html` element. Bodies without
+/// either are wrapped in a `div`.
+///
+/// ```
+/// use brief::mail::bidi::{self, Direction};
+///
+/// let html = bidi::set_direction(r#"<html dir="ltr"><body>مرحبا</body></html>"#, Direction::Rtl);
+/// assert_eq!(html, r#"<html dir="rtl"><body>مرحبا</body></html>"#);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn set_direction(html: &str, direction: Direction) -> String {
+    let tags = || html::tags(html).filter(|t| !t.closing);
+    let Some(tag) = tags()
+        .find(|t| t.is("html"))
+        .or_else(|| tags().find(|t| t.is("body")))
+    else {
+        return alloc::format!(r#"<div dir="{}">{}</div>"#, direction.as_str(), html);
+    };
+
+    let mut out = String::with_capacity(html.len() + 12);
+    match tag.attribute("dir") {
+        Some(dir) if dir.quote.is_some() => {
+            out.push_str(&html[..dir.span.start]);
+            out.push_str(direction.as_str());
+            out.push_str(&html[dir.span.end..]);
+        }
+        Some(dir) => {
+            out.push_str(&html[..dir.span.start]);
+            if !html[..dir.span.start].trim_end().ends_with('=') {
+                out.push('=');
+            }
+            out.push('"');
+            out.push_str(direction.as_str());
+            out.push('"');
+            out.push_str(&html[dir.span.end..]);
+        }
+        None => {
+            let at = tag.span.start + 1 + tag.name.len();
+            out.push_str(&html[..at]);
+            out.push_str(r#" dir=""#);
+            out.push_str(direction.as_str());
+            out.push('"');
+            out.push_str(&html[at..]);
+        }
+    }
+
+    out
+}
+
+/// Returns whether a character is a bidi isolate initiator.
+#[cfg(feature = "alloc")]
+pub(crate) fn is_isolate_initiator(c: char) -> bool {
+    matches!(c, LRI | RLI | FSI)
+}
+
+/// Returns whether a character ends a bidi isolate.
+#[cfg(feature = "alloc")]
+pub(crate) fn is_isolate_terminator(c: char) -> bool {
+    c == PDI
+}
+
+/// Returns whether a character starts or ends a bidi embedding or override. These are never
+/// needed in text written today and are the usual tool for spoofing text.
+#[cfg(feature = "alloc")]
+pub(crate) fn is_embedding_or_override(c: char) -> bool {
+    matches!(c, '\u{202a}'..='\u{202e}')
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::{set_direction, Direction};
+
+    #[test]
+    fn it_sets_the_direction_of_the_root_element() {
+        assert_eq!(
+            set_direction("<HTML lang=ar><body></body></HTML>", Direction::Rtl),
+            r#"<HTML dir="rtl" lang=ar><body></body></HTML>"#
+        );
+        assert_eq!(
+            set_direction("<body dir=rtl>x</body>", Direction::Auto),
+            r#"<body dir="auto">x</body>"#
+        );
+        assert_eq!(
+            set_direction("<body dir>x</body>", Direction::Rtl),
+            r#"<body dir="rtl">x</body>"#
+        );
+        assert_eq!(
+            set_direction("<p>x</p>", Direction::Ltr),
+            r#"<div dir="ltr"><p>x</p></div>"#
+        );
+    }
+}
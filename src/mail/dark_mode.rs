@@ -0,0 +1,117 @@
+use alloc::string::String;
+
+use super::html;
+
+/// Tells clients the message supports both light and dark mode.
+const META: &str = concat!(
+    r#"<meta name="color-scheme" content="light dark">"#,
+    r#"<meta name="supported-color-schemes" content="light dark">"#
+);
+
+/// Opts the message into dark mode and leaves a place for dark mode overrides.
+const STYLE: &str = "<style>
+:root { color-scheme: light dark; supported-color-schemes: light dark; }
+@media (prefers-color-scheme: dark) {
+  /* dark mode overrides */
+}
+</style>";
+
+/// Prepares an HTML body for dark mode by adding the `color-scheme` meta tags and a
+/// `prefers-color-scheme` media query to fill in with dark mode overrides.
+///
+/// Both are added to the `head`, which is created if there is none, and are left out if the
+/// body already has them. Use [`lint::dark_mode`](super::lint::dark_mode) to find backgrounds
+/// that will render badly.
+///
+/// ```
+/// use brief::mail::dark_mode;
+///
+/// let html = dark_mode::prepare("<html><body>Hi</body></html>");
+///
+/// assert!(html.starts_with(r#"<html><head><meta name="color-scheme" content="light dark">"#));
+/// assert!(html.contains("@media (prefers-color-scheme: dark)"));
+/// ```
+pub fn prepare(html: &str) -> String {
+    let mut head = None;
+    let mut root = None;
+    let mut has_meta = false;
+
+    for tag in html::tags(html).filter(|t| !t.closing) {
+        if tag.is("head") && head.is_none() {
+            head = Some(tag.span.end);
+        } else if tag.is("html") && root.is_none() {
+            root = Some(tag.span.end);
+        } else if tag.is("meta") {
+            has_meta |= tag
+                .attribute("name")
+                .is_some_and(|n| n.value.eq_ignore_ascii_case("color-scheme"));
+        }
+    }
+
+    let has_style = html.contains("prefers-color-scheme");
+
+    let mut insert = String::new();
+    if !has_meta {
+        insert.push_str(META);
+    }
+    if !has_style {
+        insert.push_str(STYLE);
+    }
+    if insert.is_empty() {
+        return html.into();
+    }
+
+    let (at, wrap) = match (head, root) {
+        (Some(at), _) => (at, false),
+        (None, Some(at)) => (at, true),
+        // the head can't come before the doctype
+        (None, None) => (html::prolog_end(html), true),
+    };
+
+    let mut out = String::with_capacity(html.len() + insert.len() + 13);
+    out.push_str(&html[..at]);
+    if wrap {
+        out.push_str("<head>");
+    }
+    out.push_str(&insert);
+    if wrap {
+        out.push_str("</head>");
+    }
+    out.push_str(&html[at..]);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prepare, META, STYLE};
+    use alloc::format;
+
+    #[test]
+    fn it_adds_meta_tags_and_style_to_an_existing_head() {
+        let html = "<html><head><title>t</title></head><body></body></html>";
+
+        assert_eq!(
+            prepare(html),
+            format!("<html><head>{META}{STYLE}<title>t</title></head><body></body></html>")
+        );
+    }
+
+    #[test]
+    fn it_creates_a_head_when_missing() {
+        assert_eq!(
+            prepare("<p>hi</p>"),
+            format!("<head>{META}{STYLE}</head><p>hi</p>")
+        );
+        assert_eq!(
+            prepare("<!DOCTYPE html>\n<!-- x --><p>hi</p>"),
+            format!("<!DOCTYPE html>\n<!-- x --><head>{META}{STYLE}</head><p>hi</p>")
+        );
+    }
+
+    #[test]
+    fn it_leaves_existing_dark_mode_support_alone() {
+        let html = format!("<html><head>{META}{STYLE}</head></html>");
+        assert_eq!(prepare(&html), html);
+    }
+}
@@ -0,0 +1,176 @@
+use core::fmt;
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// The largest offset that fits the four digits of a zone, `+9959`.
+const MAX_OFFSET: i16 = 99 * 60 + 59;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutOfRangeError;
+
+impl fmt::Display for OutOfRangeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("date is outside the years 1900 to 9999 or has an invalid offset")
+    }
+}
+
+impl core::error::Error for OutOfRangeError {}
+
+/// Represents the date and time of a mail, as written in its `Date` header
+///
+/// A date is a point in time with the offset of the time zone it was written in, and is
+/// displayed as specified by RFC 5322:
+/// ```
+/// use brief::mail::DateTime;
+///
+/// let date = DateTime::from_timestamp(1057049557, 120).unwrap();
+/// assert_eq!(date.to_string(), "Tue, 1 Jul 2003 10:52:37 +0200");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DateTime {
+    timestamp: i64,
+    offset: i16,
+}
+
+impl DateTime {
+    /// Creates a date from the seconds since the Unix epoch and the offset of the time zone in
+    /// minutes east of UTC. Fails if the local year is before 1900 or after 9999, or if the
+    /// offset is more than 99 hours and 59 minutes.
+    pub fn from_timestamp(timestamp: i64, offset: i16) -> Result<Self, OutOfRangeError> {
+        if !(-MAX_OFFSET..=MAX_OFFSET).contains(&offset) {
+            return Err(OutOfRangeError);
+        }
+
+        let date = Self { timestamp, offset };
+        let (year, _, _) = civil(date.local_days().ok_or(OutOfRangeError)?);
+        match (1900..=9999).contains(&year) {
+            true => Ok(date),
+            false => Err(OutOfRangeError),
+        }
+    }
+
+    /// Returns the current date in UTC.
+    #[cfg(feature = "std")]
+    pub fn now() -> Self {
+        std::time::SystemTime::now()
+            .try_into()
+            .expect("system clock is between the years 1900 and 9999")
+    }
+
+    /// Returns the seconds since the Unix epoch.
+    pub fn timestamp(&self) -> i64 {
+        self.timestamp
+    }
+
+    /// Returns the offset of the time zone in minutes east of UTC.
+    pub fn offset(&self) -> i16 {
+        self.offset
+    }
+
+    /// Returns the days since the Unix epoch in the time zone of the date.
+    fn local_days(&self) -> Option<i64> {
+        let local = self.timestamp.checked_add(i64::from(self.offset) * 60)?;
+        Some(local.div_euclid(86_400))
+    }
+}
+
+/// Converts a system time into a date in UTC.
+#[cfg(feature = "std")]
+impl TryFrom<std::time::SystemTime> for DateTime {
+    type Error = OutOfRangeError;
+
+    fn try_from(value: std::time::SystemTime) -> Result<Self, Self::Error> {
+        let timestamp = match value.duration_since(std::time::UNIX_EPOCH) {
+            Ok(since) => i64::try_from(since.as_secs()),
+            Err(error) => i64::try_from(error.duration().as_secs()).map(|s| -s),
+        };
+
+        Self::from_timestamp(timestamp.map_err(|_| OutOfRangeError)?, 0)
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the year was checked when the date was created
+        let days = self.local_days().unwrap_or_default();
+        let (year, month, day) = civil(days);
+        let seconds = (self.timestamp + i64::from(self.offset) * 60).rem_euclid(86_400);
+        // the epoch was a Thursday
+        let weekday = WEEKDAYS[(days + 4).rem_euclid(7) as usize];
+
+        let sign = if self.offset < 0 { '-' } else { '+' };
+        let offset = self.offset.unsigned_abs();
+
+        write!(
+            f,
+            "{weekday}, {day} {} {year:04} {:02}:{:02}:{:02} {sign}{:02}{:02}",
+            MONTHS[month as usize - 1],
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60,
+            offset / 60,
+            offset % 60,
+        )
+    }
+}
+
+/// Returns the year, month and day of the given days since the Unix epoch, in the proleptic
+/// Gregorian calendar.
+fn civil(days: i64) -> (i64, u32, u32) {
+    // shift the epoch to 0000-03-01, so leap days are at the end of a year
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month as u32, day as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{civil, DateTime, OutOfRangeError};
+
+    #[test]
+    fn it_converts_days_to_dates() {
+        assert_eq!(civil(0), (1970, 1, 1));
+        assert_eq!(civil(-1), (1969, 12, 31));
+        assert_eq!(civil(11_016), (2000, 2, 29));
+        assert_eq!(civil(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn it_displays_dates_in_their_time_zone() {
+        let date = DateTime::from_timestamp(0, 0).unwrap();
+        assert_eq!(std::format!("{date}"), "Thu, 1 Jan 1970 00:00:00 +0000");
+
+        let date = DateTime::from_timestamp(0, -330).unwrap();
+        assert_eq!(std::format!("{date}"), "Wed, 31 Dec 1969 18:30:00 -0530");
+    }
+
+    #[test]
+    fn it_rejects_dates_out_of_range() {
+        assert_eq!(DateTime::from_timestamp(0, 6000), Err(OutOfRangeError));
+        assert_eq!(
+            DateTime::from_timestamp(-2_208_988_801, 0),
+            Err(OutOfRangeError)
+        );
+        assert!(DateTime::from_timestamp(-2_208_988_800, 0).is_ok());
+        assert_eq!(DateTime::from_timestamp(i64::MAX, 1), Err(OutOfRangeError));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_converts_system_times() {
+        let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(86_400);
+        assert_eq!(DateTime::try_from(time).unwrap().timestamp(), 86_400);
+    }
+}
@@ -0,0 +1,456 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use super::{
+    header, html,
+    links::{self, decode_entities},
+    Mail,
+};
+
+/// Inline elements, which don't break the text around them into separate blocks.
+const INLINE_ELEMENTS: [&str; 15] = [
+    "a", "abbr", "b", "big", "code", "em", "font", "i", "img", "small", "span", "strong", "sub",
+    "sup", "u",
+];
+
+/// A single step in an edit script produced by [`edits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Edit {
+    Keep(usize, usize),
+    Remove(usize),
+    Add(usize),
+}
+
+/// The most cells of the table [`edits`] allocates, about 32 MiB on 64-bit targets.
+const MAX_TABLE_CELLS: usize = 1 << 22;
+
+/// Computes the shortest edit script turning `old` into `new`, using the longest common
+/// subsequence of both. The common prefix and suffix are kept as they are, and if what remains
+/// is too large to compare, it is removed and added as a whole instead.
+pub(crate) fn edits<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Edit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (n, m) = (old.len() - prefix - suffix, new.len() - prefix - suffix);
+
+    let mut script = Vec::with_capacity(old.len().max(new.len()));
+    script.extend((0..prefix).map(|i| Edit::Keep(i, i)));
+
+    let cells = (n + 1)
+        .checked_mul(m + 1)
+        .filter(|&cells| cells <= MAX_TABLE_CELLS);
+    match cells {
+        Some(cells) => {
+            let (old, new) = (&old[prefix..prefix + n], &new[prefix..prefix + m]);
+            let mut lcs = vec![0usize; cells];
+
+            for i in (0..n).rev() {
+                for j in (0..m).rev() {
+                    lcs[i * (m + 1) + j] = if old[i] == new[j] {
+                        lcs[(i + 1) * (m + 1) + j + 1] + 1
+                    } else {
+                        lcs[(i + 1) * (m + 1) + j].max(lcs[i * (m + 1) + j + 1])
+                    };
+                }
+            }
+
+            let (mut i, mut j) = (0, 0);
+            while i < n || j < m {
+                if i < n && j < m && old[i] == new[j] {
+                    script.push(Edit::Keep(prefix + i, prefix + j));
+                    i += 1;
+                    j += 1;
+                } else if j == m
+                    || (i < n && lcs[(i + 1) * (m + 1) + j] >= lcs[i * (m + 1) + j + 1])
+                {
+                    script.push(Edit::Remove(prefix + i));
+                    i += 1;
+                } else {
+                    script.push(Edit::Add(prefix + j));
+                    j += 1;
+                }
+            }
+        }
+        None => {
+            script.extend((prefix..prefix + n).map(Edit::Remove));
+            script.extend((prefix..prefix + m).map(Edit::Add));
+        }
+    }
+
+    script.extend((0..suffix).map(|k| Edit::Keep(prefix + n + k, prefix + m + k)));
+    script
+}
+
+/// The differences between two HTML bodies. Created by [`html`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HtmlDiff {
+    /// Links that are only in the old body.
+    pub removed_links: Vec<String>,
+    /// Links that are only in the new body.
+    pub added_links: Vec<String>,
+    /// Blocks of text that are only in the old body.
+    pub removed_text: Vec<String>,
+    /// Blocks of text that are only in the new body.
+    pub added_text: Vec<String>,
+}
+
+impl HtmlDiff {
+    /// Returns whether both bodies have the same links and text.
+    pub fn is_empty(&self) -> bool {
+        self.removed_links.is_empty()
+            && self.added_links.is_empty()
+            && self.removed_text.is_empty()
+            && self.added_text.is_empty()
+    }
+}
+
+/// Compares the links and visible text of two HTML bodies, for example an original newsletter
+/// and its corrected version. Markup that doesn't change the links or text is ignored.
+///
+/// ```
+/// use brief::mail::diff;
+///
+/// let old = r#"<p>Sale ends <b>Friday</b>!</p><a href="https://shop.example/old">Shop</a>"#;
+/// let new = r#"<p>Sale ends <i>Sunday</i>!</p><a href="https://shop.example/new">Shop</a>"#;
+/// let diff = diff::html(old, new);
+///
+/// assert_eq!(diff.removed_text, ["Sale ends Friday!"]);
+/// assert_eq!(diff.added_text, ["Sale ends Sunday!"]);
+/// assert_eq!(diff.removed_links, ["https://shop.example/old"]);
+/// assert_eq!(diff.added_links, ["https://shop.example/new"]);
+/// ```
+pub fn html(old: &str, new: &str) -> HtmlDiff {
+    let mut diff = HtmlDiff::default();
+
+    let old_links: Vec<_> = links::html_links(old)
+        .map(|l| decode_entities(l.as_str().trim()))
+        .collect();
+    let new_links: Vec<_> = links::html_links(new)
+        .map(|l| decode_entities(l.as_str().trim()))
+        .collect();
+    for edit in edits(&old_links, &new_links) {
+        match edit {
+            Edit::Keep(..) => {}
+            Edit::Remove(i) => diff.removed_links.push(old_links[i].clone()),
+            Edit::Add(j) => diff.added_links.push(new_links[j].clone()),
+        }
+    }
+
+    let old_text = text_blocks(old);
+    let new_text = text_blocks(new);
+    for edit in edits(&old_text, &new_text) {
+        match edit {
+            Edit::Keep(..) => {}
+            Edit::Remove(i) => diff.removed_text.push(old_text[i].clone()),
+            Edit::Add(j) => diff.added_text.push(new_text[j].clone()),
+        }
+    }
+
+    diff
+}
+
+/// A header whose value differs between two mails. Created by [`Mail::diff`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangedHeader {
+    pub name: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// The difference between the bodies of two mails. Created by [`Mail::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum BodyDiff {
+    /// Both bodies are the same.
+    #[default]
+    Same,
+    /// The lines that are only in the old or only in the new body, if both are text.
+    Text {
+        removed_lines: Vec<String>,
+        added_lines: Vec<String>,
+    },
+    /// The FNV-1a hashes of the bodies, if either is binary.
+    Binary { old_hash: u64, new_hash: u64 },
+}
+
+/// The differences between two mails. Created by [`Mail::diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MailDiff {
+    /// Headers that are only in the old mail, as `Name: value`.
+    pub removed_headers: Vec<String>,
+    /// Headers that are only in the new mail, as `Name: value`.
+    pub added_headers: Vec<String>,
+    /// Headers that are in both mails with different values.
+    pub changed_headers: Vec<ChangedHeader>,
+    pub body: BodyDiff,
+}
+
+impl MailDiff {
+    /// Returns whether both mails have the same headers and body.
+    pub fn is_empty(&self) -> bool {
+        self.removed_headers.is_empty()
+            && self.added_headers.is_empty()
+            && self.changed_headers.is_empty()
+            && self.body == BodyDiff::Same
+    }
+}
+
+/// Compares the headers and bodies of two mails. Headers with the same name are paired in the
+/// order they appear, ignoring case and folding.
+pub(crate) fn mail(old: &Mail<'_>, new: &Mail<'_>) -> MailDiff {
+    let mut diff = MailDiff::default();
+
+    let values = |mail: &Mail<'_>| -> Vec<(String, String)> {
+        mail.headers()
+            .iter()
+            .map(|h| {
+                let body = h.body().to_string();
+                (h.name().to_string(), header::unfold(&body).to_string())
+            })
+            .collect()
+    };
+    let old_headers = values(old);
+    let mut new_headers: Vec<_> = values(new).into_iter().map(Some).collect();
+
+    for (name, value) in old_headers {
+        let paired = new_headers
+            .iter_mut()
+            .find(|h| {
+                h.as_ref()
+                    .is_some_and(|(n, _)| n.eq_ignore_ascii_case(&name))
+            })
+            .and_then(Option::take);
+
+        match paired {
+            None => diff.removed_headers.push(name + ": " + &value),
+            Some((_, new)) if new == value => {}
+            Some((_, new)) => diff.changed_headers.push(ChangedHeader {
+                name,
+                old: value,
+                new,
+            }),
+        }
+    }
+    diff.added_headers = new_headers
+        .into_iter()
+        .flatten()
+        .map(|(name, value)| name + ": " + &value)
+        .collect();
+
+    diff.body = body(old.body(), new.body());
+    diff
+}
+
+fn body(old: &[u8], new: &[u8]) -> BodyDiff {
+    if old == new {
+        return BodyDiff::Same;
+    }
+
+    let (Ok(old), Ok(new)) = (core::str::from_utf8(old), core::str::from_utf8(new)) else {
+        return BodyDiff::Binary {
+            old_hash: fnv1a(old),
+            new_hash: fnv1a(new),
+        };
+    };
+
+    // line endings are normalized when the mail is serialized, so they don't count
+    let old_lines: Vec<_> = old.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let new_lines: Vec<_> = new.lines().map(|l| l.trim_end_matches('\r')).collect();
+
+    let (mut removed_lines, mut added_lines) = (Vec::new(), Vec::new());
+    for edit in edits(&old_lines, &new_lines) {
+        match edit {
+            Edit::Keep(..) => {}
+            Edit::Remove(i) => removed_lines.push(old_lines[i].to_string()),
+            Edit::Add(j) => added_lines.push(new_lines[j].to_string()),
+        }
+    }
+
+    match removed_lines.is_empty() && added_lines.is_empty() {
+        true => BodyDiff::Same,
+        false => BodyDiff::Text {
+            removed_lines,
+            added_lines,
+        },
+    }
+}
+
+/// Hashes bytes with 64-bit FNV-1a.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, b| {
+        (hash ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Extracts the visible text of an HTML body as blocks with collapsed whitespace, splitting at
+/// every element that isn't inline.
+fn text_blocks(html: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    let mut raw = String::new();
+    let mut last = 0;
+    let mut raw_text = false;
+
+    let mut flush = |raw: &mut String| {
+        let decoded = decode_entities(raw);
+        let block = decoded
+            .split(|c: char| c.is_whitespace())
+            .filter(|w| !w.is_empty())
+            .fold(String::new(), |mut block, word| {
+                if !block.is_empty() {
+                    block.push(' ');
+                }
+                block.push_str(word);
+                block
+            });
+
+        if !block.is_empty() {
+            blocks.push(block);
+        }
+        raw.clear();
+    };
+
+    for tag in html::tags(html) {
+        if !raw_text {
+            push_text(&mut raw, &html[last..tag.span.start]);
+        }
+        last = tag.span.end;
+        raw_text = !tag.closing && (tag.is("script") || tag.is("style"));
+
+        if !INLINE_ELEMENTS.iter().any(|e| tag.is(e)) {
+            flush(&mut raw);
+        }
+    }
+
+    if !raw_text {
+        push_text(&mut raw, &html[last..]);
+    }
+    flush(&mut raw);
+
+    blocks
+}
+
+/// Appends the text between two tags, leaving out comments and declarations.
+fn push_text(raw: &mut String, mut text: &str) {
+    while let Some(open) = text.find("<!") {
+        raw.push_str(&text[..open]);
+
+        let rest = &text[open..];
+        let end = match rest.starts_with("<!--") {
+            true => rest.find("-->").map_or(rest.len(), |i| i + 3),
+            false => rest.find('>').map_or(rest.len(), |i| i + 1),
+        };
+        text = &rest[end..];
+    }
+
+    raw.push_str(text);
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::{edits, fnv1a, html, text_blocks, BodyDiff, Edit};
+    use crate::mail::{Header, HeaderMap, Mail};
+
+    #[test]
+    fn it_computes_a_minimal_edit_script() {
+        let script = edits(&['a', 'b', 'c', 'd'], &['a', 'c', 'e', 'd']);
+
+        assert_eq!(
+            script,
+            [
+                Edit::Keep(0, 0),
+                Edit::Remove(1),
+                Edit::Keep(2, 1),
+                Edit::Add(2),
+                Edit::Keep(3, 3)
+            ]
+        );
+    }
+
+    #[test]
+    fn it_trims_common_lines_and_caps_the_table() {
+        let old: Vec<_> = (0..5000).collect();
+        let mut new: Vec<_> = (0..5000).map(|i| i + 10000).collect();
+        new[0] = 0;
+        new[4999] = 4999;
+
+        let script = edits(&old, &new);
+        assert_eq!(script.len(), 2 + 2 * 4998);
+        assert_eq!(script[0], Edit::Keep(0, 0));
+        assert_eq!(script[1], Edit::Remove(1));
+        assert_eq!(script[4999], Edit::Add(1));
+        assert_eq!(script.last(), Some(&Edit::Keep(4999, 4999)));
+
+        let script = edits(&['a', 'b', 'c'], &['a', 'c']);
+        assert_eq!(
+            script,
+            [Edit::Keep(0, 0), Edit::Remove(1), Edit::Keep(2, 1)]
+        );
+    }
+
+    #[test]
+    fn it_extracts_visible_text_blocks() {
+        let blocks = text_blocks(
+            "<html><style>p { color: red }</style><p>Hello <b>dear</b>\n  reader,</p><!-- x --><br>caf&eacute; &amp; more<script>let a = '<p>';</script></html>",
+        );
+
+        assert_eq!(blocks, ["Hello dear reader,", "caf&eacute; & more"]);
+    }
+
+    #[test]
+    fn it_pairs_headers_by_name_in_order() {
+        let old = HeaderMap::from_iter([
+            Header::Custom("X-Tag", "a"),
+            Header::Custom("X-Tag", "b"),
+            Header::Subject("Hi"),
+        ]);
+        let new = HeaderMap::from_iter([Header::Custom("x-tag", "a"), Header::MimeVersion]);
+        let diff = Mail::new(old, b"").diff(&Mail::new(new, b""));
+
+        assert_eq!(diff.removed_headers, ["X-Tag: b", "Subject: Hi"]);
+        assert_eq!(diff.added_headers, ["MIME-Version: 1.0"]);
+        assert!(diff.changed_headers.is_empty());
+    }
+
+    #[test]
+    fn it_diffs_text_and_binary_bodies() {
+        let old = Mail::new(HeaderMap::new(), b"a\nb\nc");
+        let diff = old.diff(&Mail::new(HeaderMap::new(), b"a\r\nx\r\nc"));
+        assert_eq!(
+            diff.body,
+            BodyDiff::Text {
+                removed_lines: ["b".into()].into(),
+                added_lines: ["x".into()].into(),
+            }
+        );
+
+        let diff = old.diff(&Mail::new(HeaderMap::new(), b"a\r\nb\r\nc"));
+        assert!(diff.is_empty());
+
+        let diff = old.diff(&Mail::new(HeaderMap::new(), b"\xff"));
+        assert_eq!(
+            diff.body,
+            BodyDiff::Binary {
+                old_hash: fnv1a(b"a\nb\nc"),
+                new_hash: fnv1a(b"\xff"),
+            }
+        );
+        assert_eq!(fnv1a(b""), 0xcbf29ce484222325);
+        assert_eq!(fnv1a(b"a"), 0xaf63dc4c8601ec8c);
+    }
+
+    #[test]
+    fn it_ignores_markup_only_changes() {
+        let old = r#"<p style="color: red">Hi <a href="https://x.com/?a=1&amp;b=2">there</a></p>"#;
+        let new = r#"<div>Hi <a class="btn" href='https://x.com/?a=1&b=2'>there</a></div>"#;
+
+        assert!(html(old, new).is_empty());
+    }
+}
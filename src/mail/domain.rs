@@ -0,0 +1,405 @@
+use core::{
+    fmt::{self, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use super::punycode::{self, MAX_DECODED_LEN};
+
+/// The maximum length of a domain in its ASCII form.
+const MAX_LEN: usize = 253;
+
+/// The maximum length of a label in its ASCII form.
+const MAX_LABEL_LEN: usize = 63;
+
+/// The prefix of labels encoded as Punycode.
+const ACE_PREFIX: &str = "xn--";
+
+/// The tag of IPv6 address literals.
+const IPV6_TAG: &str = "IPv6:";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseDomainError {
+    IsEmpty,
+    /// The domain is longer than 253 octets in its ASCII form.
+    TooLong,
+    /// The domain has an empty label, like `example..com`.
+    EmptyLabel,
+    /// A label is longer than 63 octets in its ASCII form.
+    LabelTooLong,
+    /// A label starts or ends with a hyphen, or has hyphens as its third and fourth character
+    /// without being Punycode.
+    InvalidHyphen,
+    /// A label contains a character that is neither a letter, a digit nor a hyphen.
+    InvalidCharacter(char),
+    /// A label starting with `xn--` is not valid Punycode.
+    InvalidPunycode,
+    /// An address literal in square brackets is neither an IPv4 address nor an IPv6 address
+    /// tagged with `IPv6:`.
+    InvalidLiteral,
+}
+
+impl fmt::Display for ParseDomainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IsEmpty => f.write_str("domain is empty"),
+            Self::TooLong => write!(f, "domain is longer than {MAX_LEN} octets"),
+            Self::EmptyLabel => f.write_str("domain has an empty label"),
+            Self::LabelTooLong => write!(f, "label is longer than {MAX_LABEL_LEN} octets"),
+            Self::InvalidHyphen => f.write_str("label has a hyphen in an invalid position"),
+            Self::InvalidCharacter(c) => write!(f, "label contains invalid character {c:?}"),
+            Self::InvalidPunycode => f.write_str("label is not valid punycode"),
+            Self::InvalidLiteral => f.write_str("address literal is not an IP address"),
+        }
+    }
+}
+
+impl core::error::Error for ParseDomainError {}
+
+/// Represents a domain name, which may be internationalized
+///
+/// A domain can be written with Unicode labels, like `bücher.de`, or with labels encoded as
+/// Punycode, like `xn--bcher-kva.de`, and converts between both forms:
+/// ```
+/// use brief::mail::Domain;
+///
+/// let domain = Domain::try_from("Bücher.de").unwrap();
+/// assert_eq!(domain.to_ascii().to_string(), "xn--bcher-kva.de");
+///
+/// let domain = Domain::try_from("xn--bcher-kva.de").unwrap();
+/// assert_eq!(domain.to_unicode().to_string(), "bücher.de");
+/// ```
+///
+/// Labels are validated following IDNA2008 as far as that is possible without the Unicode
+/// tables it depends on: they can contain letters, digits and hyphens, but can't start or end
+/// with a hyphen. Normalization is not checked.
+///
+/// A domain can also be an address literal, as RFC 5321 allows:
+/// ```
+/// use brief::mail::Domain;
+/// use std::net::{IpAddr, Ipv4Addr};
+///
+/// let domain = Domain::try_from("[192.0.2.1]").unwrap();
+/// assert_eq!(domain.ip_addr(), Some(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1))));
+///
+/// let domain = Domain::try_from("[IPv6:2001:db8::1]").unwrap();
+/// assert!(domain.ip_addr().unwrap().is_ipv6());
+///
+/// assert!(Domain::try_from("[2001:db8::1]").is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Domain<'a> {
+    name: &'a str,
+    literal: Option<IpAddr>,
+}
+
+impl<'a> Domain<'a> {
+    /// Returns the domain as it was written.
+    pub fn as_str(&self) -> &'a str {
+        self.name
+    }
+
+    /// Returns whether the domain is an address literal instead of a host name.
+    pub fn is_literal(&self) -> bool {
+        self.literal.is_some()
+    }
+
+    /// Returns the IP address of an address literal, or `None` if the domain is a host name.
+    pub fn ip_addr(&self) -> Option<IpAddr> {
+        self.literal
+    }
+
+    /// Returns whether the domain has Unicode or Punycode labels.
+    pub fn is_internationalized(&self) -> bool {
+        !self.is_literal() && self.labels().any(|l| !l.is_ascii() || is_punycode(l))
+    }
+
+    /// Returns the domain with every Unicode label encoded as Punycode and lowercased, which is
+    /// how it is sent over SMTP without the SMTPUTF8 extension.
+    pub fn to_ascii(&self) -> ToAscii<'a> {
+        ToAscii { domain: *self }
+    }
+
+    /// Returns the domain with every Punycode label decoded.
+    pub fn to_unicode(&self) -> ToUnicode<'a> {
+        ToUnicode { domain: *self }
+    }
+
+    fn labels(&self) -> core::str::Split<'a, char> {
+        self.name.split('.')
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Domain<'a> {
+    type Error = ParseDomainError;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        if value.is_empty() {
+            return Err(ParseDomainError::IsEmpty);
+        }
+
+        if let Some(literal) = value.strip_prefix('[') {
+            let literal = literal
+                .strip_suffix(']')
+                .and_then(parse_literal)
+                .ok_or(ParseDomainError::InvalidLiteral)?;
+
+            return Ok(Domain {
+                name: value,
+                literal: Some(literal),
+            });
+        }
+
+        let domain = Domain {
+            name: value,
+            literal: None,
+        };
+        for label in domain.labels() {
+            validate_label(label)?;
+        }
+
+        let mut counter = Counter(0);
+        let _ = write!(counter, "{}", domain.to_ascii());
+        if counter.0 > MAX_LEN {
+            return Err(ParseDomainError::TooLong);
+        }
+
+        Ok(domain)
+    }
+}
+
+impl fmt::Display for Domain<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
+
+/// A domain in its ASCII form. Created by [`Domain::to_ascii`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToAscii<'a> {
+    domain: Domain<'a>,
+}
+
+impl fmt::Display for ToAscii<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, label) in self.domain.labels().enumerate() {
+            if i > 0 {
+                f.write_char('.')?;
+            }
+
+            if label.is_ascii() {
+                f.write_str(label)?;
+            } else {
+                f.write_str(ACE_PREFIX)?;
+                punycode::encode(|| label.chars().flat_map(char::to_lowercase), f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A domain in its Unicode form. Created by [`Domain::to_unicode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToUnicode<'a> {
+    domain: Domain<'a>,
+}
+
+impl fmt::Display for ToUnicode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, label) in self.domain.labels().enumerate() {
+            if i > 0 {
+                f.write_char('.')?;
+            }
+
+            if !is_punycode(label) {
+                f.write_str(label)?;
+                continue;
+            }
+
+            // punycode labels were decoded when the domain was created, so this can't fail
+            let mut decoded = ['\0'; MAX_DECODED_LEN];
+            let len = punycode::decode(&label[ACE_PREFIX.len()..], &mut decoded).unwrap_or(0);
+            for c in &decoded[..len] {
+                f.write_char(*c)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the text of an address literal, between the brackets.
+fn parse_literal(text: &str) -> Option<IpAddr> {
+    match text.get(..IPV6_TAG.len()) {
+        Some(tag) if tag.eq_ignore_ascii_case(IPV6_TAG) => text[IPV6_TAG.len()..]
+            .parse::<Ipv6Addr>()
+            .ok()
+            .map(IpAddr::V6),
+        _ => text.parse::<Ipv4Addr>().ok().map(IpAddr::V4),
+    }
+}
+
+fn is_punycode(label: &str) -> bool {
+    label
+        .get(..ACE_PREFIX.len())
+        .is_some_and(|p| p.eq_ignore_ascii_case(ACE_PREFIX))
+}
+
+fn validate_label(label: &str) -> Result<(), ParseDomainError> {
+    if label.is_empty() {
+        return Err(ParseDomainError::EmptyLabel);
+    }
+    if label.starts_with('-') || label.ends_with('-') {
+        return Err(ParseDomainError::InvalidHyphen);
+    }
+
+    if let Some(c) = label.chars().find(|c| !c.is_alphanumeric() && *c != '-') {
+        return Err(ParseDomainError::InvalidCharacter(c));
+    }
+
+    if !label.is_ascii() {
+        // every character becomes at least one character of the encoded label, so a long
+        // label can be rejected without encoding it, which takes quadratic time
+        if label.chars().count() > MAX_LABEL_LEN - ACE_PREFIX.len() {
+            return Err(ParseDomainError::LabelTooLong);
+        }
+
+        let mut counter = Counter(ACE_PREFIX.len());
+        punycode::encode(|| label.chars().flat_map(char::to_lowercase), &mut counter)
+            .map_err(|_| ParseDomainError::InvalidPunycode)?;
+
+        return match counter.0 > MAX_LABEL_LEN {
+            true => Err(ParseDomainError::LabelTooLong),
+            false => Ok(()),
+        };
+    }
+
+    if label.len() > MAX_LABEL_LEN {
+        return Err(ParseDomainError::LabelTooLong);
+    }
+
+    if label.get(2..4) == Some("--") {
+        if !is_punycode(label) {
+            return Err(ParseDomainError::InvalidHyphen);
+        }
+
+        // the decoded label must be a valid Unicode label itself
+        let mut decoded = ['\0'; MAX_DECODED_LEN];
+        let len = punycode::decode(&label[ACE_PREFIX.len()..], &mut decoded)
+            .ok_or(ParseDomainError::InvalidPunycode)?;
+        let decoded = &decoded[..len];
+
+        let valid = decoded.iter().any(|c| !c.is_ascii())
+            && decoded.iter().all(|c| c.is_alphanumeric() || *c == '-')
+            && decoded.first() != Some(&'-')
+            && decoded.last() != Some(&'-');
+        if !valid {
+            return Err(ParseDomainError::InvalidPunycode);
+        }
+    }
+
+    Ok(())
+}
+
+/// Counts the bytes written to it.
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Domain, ParseDomainError};
+
+    #[test]
+    fn it_validates_labels() {
+        assert!(Domain::try_from("mail.example.com").is_ok());
+        assert!(Domain::try_from("例子.在线").is_ok());
+        assert_eq!(Domain::try_from(""), Err(ParseDomainError::IsEmpty));
+        assert_eq!(
+            Domain::try_from("example..com"),
+            Err(ParseDomainError::EmptyLabel)
+        );
+        assert_eq!(
+            Domain::try_from("-example.com"),
+            Err(ParseDomainError::InvalidHyphen)
+        );
+        assert_eq!(
+            Domain::try_from("ab--cd.com"),
+            Err(ParseDomainError::InvalidHyphen)
+        );
+        assert_eq!(
+            Domain::try_from("ex_ample.com"),
+            Err(ParseDomainError::InvalidCharacter('_'))
+        );
+        assert_eq!(
+            Domain::try_from("xn--abc-.com"),
+            Err(ParseDomainError::InvalidHyphen)
+        );
+        assert_eq!(
+            Domain::try_from("xn--abc.com"),
+            Err(ParseDomainError::InvalidPunycode)
+        );
+    }
+
+    #[test]
+    fn it_limits_lengths_in_ascii_form() {
+        // 60 characters, but longer than 63 octets as punycode
+        assert_eq!(
+            Domain::try_from(&*"ü".repeat(60)),
+            Err(ParseDomainError::LabelTooLong)
+        );
+        // rejected before encoding, which would take quadratic time
+        assert_eq!(
+            Domain::try_from(&*"ü".repeat(100_000)),
+            Err(ParseDomainError::LabelTooLong)
+        );
+        assert!(Domain::try_from(&*("a".repeat(50) + "ü")).is_ok());
+
+        let label = "a".repeat(63);
+        let domain = [&*label, &*label, &*label, &*label].join(".");
+        assert_eq!(Domain::try_from(&*domain), Err(ParseDomainError::TooLong));
+    }
+
+    #[test]
+    fn it_parses_address_literals() {
+        let domain = Domain::try_from("[ipv6:::ffff:192.0.2.1]").unwrap();
+        assert!(domain.is_literal());
+        assert!(!domain.is_internationalized());
+        assert_eq!(domain.as_str(), "[ipv6:::ffff:192.0.2.1]");
+
+        assert!(!Domain::try_from("example.com").unwrap().is_literal());
+        for literal in [
+            "[192.0.2]",
+            "[192.0.2.1",
+            "[IPv6:192.0.2.1]",
+            "[tag:text]",
+            "[]",
+        ] {
+            assert_eq!(
+                Domain::try_from(literal),
+                Err(ParseDomainError::InvalidLiteral)
+            );
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn it_converts_between_unicode_and_ascii() {
+        use alloc::string::ToString;
+
+        let domain = Domain::try_from("Mail.Bücher.example").unwrap();
+        assert!(domain.is_internationalized());
+        assert_eq!(domain.to_ascii().to_string(), "Mail.xn--bcher-kva.example");
+
+        let domain = Domain::try_from("XN--fsqu00a.xn--3ds443g").unwrap();
+        assert_eq!(domain.to_unicode().to_string(), "例子.在线");
+        assert!(!Domain::try_from("example.com")
+            .unwrap()
+            .is_internationalized());
+    }
+}
@@ -0,0 +1,228 @@
+//! RFC 2047 encoded words, which carry non-ASCII text in headers as ASCII, like
+//! `=?UTF-8?B?R3LDvMOfZQ==?=`.
+
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+
+use super::base64;
+
+/// The maximum length of an encoded word.
+const MAX_WORD_LEN: usize = 75;
+
+/// Everything around the encoded text of a word.
+const PREFIX: &str = "=?UTF-8?B?";
+const SUFFIX: &str = "?=";
+
+/// Returns whether text must be encoded to appear in a header: when it contains non-ASCII
+/// characters, control characters other than tab, or something that would be mistaken for an
+/// encoded word.
+pub fn needs_encoding(text: &str) -> bool {
+    text.contains("=?")
+        || text
+            .chars()
+            .any(|c| !c.is_ascii() || (c.is_ascii_control() && c != '\t'))
+}
+
+/// Text encoded as encoded words. Created by [`encode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Encoded<'a> {
+    text: &'a str,
+}
+
+impl fmt::Display for Encoded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !needs_encoding(self.text) {
+            return f.write_str(self.text);
+        }
+
+        let max_chunk_len = (MAX_WORD_LEN - PREFIX.len() - SUFFIX.len()) / 4 * 3;
+        let mut rest = self.text;
+
+        while !rest.is_empty() {
+            // split on a character boundary, so every word can be decoded by itself
+            let mut len = rest.len().min(max_chunk_len);
+            while !rest.is_char_boundary(len) {
+                len -= 1;
+            }
+
+            if rest.len() < self.text.len() {
+                f.write_str(" ")?;
+            }
+            f.write_str(PREFIX)?;
+            base64::encode(&rest.as_bytes()[..len], f)?;
+            f.write_str(SUFFIX)?;
+
+            rest = &rest[len..];
+        }
+
+        Ok(())
+    }
+}
+
+/// Encodes text as UTF-8 encoded words if it [needs encoding](needs_encoding), or leaves it as
+/// it is. Long text is split into several words separated by spaces, so the header can be
+/// folded between them.
+///
+/// ```
+/// use brief::mail::encoded_word;
+///
+/// assert_eq!(encoded_word::encode("Grüße").to_string(), "=?UTF-8?B?R3LDvMOfZQ==?=");
+/// assert_eq!(encoded_word::encode("Hello").to_string(), "Hello");
+/// ```
+pub fn encode(text: &str) -> Encoded<'_> {
+    Encoded { text }
+}
+
+/// Decodes all encoded words in text, dropping the whitespace between adjacent words. Words in
+/// the UTF-8, US-ASCII and ISO-8859-1 charsets with the B or Q encoding are decoded; anything
+/// else is left as it is.
+///
+/// ```
+/// use brief::mail::encoded_word;
+///
+/// let subject = encoded_word::decode("Re: =?utf-8?q?Gr=C3=BC=C3=9Fe?= =?ISO-8859-1?B?IGF1cyBL9mxu?=");
+/// assert_eq!(subject, "Re: Grüße aus Köln");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn decode(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    // adjacent words are decoded together, since some senders split characters between words
+    let mut pending = Vec::new();
+    let mut after_word = false;
+    let mut rest = text;
+
+    let flush = |decoded: &mut String, pending: &mut Vec<u8>| {
+        decoded.push_str(&String::from_utf8_lossy(pending));
+        pending.clear();
+    };
+
+    while let Some(start) = rest.find("=?") {
+        let between = &rest[..start];
+        match decode_word(&rest[start..]) {
+            Some((len, word)) => {
+                if !after_word || !between.trim_matches([' ', '\t', '\r', '\n']).is_empty() {
+                    flush(&mut decoded, &mut pending);
+                    decoded.push_str(between);
+                }
+                pending.extend_from_slice(&word);
+                rest = &rest[start + len..];
+                after_word = true;
+            }
+            None => {
+                flush(&mut decoded, &mut pending);
+                decoded.push_str(&rest[..start + 2]);
+                rest = &rest[start + 2..];
+                after_word = false;
+            }
+        }
+    }
+
+    flush(&mut decoded, &mut pending);
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Decodes the encoded word at the start of text to UTF-8, returning its length with the
+/// decoded bytes.
+#[cfg(feature = "alloc")]
+fn decode_word(text: &str) -> Option<(usize, Vec<u8>)> {
+    let inner = text.strip_prefix("=?")?;
+    let (charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let end = inner.find("?=")?;
+    let encoded = &inner[..end];
+
+    if encoded.contains(|c: char| c.is_whitespace() || c == '?') {
+        return None;
+    }
+
+    // a language can follow the charset, like `UTF-8*en`
+    let charset = charset.split('*').next().unwrap_or(charset);
+    let raw = match encoding {
+        "B" | "b" => base64::decode(encoded.as_bytes())?,
+        "Q" | "q" => decode_q(encoded)?,
+        _ => return None,
+    };
+
+    let bytes = if charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("us-ascii")
+    {
+        raw
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") || charset.eq_ignore_ascii_case("latin1") {
+        raw.into_iter()
+            .map(char::from)
+            .collect::<String>()
+            .into_bytes()
+    } else {
+        return None;
+    };
+
+    let len = text.len() - inner.len() + end + SUFFIX.len();
+    Some((len, bytes))
+}
+
+/// Decodes the Q encoding, which is quoted-printable with `_` for spaces.
+#[cfg(feature = "alloc")]
+fn decode_q(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut rest = text.as_bytes();
+
+    while let Some((&b, tail)) = rest.split_first() {
+        match b {
+            b'_' => bytes.push(b' '),
+            b'=' => {
+                let hex = core::str::from_utf8(tail.get(..2)?).ok()?;
+                bytes.push(u8::from_str_radix(hex, 16).ok()?);
+                rest = &tail[2..];
+                continue;
+            }
+            b => bytes.push(b),
+        }
+        rest = tail;
+    }
+
+    Some(bytes)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::{decode, encode, needs_encoding, MAX_WORD_LEN};
+    use alloc::string::ToString;
+
+    #[test]
+    fn it_splits_long_text_into_words_on_character_boundaries() {
+        let text = "ä".repeat(40);
+        let encoded = encode(&text).to_string();
+        let words: std::vec::Vec<_> = encoded.split(' ').collect();
+
+        assert_eq!(words.len(), 2);
+        assert!(words.iter().all(|w| w.len() <= MAX_WORD_LEN));
+        assert_eq!(decode(&encoded), text);
+    }
+
+    #[test]
+    fn it_encodes_text_that_looks_like_an_encoded_word() {
+        assert!(needs_encoding("=?UTF-8?B?x?="));
+        assert!(needs_encoding("line\r\nbreak"));
+        assert!(!needs_encoding("tab\there"));
+        assert!(!needs_encoding("plain text, with (specials)"));
+    }
+
+    #[test]
+    fn it_leaves_invalid_words_undecoded() {
+        assert_eq!(decode("=?utf-8?x?abc?= =?"), "=?utf-8?x?abc?= =?");
+        assert_eq!(decode("=?koi8-r?B?8MHS?="), "=?koi8-r?B?8MHS?=");
+        assert_eq!(decode("=?utf-8?Q?a b?="), "=?utf-8?Q?a b?=");
+    }
+
+    #[test]
+    fn it_joins_adjacent_words() {
+        // "é" is split between the two words
+        assert_eq!(
+            decode("=?UTF-8?Q?caf=C3?=\r\n =?UTF-8?Q?=A9_au_lait?="),
+            "café au lait"
+        );
+        assert_eq!(decode("=?UTF-8?Q?a?= b =?UTF-8?Q?c?="), "a b c");
+    }
+}
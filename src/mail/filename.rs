@@ -0,0 +1,177 @@
+use core::fmt::{self, Write};
+
+/// The maximum length in bytes of a sanitized filename, which is the limit of most filesystems.
+const MAX_LEN: usize = 255;
+
+/// The maximum length in bytes of an extension that is kept when a filename is shortened.
+const MAX_EXTENSION_LEN: usize = 16;
+
+/// Used when nothing is left of a filename after sanitizing it.
+const FALLBACK: &str = "attachment";
+
+/// Device names that can't be used as a filename on Windows, regardless of extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// A filename that is safe to hand to a recipient. Created by [`sanitize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sanitized<'a> {
+    original: &'a str,
+    stem: &'a str,
+    extension: &'a str,
+    reserved: bool,
+}
+
+impl<'a> Sanitized<'a> {
+    /// Returns the filename as it was passed to [`sanitize`].
+    pub fn original(&self) -> &'a str {
+        self.original
+    }
+
+    /// Returns whether sanitizing changed the filename.
+    pub fn is_changed(&self) -> bool {
+        let mut cmp = Compare {
+            rest: self.original,
+            equal: true,
+        };
+
+        write!(cmp, "{}", self).is_err() || !cmp.equal || !cmp.rest.is_empty()
+    }
+}
+
+impl fmt::Display for Sanitized<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.stem.is_empty() && self.extension.is_empty() {
+            return f.write_str(FALLBACK);
+        }
+
+        let extension_len: usize = self
+            .extension
+            .chars()
+            .filter(is_allowed)
+            .map(char::len_utf8)
+            .sum();
+        let mut budget = MAX_LEN - extension_len;
+
+        if self.reserved {
+            f.write_char('_')?;
+            budget -= 1;
+        }
+
+        for c in self.stem.chars().filter(is_allowed) {
+            if c.len_utf8() > budget {
+                break;
+            }
+            budget -= c.len_utf8();
+            f.write_char(c)?;
+        }
+
+        for c in self.extension.chars().filter(is_allowed) {
+            f.write_char(c)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_allowed(c: &char) -> bool {
+    !c.is_control()
+}
+
+/// Sanitizes an attachment filename so it can't be abused by the recipient's tooling.
+///
+/// Directories are stripped, as are control characters and leading dots. Names that are
+/// reserved on Windows are prefixed with an underscore and overly long names are shortened
+/// while keeping the extension. The result implements `Display`.
+///
+/// ```
+/// use brief::mail::filename;
+///
+/// assert_eq!(format!("{}", filename::sanitize("../../etc/passwd")), "passwd");
+/// assert_eq!(format!("{}", filename::sanitize("C:\\Users\\me\\.bashrc")), "bashrc");
+/// assert_eq!(format!("{}", filename::sanitize("con.txt")), "_con.txt");
+/// ```
+pub fn sanitize(name: &str) -> Sanitized<'_> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let base = base
+        .trim_start_matches(|c: char| c == '.' || c.is_whitespace() || c.is_control())
+        .trim_end_matches(|c: char| c == '.' || c.is_whitespace() || c.is_control());
+
+    let (stem, extension) = match base.rfind('.') {
+        Some(i) if i > 0 && base.len() - i <= MAX_EXTENSION_LEN => base.split_at(i),
+        _ => (base, ""),
+    };
+
+    let device = stem.split('.').next().unwrap_or("").trim_end();
+    let reserved = RESERVED_NAMES
+        .iter()
+        .any(|r| device.eq_ignore_ascii_case(r));
+
+    Sanitized {
+        original: name,
+        stem,
+        extension,
+        reserved,
+    }
+}
+
+/// Compares written output against a string without allocating.
+struct Compare<'a> {
+    rest: &'a str,
+    equal: bool,
+}
+
+impl Write for Compare<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self.rest.strip_prefix(s) {
+            Some(rest) if self.equal => self.rest = rest,
+            _ => self.equal = false,
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize;
+    use std::string::ToString;
+
+    #[test]
+    fn it_leaves_ordinary_filenames_alone() {
+        let sanitized = sanitize("invoice 2023.pdf");
+        assert_eq!(sanitized.to_string(), "invoice 2023.pdf");
+        assert!(!sanitized.is_changed());
+    }
+
+    #[test]
+    fn it_strips_directories_control_characters_and_leading_dots() {
+        assert_eq!(sanitize("a/b\\..\\..hidden.txt").to_string(), "hidden.txt");
+        assert_eq!(sanitize("re\u{0}port\r\n.pdf").to_string(), "report.pdf");
+        assert!(sanitize("../x").is_changed());
+    }
+
+    #[test]
+    fn it_prefixes_reserved_device_names() {
+        assert_eq!(sanitize("NUL").to_string(), "_NUL");
+        assert_eq!(sanitize("lpt1.tar.gz").to_string(), "_lpt1.tar.gz");
+        assert_eq!(sanitize("console.log").to_string(), "console.log");
+    }
+
+    #[test]
+    fn it_shortens_long_names_but_keeps_the_extension() {
+        let long = "ä".repeat(300) + ".pdf";
+        let sanitized = sanitize(&long).to_string();
+
+        assert!(sanitized.len() <= 255);
+        assert!(sanitized.ends_with("ä.pdf"));
+    }
+
+    #[test]
+    fn it_falls_back_when_nothing_is_left() {
+        assert_eq!(sanitize("...").to_string(), "attachment");
+        assert_eq!(sanitize("dir/").to_string(), "attachment");
+    }
+}
@@ -0,0 +1,165 @@
+//! Snapshot tests for generated mail.
+//!
+//! A mail is serialized and compared with a golden `.eml` file. Headers that change on every
+//! run, like `Date`, are normalized on both sides, so only real changes fail the comparison:
+//! ```no_run
+//! use brief::mail::{golden, Header, HeaderMap, Mail};
+//!
+//! let headers = HeaderMap::from_iter([Header::Subject("Your invoice")]);
+//! golden::assert_matches(&Mail::new(headers, b"Hi!"), "tests/golden/invoice.eml");
+//! ```
+//!
+//! A missing golden file is created from the mail. Set the `BRIEF_UPDATE_GOLDEN` environment
+//! variable to overwrite existing files after an intended change.
+
+use std::{env, fs, io, path::Path, string::String, vec::Vec};
+
+use super::{
+    diff::{edits, Edit},
+    Mail,
+};
+
+/// The headers whose values are replaced before comparing, since they change on every run.
+const VOLATILE_HEADERS: [&str; 2] = ["Date", "Message-ID"];
+
+/// The environment variable that makes [`compare`] overwrite golden files.
+const UPDATE_VAR: &str = "BRIEF_UPDATE_GOLDEN";
+
+/// Replaces the values of volatile headers in a serialized message with a placeholder,
+/// including their folded lines. The body is kept as it is.
+///
+/// ```
+/// use brief::mail::golden;
+///
+/// let message = "Date: Mon, 1 Jan 2024\r\n 00:00:00 +0000\r\nSubject: Hi\r\n\r\nDate: today";
+/// assert_eq!(
+///     golden::normalize(message),
+///     "Date: [normalized]\r\nSubject: Hi\r\n\r\nDate: today"
+/// );
+/// ```
+pub fn normalize(message: &str) -> String {
+    let mut normalized = String::with_capacity(message.len());
+    let mut lines = message.split_inclusive('\n');
+    let mut volatile = false;
+
+    for line in lines.by_ref() {
+        if line == "\r\n" || line == "\n" {
+            normalized.push_str(line);
+            break;
+        }
+
+        if line.starts_with([' ', '\t']) {
+            if !volatile {
+                normalized.push_str(line);
+            }
+            continue;
+        }
+
+        let name = line.split_once(':').map_or("", |(name, _)| name);
+        volatile = VOLATILE_HEADERS
+            .iter()
+            .any(|v| v.eq_ignore_ascii_case(name));
+        match volatile {
+            true => {
+                normalized.push_str(name);
+                normalized.push_str(": [normalized]\r\n");
+            }
+            false => normalized.push_str(line),
+        }
+    }
+
+    normalized.extend(lines);
+    normalized
+}
+
+/// Compares a mail with a golden file, returning a line diff of the normalized messages if they
+/// differ. The golden file is written instead if it doesn't exist, or if the
+/// `BRIEF_UPDATE_GOLDEN` environment variable is set.
+pub fn compare(mail: &Mail<'_>, path: impl AsRef<Path>) -> io::Result<Option<String>> {
+    let path = path.as_ref();
+    let bytes = mail
+        .to_bytes()
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    let actual = normalize(&String::from_utf8_lossy(&bytes));
+
+    if env::var_os(UPDATE_VAR).is_some() || !path.exists() {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, actual)?;
+        return Ok(None);
+    }
+
+    let expected = normalize(&fs::read_to_string(path)?);
+    match expected == actual {
+        true => Ok(None),
+        false => Ok(Some(line_diff(&expected, &actual))),
+    }
+}
+
+/// Like [`compare`], but panics with the diff if the mail doesn't match the golden file.
+#[track_caller]
+pub fn assert_matches(mail: &Mail<'_>, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    match compare(mail, path) {
+        Ok(None) => {}
+        Ok(Some(diff)) => panic!(
+            "mail doesn't match {} (set {UPDATE_VAR} to update it):\n{diff}",
+            path.display()
+        ),
+        Err(error) => panic!("failed to compare mail with {}: {error}", path.display()),
+    }
+}
+
+/// Writes the lines of both messages, prefixing removed lines with `-` and added lines with `+`.
+fn line_diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<_> = expected.lines().collect();
+    let actual: Vec<_> = actual.lines().collect();
+
+    let mut diff = String::new();
+    for edit in edits(&expected, &actual) {
+        let (prefix, line) = match edit {
+            Edit::Keep(i, _) => (' ', expected[i]),
+            Edit::Remove(i) => ('-', expected[i]),
+            Edit::Add(j) => ('+', actual[j]),
+        };
+        diff.push(prefix);
+        diff.push_str(line);
+        diff.push('\n');
+    }
+
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare, line_diff};
+    use crate::mail::{Header, HeaderMap, Mail};
+
+    #[test]
+    fn it_diffs_lines() {
+        assert_eq!(line_diff("a\nb\nc", "a\nx\nc"), " a\n-b\n+x\n c\n");
+    }
+
+    #[test]
+    fn it_creates_and_compares_golden_files() {
+        let path =
+            std::env::temp_dir().join(std::format!("brief-golden-{}.eml", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let headers = HeaderMap::from_iter([Header::Date("Mon, 1 Jan 2024 00:00:00 +0000")]);
+        assert_eq!(compare(&Mail::new(headers, b"Hi!"), &path).unwrap(), None);
+
+        let headers = HeaderMap::from_iter([Header::Date("Tue, 2 Jan 2024 00:00:00 +0000")]);
+        assert_eq!(compare(&Mail::new(headers, b"Hi!"), &path).unwrap(), None);
+
+        let headers = HeaderMap::from_iter([Header::Date("Tue, 2 Jan 2024 00:00:00 +0000")]);
+        let diff = compare(&Mail::new(headers, b"Bye!"), &path).unwrap();
+        assert_eq!(
+            diff.as_deref(),
+            Some(" Date: [normalized]\n \n-Hi!\n+Bye!\n")
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,3 +1,414 @@
-pub enum Header {
-    //
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+#[cfg(feature = "alloc")]
+use super::SerializeMailError;
+use super::{encoded_word, Address, Mailbox, MailboxList};
+
+/// Header lines are folded to fit within this length when possible, as recommended by RFC 5322.
+#[cfg(feature = "alloc")]
+const LINE_LENGTH: usize = 78;
+/// The length lines can't exceed, not counting the CRLF.
+pub(crate) const MAX_LINE_LENGTH: usize = 998;
+
+/// Represents a header field of a mail
+///
+/// Every header has a typed variant, and [`Header::Custom`] covers the rest. Subjects and
+/// display names are written as [encoded words](super::encoded_word) when they need to be;
+/// other bodies are written as given, so they must already be encoded for use in a header.
+/// ```
+/// use brief::mail::{Header, MailboxList};
+///
+/// let to = Header::To(MailboxList::try_from("Alice <alice@example.com>").unwrap());
+///
+/// assert_eq!(to.name(), "To");
+/// assert_eq!(to.body().to_string(), "Alice <alice@example.com>");
+/// assert_eq!(to.to_string(), "To: Alice <alice@example.com>");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Header<'a> {
+    ReturnPath(Address<'a>),
+    From(MailboxList<'a>),
+    Sender(Mailbox<'a>),
+    To(MailboxList<'a>),
+    Cc(MailboxList<'a>),
+    Bcc(MailboxList<'a>),
+    ReplyTo(MailboxList<'a>),
+    Subject(&'a str),
+    Date(&'a str),
+    /// A message identifier without the angle brackets, like `1234@example.com`.
+    MessageId(&'a str),
+    /// One or more message identifiers, each enclosed in angle brackets.
+    InReplyTo(&'a str),
+    /// One or more message identifiers, each enclosed in angle brackets.
+    References(&'a str),
+    /// Always has the body `1.0`.
+    MimeVersion,
+    ContentType(&'a str),
+    ContentTransferEncoding(&'a str),
+    /// The address a server delivered the mail to, added on local delivery and forwarding.
+    DeliveredTo(Address<'a>),
+    /// The `X-Original-To` header, with the recipients before aliases were expanded or the mail
+    /// was redirected.
+    OriginalTo(MailboxList<'a>),
+    /// Any other header, with its name and body.
+    Custom(&'a str, &'a str),
+}
+
+impl<'a> Header<'a> {
+    /// Returns the name of the header.
+    pub fn name(&self) -> &str {
+        match self {
+            Header::ReturnPath(_) => "Return-Path",
+            Header::From(_) => "From",
+            Header::Sender(_) => "Sender",
+            Header::To(_) => "To",
+            Header::Cc(_) => "Cc",
+            Header::Bcc(_) => "Bcc",
+            Header::ReplyTo(_) => "Reply-To",
+            Header::Subject(_) => "Subject",
+            Header::Date(_) => "Date",
+            Header::MessageId(_) => "Message-ID",
+            Header::InReplyTo(_) => "In-Reply-To",
+            Header::References(_) => "References",
+            Header::MimeVersion => "MIME-Version",
+            Header::ContentType(_) => "Content-Type",
+            Header::ContentTransferEncoding(_) => "Content-Transfer-Encoding",
+            Header::DeliveredTo(_) => "Delivered-To",
+            Header::OriginalTo(_) => "X-Original-To",
+            Header::Custom(name, _) => name,
+        }
+    }
+
+    /// Returns whether the header contains UTF-8 that can't be encoded, so a mail with it can
+    /// only be sent to servers that support the SMTPUTF8 extension. These are addresses with
+    /// UTF-8 and bodies that are written as given.
+    pub fn requires_smtputf8(&self) -> bool {
+        match self {
+            Header::ReturnPath(address) | Header::DeliveredTo(address) => {
+                address.requires_smtputf8()
+            }
+            Header::Sender(mailbox) => mailbox.requires_smtputf8(),
+            Header::From(list)
+            | Header::To(list)
+            | Header::Cc(list)
+            | Header::Bcc(list)
+            | Header::ReplyTo(list)
+            | Header::OriginalTo(list) => list.requires_smtputf8(),
+            Header::Subject(_) | Header::MimeVersion => false,
+            Header::Date(body)
+            | Header::MessageId(body)
+            | Header::InReplyTo(body)
+            | Header::References(body)
+            | Header::ContentType(body)
+            | Header::ContentTransferEncoding(body) => !body.is_ascii(),
+            Header::Custom(name, body) => !name.is_ascii() || !body.is_ascii(),
+        }
+    }
+
+    /// Returns the body of the header, which is serialized when displayed. Mailbox lists are
+    /// folded to fit after the header name.
+    pub fn body(&self) -> Body<'_> {
+        Body { header: self }
+    }
+}
+
+impl fmt::Display for Header<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.name(), self.body())
+    }
+}
+
+/// The serialized body of a [`Header`]. Created by [`Header::body`].
+#[derive(Debug, Clone, Copy)]
+pub struct Body<'a> {
+    header: &'a Header<'a>,
+}
+
+impl fmt::Display for Body<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the body starts after the name, a colon and a space
+        let column = self.header.name().len() + 2;
+
+        match self.header {
+            Header::ReturnPath(address) => write!(f, "<{}>", address),
+            Header::Sender(mailbox) => mailbox.fmt(f),
+            Header::DeliveredTo(address) => address.fmt(f),
+            Header::From(list)
+            | Header::To(list)
+            | Header::Cc(list)
+            | Header::Bcc(list)
+            | Header::ReplyTo(list)
+            | Header::OriginalTo(list) => list.folded(column).fmt(f),
+            Header::MessageId(id) => write!(f, "<{}>", id),
+            Header::MimeVersion => f.write_str("1.0"),
+            Header::Subject(subject) => encoded_word::encode(subject).fmt(f),
+            Header::Date(body)
+            | Header::InReplyTo(body)
+            | Header::References(body)
+            | Header::ContentType(body)
+            | Header::ContentTransferEncoding(body)
+            | Header::Custom(_, body) => f.write_str(body),
+        }
+    }
+}
+
+/// Returns whether a header name is valid: printable ASCII without a colon, as RFC 5322
+/// requires.
+#[cfg(any(feature = "alloc", feature = "serde"))]
+pub(crate) fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.bytes().all(|b| b.is_ascii_graphic() && b != b':')
+}
+
+/// Folds a serialized header by inserting a CRLF before whitespace, so its lines fit within 78
+/// octets where possible. Fails if a line is still longer than 998 octets, since such a line
+/// can't be sent at all, or if the header has a line break that isn't a fold, since that would
+/// start another header.
+#[cfg(feature = "alloc")]
+pub(crate) fn fold(header: &str) -> Result<String, SerializeMailError> {
+    let is_wsp = |b: &u8| *b == b' ' || *b == b'\t';
+    let mut folded = String::with_capacity(header.len() + 8);
+
+    for (i, mut line) in header.split("\r\n").enumerate() {
+        if line.contains(['\r', '\n']) || (i > 0 && !line.starts_with([' ', '\t'])) {
+            return Err(SerializeMailError::BareLineBreak);
+        }
+        if i > 0 {
+            folded.push_str("\r\n");
+        }
+
+        while line.len() > LINE_LENGTH {
+            // a fold must leave text on both lines, or it would create a line of only whitespace
+            let bytes = line.as_bytes();
+            let text_start = bytes.iter().position(|b| !is_wsp(b)).unwrap_or(bytes.len());
+            let text_end = bytes.iter().rposition(|b| !is_wsp(b)).unwrap_or(0);
+            let breaks = (text_start + 1..=text_end).filter(|&i| is_wsp(&bytes[i]));
+
+            let Some(at) = breaks
+                .clone()
+                .take_while(|&i| i <= LINE_LENGTH)
+                .last()
+                .or_else(|| breaks.clone().next())
+            else {
+                break;
+            };
+
+            if line[..at].len() > MAX_LINE_LENGTH {
+                return Err(SerializeMailError::LineTooLong(at));
+            }
+            folded.push_str(&line[..at]);
+            folded.push_str("\r\n");
+            line = &line[at..];
+        }
+
+        if line.len() > MAX_LINE_LENGTH {
+            return Err(SerializeMailError::LineTooLong(line.len()));
+        }
+        folded.push_str(line);
+    }
+
+    Ok(folded)
+}
+
+/// A header with its folds removed. Created by [`unfold`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unfolded<'a> {
+    header: &'a str,
+}
+
+impl fmt::Display for Unfolded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rest = self.header;
+
+        while let Some(i) = rest.find('\n') {
+            let folded = rest[i + 1..].starts_with([' ', '\t']);
+            let line = match folded {
+                true => rest[..i].strip_suffix('\r').unwrap_or(&rest[..i]),
+                false => &rest[..=i],
+            };
+
+            f.write_str(line)?;
+            rest = &rest[i + 1..];
+        }
+
+        f.write_str(rest)
+    }
+}
+
+/// Unfolds a header as it was received, removing every line break that is followed by
+/// whitespace. Both CRLF and bare LF line breaks are recognized.
+///
+/// ```
+/// use brief::mail::header;
+///
+/// let subject = header::unfold("Subject: This is a\r\n very long subject");
+/// assert_eq!(subject.to_string(), "Subject: This is a very long subject");
+/// ```
+pub fn unfold(header: &str) -> Unfolded<'_> {
+    Unfolded { header }
+}
+
+/// A header that can be retrieved with its typed value from a
+/// [`HeaderMap`](super::HeaderMap), like [`Subject`].
+pub trait TypedHeader {
+    /// The value of the header.
+    type Value<'a>;
+
+    /// Returns the value of a header, or `None` if it's a different header.
+    fn value<'a>(header: &Header<'a>) -> Option<Self::Value<'a>>;
+}
+
+macro_rules! typed_headers {
+    ($($(#[$doc:meta])* $name:ident($value:ty) => |$header:pat_param| $get:expr;)*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+            pub struct $name;
+
+            impl TypedHeader for $name {
+                type Value<'a> = $value;
+
+                fn value<'a>(header: &Header<'a>) -> Option<Self::Value<'a>> {
+                    match header {
+                        $header => Some($get),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+typed_headers! {
+    /// The `Return-Path` header.
+    ReturnPath(Address<'a>) => |Header::ReturnPath(address)| address.clone();
+    /// The `From` header.
+    From(MailboxList<'a>) => |Header::From(list)| *list;
+    /// The `Sender` header.
+    Sender(Mailbox<'a>) => |Header::Sender(mailbox)| mailbox.clone();
+    /// The `To` header.
+    To(MailboxList<'a>) => |Header::To(list)| *list;
+    /// The `Cc` header.
+    Cc(MailboxList<'a>) => |Header::Cc(list)| *list;
+    /// The `Bcc` header.
+    Bcc(MailboxList<'a>) => |Header::Bcc(list)| *list;
+    /// The `Reply-To` header.
+    ReplyTo(MailboxList<'a>) => |Header::ReplyTo(list)| *list;
+    /// The `Subject` header.
+    Subject(&'a str) => |Header::Subject(subject)| *subject;
+    /// The `Date` header.
+    Date(&'a str) => |Header::Date(date)| *date;
+    /// The `Message-ID` header.
+    MessageId(&'a str) => |Header::MessageId(id)| *id;
+    /// The `In-Reply-To` header.
+    InReplyTo(&'a str) => |Header::InReplyTo(ids)| *ids;
+    /// The `References` header.
+    References(&'a str) => |Header::References(ids)| *ids;
+    /// The `MIME-Version` header.
+    MimeVersion(()) => |Header::MimeVersion| ();
+    /// The `Content-Type` header.
+    ContentType(&'a str) => |Header::ContentType(content_type)| *content_type;
+    /// The `Content-Transfer-Encoding` header.
+    ContentTransferEncoding(&'a str) => |Header::ContentTransferEncoding(encoding)| *encoding;
+    /// The `Delivered-To` header.
+    DeliveredTo(Address<'a>) => |Header::DeliveredTo(address)| address.clone();
+    /// The `X-Original-To` header.
+    OriginalTo(MailboxList<'a>) => |Header::OriginalTo(list)| *list;
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::{fold, is_valid_name, unfold, Header};
+    use crate::mail::SerializeMailError;
+    use crate::mail::{Mailbox, MailboxList};
+    use alloc::string::ToString;
+
+    #[test]
+    fn it_serializes_header_bodies() {
+        let address = "bounce@example.com".try_into().unwrap();
+        let sender = Mailbox::try_from("Mailer <mailer@example.com>").unwrap();
+
+        assert_eq!(
+            Header::ReturnPath(address).to_string(),
+            "Return-Path: <bounce@example.com>"
+        );
+        assert_eq!(
+            Header::Sender(sender).to_string(),
+            "Sender: Mailer <mailer@example.com>"
+        );
+        assert_eq!(
+            Header::DeliveredTo("alice@example.com".try_into().unwrap()).to_string(),
+            "Delivered-To: alice@example.com"
+        );
+        assert_eq!(
+            Header::MessageId("1234@example.com").body().to_string(),
+            "<1234@example.com>"
+        );
+        assert_eq!(Header::MimeVersion.to_string(), "MIME-Version: 1.0");
+        assert_eq!(
+            Header::Subject("Grüße").to_string(),
+            "Subject: =?UTF-8?B?R3LDvMOfZQ==?="
+        );
+        assert_eq!(
+            Header::Custom("X-Mailer", "brief").to_string(),
+            "X-Mailer: brief"
+        );
+    }
+
+    #[test]
+    fn it_folds_long_headers_at_whitespace() {
+        let subject = Header::Subject(
+            "A subject that is much too long to fit on a single line, so it has to be folded",
+        );
+        let folded = fold(&subject.to_string()).unwrap();
+
+        assert_eq!(
+            folded,
+            "Subject: A subject that is much too long to fit on a single line, so it has to\r\n be folded"
+        );
+        assert_eq!(unfold(&folded).to_string(), subject.to_string());
+    }
+
+    #[test]
+    fn it_rejects_lines_that_cannot_be_folded() {
+        let word = "x".repeat(1000);
+        assert_eq!(
+            fold(&alloc::format!("Subject: {}", word)),
+            Err(SerializeMailError::LineTooLong(1001))
+        );
+        assert!(fold(&alloc::format!("Subject: {} x", "x".repeat(990))).is_ok());
+        assert_eq!(fold("Subject:    ").unwrap(), "Subject:    ");
+    }
+
+    #[test]
+    fn it_rejects_line_breaks_that_are_not_folds() {
+        assert_eq!(fold("X-A: v\r\n w").unwrap(), "X-A: v\r\n w");
+        assert_eq!(
+            fold("X-A: v\r\nBcc: x@y.z"),
+            Err(SerializeMailError::BareLineBreak)
+        );
+        assert_eq!(fold("X-A: v\nw"), Err(SerializeMailError::BareLineBreak));
+        assert_eq!(fold("X-A: v\r w"), Err(SerializeMailError::BareLineBreak));
+
+        assert!(is_valid_name("X-Mailer"));
+        assert!(!is_valid_name("X Mailer"));
+        assert!(!is_valid_name("X:Mailer"));
+        assert!(!is_valid_name("X-Mäiler"));
+        assert!(!is_valid_name(""));
+    }
+
+    #[test]
+    fn it_folds_mailbox_lists_after_the_header_name() {
+        let list = MailboxList::try_from(
+            "first.person@example.com, second.person@example.com, third@example.com",
+        )
+        .unwrap();
+
+        assert_eq!(
+            Header::ReplyTo(list).to_string(),
+            "Reply-To: first.person@example.com, second.person@example.com,\r\n third@example.com"
+        );
+    }
 }
@@ -0,0 +1,191 @@
+use core::slice;
+
+use alloc::vec::Vec;
+
+use super::{header::TypedHeader, Header};
+
+/// Represents the headers of a mail
+///
+/// Headers are kept in the order they were added, and a header can appear more than once, like
+/// `Received`. Lookups by name ignore case:
+/// ```
+/// use brief::mail::{header::Subject, Header, HeaderMap};
+///
+/// let mut headers = HeaderMap::new();
+/// headers.append(Header::Custom("Received", "from a.example.com"));
+/// headers.append(Header::Custom("Received", "from b.example.com"));
+/// headers.append(Header::Subject("Hello"));
+///
+/// assert_eq!(headers.get_all("received").count(), 2);
+/// assert_eq!(headers.get("SUBJECT"), Some(&Header::Subject("Hello")));
+/// assert_eq!(headers.typed::<Subject>(), Some("Hello"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap<'a> {
+    headers: Vec<Header<'a>>,
+}
+
+impl<'a> HeaderMap<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of headers, counting every duplicate.
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    /// Adds a header after all others, keeping headers with the same name.
+    pub fn append(&mut self, header: Header<'a>) {
+        self.headers.push(header);
+    }
+
+    /// Adds a header before all others, like the trace headers a server adds on delivery.
+    pub fn prepend(&mut self, header: Header<'a>) {
+        self.headers.insert(0, header);
+    }
+
+    /// Adds a header, replacing all headers with the same name. The header takes the place of
+    /// the first one it replaces, which is returned.
+    ///
+    /// ```
+    /// use brief::mail::{Header, HeaderMap};
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.append(Header::Subject("Hello"));
+    ///
+    /// let old = headers.insert(Header::Subject("Hello again"));
+    /// assert_eq!(old, Some(Header::Subject("Hello")));
+    /// assert_eq!(headers.len(), 1);
+    /// ```
+    pub fn insert(&mut self, header: Header<'a>) -> Option<Header<'a>> {
+        let Some(first) = self.position(header.name()) else {
+            self.headers.push(header);
+            return None;
+        };
+
+        let old = core::mem::replace(&mut self.headers[first], header);
+        let mut i = 0;
+        self.headers.retain(|h| {
+            i += 1;
+            i - 1 <= first || !h.name().eq_ignore_ascii_case(old.name())
+        });
+
+        Some(old)
+    }
+
+    /// Removes all headers with the given name, returning how many were removed.
+    pub fn remove(&mut self, name: &str) -> usize {
+        let len = self.headers.len();
+        self.headers
+            .retain(|h| !h.name().eq_ignore_ascii_case(name));
+        len - self.headers.len()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// Returns the first header with the given name.
+    pub fn get(&self, name: &str) -> Option<&Header<'a>> {
+        self.position(name).map(|i| &self.headers[i])
+    }
+
+    /// Returns all headers with the given name, in order.
+    pub fn get_all<'b>(&'b self, name: &'b str) -> impl Iterator<Item = &'b Header<'a>> + 'b {
+        self.headers
+            .iter()
+            .filter(move |h| h.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the value of the first header of the given type.
+    pub fn typed<T: TypedHeader>(&self) -> Option<T::Value<'a>> {
+        self.headers.iter().find_map(T::value)
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Header<'a>> {
+        self.headers.iter()
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.headers
+            .iter()
+            .position(|h| h.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl<'a, 'b> IntoIterator for &'b HeaderMap<'a> {
+    type Item = &'b Header<'a>;
+    type IntoIter = slice::Iter<'b, Header<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> FromIterator<Header<'a>> for HeaderMap<'a> {
+    fn from_iter<T: IntoIterator<Item = Header<'a>>>(iter: T) -> Self {
+        Self {
+            headers: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<'a> Extend<Header<'a>> for HeaderMap<'a> {
+    fn extend<T: IntoIterator<Item = Header<'a>>>(&mut self, iter: T) {
+        self.headers.extend(iter);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HeaderMap;
+    use crate::mail::{
+        header::{To, TypedHeader},
+        Header, MailboxList,
+    };
+
+    #[test]
+    fn it_replaces_headers_in_place() {
+        let mut headers: HeaderMap = [
+            Header::Custom("X-A", "1"),
+            Header::Subject("one"),
+            Header::Custom("x-a", "2"),
+            Header::Custom("X-B", "3"),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            headers.insert(Header::Custom("X-a", "4")),
+            Some(Header::Custom("X-A", "1"))
+        );
+        assert_eq!(
+            headers.iter().cloned().collect::<std::vec::Vec<_>>(),
+            [
+                Header::Custom("X-a", "4"),
+                Header::Subject("one"),
+                Header::Custom("X-B", "3")
+            ]
+        );
+
+        assert_eq!(headers.remove("x-b"), 1);
+        assert!(!headers.contains("X-B"));
+    }
+
+    #[test]
+    fn it_retrieves_typed_values() {
+        let to = MailboxList::try_from("a@x.com").unwrap();
+        let headers: HeaderMap = [Header::Custom("To", "b@y.com"), Header::To(to)]
+            .into_iter()
+            .collect();
+
+        assert_eq!(headers.typed::<To>(), Some(to));
+        assert_eq!(To::value(&Header::Subject("x")), None);
+        assert_eq!(headers.get("to"), Some(&Header::Custom("To", "b@y.com")));
+    }
+}
@@ -0,0 +1,313 @@
+use core::ops::Range;
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+/// HTML elements whose content is not markup.
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+
+/// A start or end tag in an HTML document.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Tag<'a> {
+    html: &'a str,
+    pub name: &'a str,
+    pub closing: bool,
+    pub span: Range<usize>,
+    attributes: Range<usize>,
+}
+
+impl<'a> Tag<'a> {
+    /// Returns whether this tag is for the given element, ignoring case.
+    pub fn is(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name)
+    }
+
+    pub fn attributes(&self) -> Attributes<'a> {
+        Attributes {
+            html: self.html,
+            pos: self.attributes.start,
+            end: self.attributes.end,
+        }
+    }
+
+    /// Returns the first attribute with the given name, ignoring case.
+    #[cfg(any(feature = "alloc", test))]
+    pub fn attribute(&self, name: &str) -> Option<Attribute<'a>> {
+        self.attributes()
+            .find(|a| a.name.eq_ignore_ascii_case(name))
+    }
+}
+
+/// An attribute of a [`Tag`]. Attributes without a value have an empty value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Attribute<'a> {
+    pub name: &'a str,
+    pub value: &'a str,
+    /// The byte range of the value, without quotes.
+    pub span: Range<usize>,
+    pub quote: Option<u8>,
+}
+
+/// Iterator over the attributes of a [`Tag`].
+#[derive(Debug, Clone)]
+pub(crate) struct Attributes<'a> {
+    html: &'a str,
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> Iterator for Attributes<'a> {
+    type Item = Attribute<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (attribute, pos) = scan_attribute(self.html, self.pos, self.end)?;
+        self.pos = pos;
+        Some(attribute)
+    }
+}
+
+/// Scans the attribute starting at or after `pos`, returning it with the position after it.
+/// Returns `None` at the `>` that ends the tag or at `end`.
+fn scan_attribute(html: &str, mut pos: usize, end: usize) -> Option<(Attribute<'_>, usize)> {
+    let bytes = &html.as_bytes()[..end];
+    let skip_whitespace = |mut pos: usize| {
+        while pos < end && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        pos
+    };
+
+    while pos < end && (bytes[pos].is_ascii_whitespace() || bytes[pos] == b'/') {
+        pos += 1;
+    }
+    if pos >= end || bytes[pos] == b'>' {
+        return None;
+    }
+
+    let name_start = pos;
+    while pos < end
+        && !matches!(bytes[pos], b'=' | b'>' | b'/')
+        && !bytes[pos].is_ascii_whitespace()
+    {
+        pos += 1;
+    }
+    let name = &html[name_start..pos];
+
+    let equals = skip_whitespace(pos);
+    if bytes.get(equals) != Some(&b'=') {
+        let attribute = Attribute {
+            name,
+            value: "",
+            span: pos..pos,
+            quote: None,
+        };
+        return Some((attribute, pos));
+    }
+
+    pos = skip_whitespace(equals + 1);
+    let (span, quote, next) = match bytes.get(pos) {
+        Some(&quote @ (b'"' | b'\'')) => {
+            let start = pos + 1;
+            let value_end = bytes[start..]
+                .iter()
+                .position(|b| *b == quote)
+                .map_or(end, |i| start + i);
+            (start..value_end, Some(quote), (value_end + 1).min(end))
+        }
+        _ => {
+            let start = pos;
+            while pos < end && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'>' {
+                pos += 1;
+            }
+            (start..pos, None, pos)
+        }
+    };
+
+    let attribute = Attribute {
+        name,
+        value: &html[span.clone()],
+        span,
+        quote,
+    };
+    Some((attribute, next))
+}
+
+/// Iterator over the tags of an HTML document, skipping comments, declarations and the content
+/// of `script` and `style` elements.
+#[derive(Debug, Clone)]
+pub(crate) struct Tags<'a> {
+    html: &'a str,
+    pos: usize,
+    raw_text: Option<&'static str>,
+}
+
+impl<'a> Iterator for Tags<'a> {
+    type Item = Tag<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let html = self.html;
+        let bytes = html.as_bytes();
+
+        // the content of raw text elements can't contain tags, so skip to the end tag
+        if let Some(raw) = self.raw_text.take() {
+            while let Some(close) = html[self.pos..].find("</") {
+                self.pos += close;
+                let name = bytes.get(self.pos + 2..self.pos + 2 + raw.len());
+                if name.is_some_and(|n| n.eq_ignore_ascii_case(raw.as_bytes())) {
+                    break;
+                }
+                self.pos += 2;
+            }
+        }
+
+        loop {
+            let start = self.pos + html[self.pos..].find('<')?;
+            let rest = &html[start..];
+
+            if rest.starts_with("<!--") {
+                self.pos = rest.find("-->").map_or(html.len(), |i| start + i + 3);
+                continue;
+            }
+            if rest.starts_with("<!") || rest.starts_with("<?") {
+                self.pos = rest.find('>').map_or(html.len(), |i| start + i + 1);
+                continue;
+            }
+
+            let closing = rest.starts_with("</");
+            let name_start = start + 1 + usize::from(closing);
+            if !bytes.get(name_start).is_some_and(u8::is_ascii_alphabetic) {
+                self.pos = start + 1;
+                continue;
+            }
+
+            let name_end = html[name_start..]
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+                .map_or(html.len(), |i| name_start + i);
+
+            let mut pos = name_end;
+            while let Some((_, next)) = scan_attribute(html, pos, html.len()) {
+                pos = next;
+            }
+            while pos < html.len() && bytes[pos] != b'>' {
+                pos += 1;
+            }
+            let end = (pos + 1).min(html.len());
+            self.pos = end;
+
+            let tag = Tag {
+                html,
+                name: &html[name_start..name_end],
+                closing,
+                span: start..end,
+                attributes: name_end..pos,
+            };
+
+            if !closing {
+                self.raw_text = RAW_TEXT_ELEMENTS.into_iter().find(|raw| tag.is(raw));
+            }
+
+            return Some(tag);
+        }
+    }
+}
+
+/// Returns an iterator over the tags of an HTML document.
+pub(crate) fn tags(html: &str) -> Tags<'_> {
+    Tags {
+        html,
+        pos: 0,
+        raw_text: None,
+    }
+}
+
+/// Returns the offset after the doctype, comments and processing instructions at the start of
+/// an HTML document, and the whitespace around them, where the first element can be inserted.
+#[cfg(feature = "alloc")]
+pub(crate) fn prolog_end(html: &str) -> usize {
+    let mut pos = 0;
+    loop {
+        let rest = html[pos..].trim_start_matches(|c: char| c.is_ascii_whitespace());
+        let start = html.len() - rest.len();
+
+        let end = if rest.starts_with("<!--") {
+            rest.find("-->").map(|i| i + 3)
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest.find('>').map(|i| i + 1)
+        } else {
+            None
+        };
+        match end {
+            Some(end) => pos = start + end,
+            None => return pos,
+        }
+    }
+}
+
+/// Appends a value to an HTML string, escaping the characters that could end an attribute or
+/// start a tag.
+#[cfg(feature = "alloc")]
+pub(crate) fn push_escaped(out: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tags;
+
+    #[test]
+    fn it_finds_tags_and_attributes() {
+        let html = r#"<!DOCTYPE html><!-- <p> --><a href = "x>y" title='t' hidden data-x=1>t</A >"#;
+        let mut tags = tags(html);
+
+        let a = tags.next().unwrap();
+        assert!(a.is("a") && !a.closing);
+        assert_eq!(a.attribute("HREF").unwrap().value, "x>y");
+        assert_eq!(a.attribute("hidden").unwrap().value, "");
+        assert_eq!(a.attribute("data-x").unwrap().value, "1");
+        assert_eq!(
+            &html[a.span],
+            r#"<a href = "x>y" title='t' hidden data-x=1>"#
+        );
+
+        let end = tags.next().unwrap();
+        assert!(end.is("a") && end.closing);
+        assert!(tags.next().is_none());
+    }
+
+    #[test]
+    fn it_skips_raw_text_content() {
+        let html = "<style>a > b { }</style><script>'<p>'</SCRIPT><br/>";
+        let names: std::vec::Vec<_> = tags(html).map(|t| (t.name, t.closing)).collect();
+
+        assert_eq!(
+            names,
+            [
+                ("style", false),
+                ("style", true),
+                ("script", false),
+                ("SCRIPT", true),
+                ("br", false)
+            ]
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn it_finds_the_end_of_the_prolog() {
+        use super::prolog_end;
+
+        let html = "<?xml version=\"1.0\"?>\n<!DOCTYPE html>\n<!-- <p> -->\n<p>hi</p>";
+        assert_eq!(&html[prolog_end(html)..], "\n<p>hi</p>");
+        assert_eq!(prolog_end("<p>hi</p>"), 0);
+        assert_eq!(prolog_end("<!-- unclosed"), 0);
+    }
+}
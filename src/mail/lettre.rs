@@ -0,0 +1,202 @@
+//! Conversions between brief and [lettre](https://docs.rs/lettre), so a project can move
+//! from one to the other a piece at a time.
+//!
+//! Addresses and mailboxes convert both ways. A [`Mail`] converts into a lettre `Message`,
+//! which can be sent with any lettre transport:
+//! ```
+//! use brief::mail::{Header, HeaderMap, Mail};
+//!
+//! let headers = HeaderMap::from_iter([
+//!     Header::From("Alice <alice@example.com>".try_into().unwrap()),
+//!     Header::To("bob@example.com".try_into().unwrap()),
+//!     Header::Subject("Hello"),
+//! ]);
+//! let mail = Mail::new(headers, b"Hi Bob!");
+//!
+//! let message = lettre::Message::try_from(&mail).unwrap();
+//! assert_eq!(message.envelope().to(), ["bob@example.com".parse().unwrap()]);
+//! ```
+
+use core::fmt;
+use std::string::{String, ToString};
+
+use ::lettre::{
+    address::AddressError,
+    message::header::{HeaderName, HeaderValue},
+};
+
+use super::{
+    encoded_word, header,
+    syntax::{parse_exact, Cursor},
+    Address, Mail, Mailbox, OwnedAddress, OwnedMailbox, ParseAddressError,
+};
+
+#[derive(Debug)]
+pub enum ToMessageError {
+    /// A custom header has a name that lettre doesn't accept. Contains the name.
+    InvalidHeaderName(String),
+    /// lettre couldn't build the message, for example because it has no `From` header.
+    Build(::lettre::error::Error),
+}
+
+impl fmt::Display for ToMessageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeaderName(name) => write!(f, "invalid header name {name:?}"),
+            Self::Build(_) => f.write_str("failed to build the message"),
+        }
+    }
+}
+
+impl core::error::Error for ToMessageError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Build(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<Address<'_>> for ::lettre::Address {
+    type Error = AddressError;
+
+    fn try_from(value: Address<'_>) -> Result<Self, Self::Error> {
+        ::lettre::Address::new(value.user(), value.domain())
+    }
+}
+
+/// lettre accepts UTF-8 in addresses, so the address is validated as an international one.
+impl TryFrom<&::lettre::Address> for OwnedAddress {
+    type Error = ParseAddressError;
+
+    fn try_from(value: &::lettre::Address) -> Result<Self, Self::Error> {
+        Address::try_new_international(value.user(), value.domain()).map(Address::into_owned)
+    }
+}
+
+impl TryFrom<Mailbox<'_>> for ::lettre::message::Mailbox {
+    type Error = AddressError;
+
+    fn try_from(value: Mailbox<'_>) -> Result<Self, Self::Error> {
+        let name = value
+            .name
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(unquote);
+
+        Ok(::lettre::message::Mailbox::new(
+            name,
+            value.address.try_into()?,
+        ))
+    }
+}
+
+impl TryFrom<&::lettre::message::Mailbox> for OwnedMailbox {
+    type Error = ParseAddressError;
+
+    fn try_from(value: &::lettre::message::Mailbox) -> Result<Self, Self::Error> {
+        Ok(OwnedMailbox {
+            name: value.name.clone(),
+            address: (&value.email).try_into()?,
+        })
+    }
+}
+
+/// Builds a lettre message with the headers and body of the mail. lettre adds a `Date` header
+/// if there is none, and chooses the `Content-Transfer-Encoding` of the body itself.
+impl TryFrom<&Mail<'_>> for ::lettre::Message {
+    type Error = ToMessageError;
+
+    fn try_from(value: &Mail<'_>) -> Result<Self, Self::Error> {
+        let mut builder = ::lettre::Message::builder();
+        for h in value.headers() {
+            let name = HeaderName::new_from_ascii(h.name().to_string())
+                .map_err(|_| ToMessageError::InvalidHeaderName(h.name().to_string()))?;
+
+            // lettre encodes and folds values itself, so it gets the decoded text
+            let body = header::unfold(&h.body().to_string()).to_string();
+            builder = builder.raw_header(HeaderValue::new(name, encoded_word::decode(&body)));
+        }
+
+        builder
+            .body(value.body().to_vec())
+            .map_err(ToMessageError::Build)
+    }
+}
+
+/// Returns the text of a display name, without the quotes and escapes of a quoted string.
+fn unquote(name: &str) -> String {
+    if parse_exact(name, true, Cursor::quoted_string).is_err() {
+        return name.to_string();
+    }
+
+    let mut text = String::with_capacity(name.len());
+    let mut escaped = false;
+    for c in name[1..name.len() - 1].chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            c => {
+                text.push(c);
+                escaped = false;
+            }
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unquote;
+    use crate::mail::{Address, Header, HeaderMap, Mail, Mailbox, OwnedAddress, OwnedMailbox};
+
+    #[test]
+    fn it_converts_addresses_and_mailboxes_both_ways() {
+        let address = Address::try_from("user@domain.com").unwrap();
+        let converted = ::lettre::Address::try_from(address.clone()).unwrap();
+        assert_eq!(
+            OwnedAddress::try_from(&converted).unwrap().as_address(),
+            address
+        );
+
+        let mailbox = Mailbox::try_from(r#""Doe, \"J\"" <j@x.com>"#).unwrap();
+        let converted = ::lettre::message::Mailbox::try_from(mailbox).unwrap();
+        assert_eq!(converted.name.as_deref(), Some(r#"Doe, "J""#));
+
+        let owned = OwnedMailbox::try_from(&converted).unwrap();
+        assert_eq!(
+            std::string::ToString::to_string(&owned.as_mailbox()),
+            r#""Doe, \"J\"" <j@x.com>"#
+        );
+    }
+
+    #[test]
+    fn it_builds_a_message() {
+        let headers = HeaderMap::from_iter([
+            Header::From("alice@example.com".try_into().unwrap()),
+            Header::Bcc("carol@example.com".try_into().unwrap()),
+            Header::Subject("Grüße"),
+            Header::Custom("X-Campaign", "spring"),
+        ]);
+        let message = ::lettre::Message::try_from(&Mail::new(headers, b"Hi!")).unwrap();
+
+        assert_eq!(
+            message.envelope().to(),
+            ["carol@example.com".parse().unwrap()]
+        );
+        let formatted = std::string::String::from_utf8(message.formatted()).unwrap();
+        assert!(formatted.contains("Subject: =?utf-8?b?R3LDvMOfZQ==?=\r\n"));
+        assert!(formatted.contains("X-Campaign: spring\r\n"));
+        assert!(!formatted.contains("Bcc"));
+        assert!(formatted.ends_with("\r\n\r\nHi!"));
+
+        let headers = HeaderMap::from_iter([Header::Custom("Bad Name", "x")]);
+        assert!(::lettre::Message::try_from(&Mail::new(headers, b"")).is_err());
+    }
+
+    #[test]
+    fn it_unquotes_display_names() {
+        assert_eq!(unquote(r#""a \"b\" \\ c""#), r#"a "b" \ c"#);
+        assert_eq!(unquote("John"), "John");
+    }
+}
@@ -0,0 +1,359 @@
+use core::ops::Range;
+
+use super::html;
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+/// Schemes that are never handed to a rewrite callback.
+const UNTOUCHED_SCHEMES: [&str; 2] = ["cid:", "mailto:"];
+
+/// HTML attributes that hold a URL.
+const URL_ATTRIBUTES: [&str; 3] = ["href", "src", "background"];
+
+/// A URL found in a text or HTML body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link<'a> {
+    url: &'a str,
+    span: Range<usize>,
+    quote: Option<u8>,
+}
+
+impl<'a> Link<'a> {
+    /// Returns the URL as it appears in the body. URLs from HTML are not entity-decoded.
+    pub fn as_str(&self) -> &'a str {
+        self.url
+    }
+
+    /// Returns the byte range of the URL within the body.
+    pub fn span(&self) -> Range<usize> {
+        self.span.clone()
+    }
+
+    /// Returns whether rewriting leaves this URL alone, which is the case for `cid:` and
+    /// `mailto:` URLs. Browsers ignore whitespace around URLs, so it is ignored here too.
+    pub fn is_untouched(&self) -> bool {
+        let url = self.url.trim_matches(|c: char| c.is_ascii_whitespace());
+        UNTOUCHED_SCHEMES.iter().any(|s| {
+            url.get(..s.len())
+                .is_some_and(|p| p.eq_ignore_ascii_case(s))
+        })
+    }
+}
+
+/// Iterator over the URLs in a plain-text body. Created by [`text_links`].
+#[derive(Debug, Clone)]
+pub struct TextLinks<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Iterator for TextLinks<'a> {
+    type Item = Link<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let bytes = self.text.as_bytes();
+
+        while let Some(found) = self.text[self.pos..].find("://") {
+            let separator = self.pos + found;
+            self.pos = separator + 3;
+
+            let start = bytes[..separator]
+                .iter()
+                .rposition(|b| !(b.is_ascii_alphanumeric() || matches!(b, b'+' | b'-' | b'.')))
+                .map_or(0, |i| i + 1);
+            let start = start
+                + bytes[start..separator]
+                    .iter()
+                    .take_while(|b| !b.is_ascii_alphabetic())
+                    .count();
+            if start == separator {
+                continue;
+            }
+
+            let end = self.text[separator..]
+                .find(|c: char| c.is_whitespace() || c.is_control() || matches!(c, '<' | '>' | '"'))
+                .map_or(self.text.len(), |i| separator + i);
+            let end = trim_trailing_punctuation(self.text, start, end);
+            if end <= separator + 3 {
+                continue;
+            }
+
+            self.pos = end;
+            return Some(Link {
+                url: &self.text[start..end],
+                span: start..end,
+                quote: None,
+            });
+        }
+
+        self.pos = self.text.len();
+        None
+    }
+}
+
+/// Strips punctuation that ends a sentence rather than the URL, keeping closing brackets that
+/// have a matching opening bracket inside the URL.
+fn trim_trailing_punctuation(text: &str, start: usize, mut end: usize) -> usize {
+    loop {
+        let url = &text[start..end];
+        let trim = match url.as_bytes().last() {
+            Some(b'.' | b',' | b';' | b':' | b'!' | b'?' | b'\'') => true,
+            Some(b')') => url.matches('(').count() < url.matches(')').count(),
+            Some(b']') => url.matches('[').count() < url.matches(']').count(),
+            _ => false,
+        };
+
+        if !trim {
+            return end;
+        }
+        end -= 1;
+    }
+}
+
+/// Finds all URLs in a plain-text body.
+///
+/// A URL starts with a scheme followed by `://` and ends at whitespace, an angle bracket or a
+/// double quote. Punctuation that ends the surrounding sentence is not part of the URL.
+///
+/// ```
+/// use brief::mail::links;
+///
+/// let text = "See https://example.com/docs. Or (https://example.com/faq)!";
+/// let urls: Vec<_> = links::text_links(text).map(|l| l.as_str()).collect();
+///
+/// assert_eq!(urls, ["https://example.com/docs", "https://example.com/faq"]);
+/// ```
+pub fn text_links(text: &str) -> TextLinks<'_> {
+    TextLinks { text, pos: 0 }
+}
+
+/// Iterator over the URLs in the attributes of an HTML body. Created by [`html_links`].
+#[derive(Debug, Clone)]
+pub struct HtmlLinks<'a> {
+    tags: html::Tags<'a>,
+    attributes: Option<html::Attributes<'a>>,
+}
+
+impl<'a> Iterator for HtmlLinks<'a> {
+    type Item = Link<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(attribute) = self.attributes.as_mut().and_then(Iterator::next) {
+                let is_url = URL_ATTRIBUTES
+                    .iter()
+                    .any(|a| attribute.name.eq_ignore_ascii_case(a));
+                if is_url && !attribute.value.trim().is_empty() {
+                    return Some(Link {
+                        url: attribute.value,
+                        span: attribute.span,
+                        quote: attribute.quote,
+                    });
+                }
+                continue;
+            }
+
+            let tag = self.tags.next()?;
+            self.attributes = (!tag.closing).then(|| tag.attributes());
+        }
+    }
+}
+
+/// Finds all URLs in the `href`, `src` and `background` attributes of an HTML body.
+///
+/// ```
+/// use brief::mail::links;
+///
+/// let html = r#"<a href="https://example.com/?a=1&amp;b=2">hi</a><img src=cid:logo>"#;
+/// let urls: Vec<_> = links::html_links(html).map(|l| l.as_str()).collect();
+///
+/// assert_eq!(urls, ["https://example.com/?a=1&amp;b=2", "cid:logo"]);
+/// ```
+pub fn html_links(html: &str) -> HtmlLinks<'_> {
+    HtmlLinks {
+        tags: html::tags(html),
+        attributes: None,
+    }
+}
+
+/// Rewrites the URLs in a plain-text body.
+///
+/// The callback receives every URL found by [`text_links`] and returns its replacement, or
+/// `None` to leave it as is.
+///
+/// ```
+/// use brief::mail::links;
+///
+/// let text = links::rewrite_text("Go to https://example.com.", |url| {
+///     Some(format!("https://track.example.net/?u={}", url.len()))
+/// });
+///
+/// assert_eq!(text, "Go to https://track.example.net/?u=19.");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn rewrite_text(text: &str, mut f: impl FnMut(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+
+    for link in text_links(text) {
+        if link.is_untouched() {
+            continue;
+        }
+
+        if let Some(url) = f(link.as_str()) {
+            out.push_str(&text[last..link.span.start]);
+            out.push_str(&url);
+            last = link.span.end;
+        }
+    }
+
+    out.push_str(&text[last..]);
+    out
+}
+
+/// Rewrites the URLs in the attributes of an HTML body.
+///
+/// The callback receives every URL found by [`html_links`], except `cid:` and `mailto:` URLs,
+/// with HTML entities decoded. It returns the replacement, which gets escaped and quoted, or
+/// `None` to leave the URL as is.
+///
+/// ```
+/// use brief::mail::links;
+///
+/// let html = links::rewrite_html(r#"<a href="https://example.com/?a=1&amp;b=2">hi</a>"#, |url| {
+///     assert_eq!(url, "https://example.com/?a=1&b=2");
+///     Some("https://safe.example.net/?u=1&v=2".into())
+/// });
+///
+/// assert_eq!(html, r#"<a href="https://safe.example.net/?u=1&amp;v=2">hi</a>"#);
+/// ```
+#[cfg(feature = "alloc")]
+pub fn rewrite_html(html: &str, mut f: impl FnMut(&str) -> Option<String>) -> String {
+    let mut out = String::with_capacity(html.len());
+    let mut last = 0;
+
+    for link in html_links(html) {
+        if link.is_untouched() {
+            continue;
+        }
+
+        let decoded = decode_entities(link.as_str().trim());
+        if let Some(url) = f(&decoded) {
+            out.push_str(&html[last..link.span.start]);
+
+            let quote = link.quote.map_or('"', char::from);
+            if link.quote.is_none() {
+                out.push(quote);
+            }
+            html::push_escaped(&mut out, &url);
+            if link.quote.is_none() {
+                out.push(quote);
+            }
+
+            last = link.span.end;
+        }
+    }
+
+    out.push_str(&html[last..]);
+    out
+}
+
+/// Decodes the character references that commonly appear in URLs and short texts.
+#[cfg(feature = "alloc")]
+pub(crate) fn decode_entities(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest.find(';').filter(|i| *i <= 10).and_then(|semi| {
+            let c = match &rest[1..semi] {
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "nbsp" => Some('\u{a0}'),
+                num if num.starts_with("#x") || num.starts_with("#X") => {
+                    u32::from_str_radix(&num[2..], 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                }
+                num if num.starts_with('#') => num[1..].parse().ok().and_then(char::from_u32),
+                _ => None,
+            };
+            c.map(|c| (c, semi))
+        });
+
+        match decoded {
+            Some((c, semi)) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{html_links, text_links};
+
+    #[test]
+    fn it_finds_links_in_text() {
+        let text = "a http://x.com/a_(b) b <https://y.org/?q=1>, c ftp://z.net/f.txt?\nd://";
+        let mut links = text_links(text);
+
+        assert_eq!(links.next().unwrap().as_str(), "http://x.com/a_(b)");
+        let link = links.next().unwrap();
+        assert_eq!(link.as_str(), "https://y.org/?q=1");
+        assert_eq!(&text[link.span()], "https://y.org/?q=1");
+        assert_eq!(links.next().unwrap().as_str(), "ftp://z.net/f.txt");
+        assert!(links.next().is_none());
+    }
+
+    #[test]
+    fn it_finds_links_in_html_attributes() {
+        let html = r#"<!-- <a href="https://hidden"> --><p title='https://no'>https://text</p>
+            <A HREF = 'mailto:a@b.c' >x</A><td background=bg.png><script>"<a href='https://js'>"</SCRIPT><img alt src="cid:a">"#;
+        let links: std::vec::Vec<_> = html_links(html).collect();
+
+        assert_eq!(links.len(), 3);
+        assert!(links[0].is_untouched());
+        assert_eq!(links[1].as_str(), "bg.png");
+        assert_eq!(links[2].as_str(), "cid:a");
+        assert!(links[2].is_untouched());
+
+        let links: std::vec::Vec<_> =
+            html_links("<a href=\" cid:x\"><a href='\tMAILTO:a@b.c'>").collect();
+        assert!(links.iter().all(|link| link.is_untouched()));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn it_rewrites_links_but_leaves_cid_and_mailto_alone() {
+        use super::{rewrite_html, rewrite_text};
+        use alloc::string::ToString;
+
+        let html = r#"<a href=https://a.com?x=1&amp;y=2>a</a><a href="mailto:x@y.z">b</a><img src="cid:c">"#;
+        let rewritten = rewrite_html(html, |url| Some(url.replace("a.com", "p.net")));
+        assert_eq!(
+            rewritten,
+            r#"<a href="https://p.net?x=1&amp;y=2">a</a><a href="mailto:x@y.z">b</a><img src="cid:c">"#
+        );
+
+        let text = rewrite_text("https://a.com and https://b.com", |url| {
+            url.contains('a').then(|| "X".to_string())
+        });
+        assert_eq!(text, "X and https://b.com");
+    }
+}
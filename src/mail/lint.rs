@@ -0,0 +1,514 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use super::{bidi, html};
+
+/// Linear light values for every sRGB channel value, as used by the WCAG luminance formula.
+#[rustfmt::skip]
+const SRGB_TO_LINEAR: [f32; 256] = [
+    0.000000, 0.000304, 0.000607, 0.000911, 0.001214, 0.001518,
+    0.001821, 0.002125, 0.002428, 0.002732, 0.003035, 0.003347,
+    0.003677, 0.004025, 0.004391, 0.004777, 0.005182, 0.005605,
+    0.006049, 0.006512, 0.006995, 0.007499, 0.008023, 0.008568,
+    0.009134, 0.009721, 0.010330, 0.010960, 0.011612, 0.012286,
+    0.012983, 0.013702, 0.014444, 0.015209, 0.015996, 0.016807,
+    0.017642, 0.018500, 0.019382, 0.020289, 0.021219, 0.022174,
+    0.023153, 0.024158, 0.025187, 0.026241, 0.027321, 0.028426,
+    0.029557, 0.030713, 0.031896, 0.033105, 0.034340, 0.035601,
+    0.036889, 0.038204, 0.039546, 0.040915, 0.042311, 0.043735,
+    0.045186, 0.046665, 0.048172, 0.049707, 0.051269, 0.052861,
+    0.054480, 0.056128, 0.057805, 0.059511, 0.061246, 0.063010,
+    0.064803, 0.066626, 0.068478, 0.070360, 0.072272, 0.074214,
+    0.076185, 0.078187, 0.080220, 0.082283, 0.084376, 0.086500,
+    0.088656, 0.090842, 0.093059, 0.095307, 0.097587, 0.099899,
+    0.102242, 0.104616, 0.107023, 0.109462, 0.111932, 0.114435,
+    0.116971, 0.119538, 0.122139, 0.124772, 0.127438, 0.130136,
+    0.132868, 0.135633, 0.138432, 0.141263, 0.144128, 0.147027,
+    0.149960, 0.152926, 0.155926, 0.158961, 0.162029, 0.165132,
+    0.168269, 0.171441, 0.174647, 0.177888, 0.181164, 0.184475,
+    0.187821, 0.191202, 0.194618, 0.198069, 0.201556, 0.205079,
+    0.208637, 0.212231, 0.215861, 0.219526, 0.223228, 0.226966,
+    0.230740, 0.234551, 0.238398, 0.242281, 0.246201, 0.250158,
+    0.254152, 0.258183, 0.262251, 0.266356, 0.270498, 0.274677,
+    0.278894, 0.283149, 0.287441, 0.291771, 0.296138, 0.300544,
+    0.304987, 0.309469, 0.313989, 0.318547, 0.323143, 0.327778,
+    0.332452, 0.337164, 0.341914, 0.346704, 0.351533, 0.356400,
+    0.361307, 0.366253, 0.371238, 0.376262, 0.381326, 0.386429,
+    0.391572, 0.396755, 0.401978, 0.407240, 0.412543, 0.417885,
+    0.423268, 0.428690, 0.434154, 0.439657, 0.445201, 0.450786,
+    0.456411, 0.462077, 0.467784, 0.473531, 0.479320, 0.485150,
+    0.491021, 0.496933, 0.502886, 0.508881, 0.514918, 0.520996,
+    0.527115, 0.533276, 0.539479, 0.545724, 0.552011, 0.558340,
+    0.564712, 0.571125, 0.577580, 0.584078, 0.590619, 0.597202,
+    0.603827, 0.610496, 0.617207, 0.623960, 0.630757, 0.637597,
+    0.644480, 0.651406, 0.658375, 0.665387, 0.672443, 0.679542,
+    0.686685, 0.693872, 0.701102, 0.708376, 0.715694, 0.723055,
+    0.730461, 0.737910, 0.745404, 0.752942, 0.760525, 0.768151,
+    0.775822, 0.783538, 0.791298, 0.799103, 0.806952, 0.814847,
+    0.822786, 0.830770, 0.838799, 0.846873, 0.854993, 0.863157,
+    0.871367, 0.879622, 0.887923, 0.896269, 0.904661, 0.913099,
+    0.921582, 0.930111, 0.938686, 0.947307, 0.955973, 0.964686,
+    0.973445, 0.982251, 0.991102, 1.000000,
+];
+
+/// Named colors that are commonly used in emails.
+const NAMED_COLORS: [(&str, Rgb); 18] = [
+    ("black", Rgb(0, 0, 0)),
+    ("white", Rgb(255, 255, 255)),
+    ("red", Rgb(255, 0, 0)),
+    ("green", Rgb(0, 128, 0)),
+    ("blue", Rgb(0, 0, 255)),
+    ("gray", Rgb(128, 128, 128)),
+    ("grey", Rgb(128, 128, 128)),
+    ("silver", Rgb(192, 192, 192)),
+    ("yellow", Rgb(255, 255, 0)),
+    ("orange", Rgb(255, 165, 0)),
+    ("navy", Rgb(0, 0, 128)),
+    ("maroon", Rgb(128, 0, 0)),
+    ("purple", Rgb(128, 0, 128)),
+    ("teal", Rgb(0, 128, 128)),
+    ("olive", Rgb(128, 128, 0)),
+    ("lime", Rgb(0, 255, 0)),
+    ("aqua", Rgb(0, 255, 255)),
+    ("fuchsia", Rgb(255, 0, 255)),
+];
+
+/// Elements that never have content or an end tag.
+const VOID_ELEMENTS: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// A problem found by a lint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Finding {
+    pub kind: FindingKind,
+    /// The byte range in the linted body the finding applies to.
+    pub span: Range<usize>,
+}
+
+/// The kinds of problems the lints can find.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[non_exhaustive]
+pub enum FindingKind {
+    /// An image without an `alt` attribute. Decorative images should have an empty one.
+    MissingAltText,
+    /// Text and background colors with a contrast ratio below the minimum.
+    InsufficientContrast { ratio: f32 },
+    /// A table without `role="presentation"` and without header cells, which screen readers
+    /// will announce as a data table even though it is most likely used for layout.
+    LayoutTableWithoutRole,
+    /// A font size, in pixels, below the minimum.
+    FontTooSmall { size: f32 },
+    /// A hard-coded white or near-white background, which some clients invert badly in dark
+    /// mode and others keep, leaving a glaring block on a dark screen.
+    WhiteBackground,
+    /// A bidi embedding or override character, or an isolate that isn't closed on the same
+    /// line. These can make text display differently from how it reads, which is used for
+    /// spoofing.
+    UnisolatedBidiControl { control: char },
+}
+
+/// Options for [`accessibility`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccessibilityOptions {
+    /// The minimum contrast ratio between text and background, 4.5 by default as required by
+    /// WCAG level AA.
+    pub min_contrast: f32,
+    /// The minimum font size in pixels, 14 by default.
+    pub min_font_size: f32,
+}
+
+impl Default for AccessibilityOptions {
+    fn default() -> Self {
+        Self {
+            min_contrast: 4.5,
+            min_font_size: 14.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Rgb(u8, u8, u8);
+
+impl Rgb {
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().trim_end_matches("!important").trim_end();
+
+        if let Some(hex) = value.strip_prefix('#') {
+            let digit = |i: usize| u8::from_str_radix(hex.get(i..i + 1)?, 16).ok();
+            let pair = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+
+            return match hex.len() {
+                3 => Some(Rgb(digit(0)? * 17, digit(1)? * 17, digit(2)? * 17)),
+                6 => Some(Rgb(pair(0)?, pair(2)?, pair(4)?)),
+                _ => None,
+            };
+        }
+
+        let lower = value.get(..4).map(|p| p.to_ascii_lowercase());
+        if let Some(args) = lower
+            .filter(|p| p.starts_with("rgb"))
+            .and_then(|_| value.split_once('('))
+            .and_then(|(_, rest)| rest.strip_suffix(')'))
+        {
+            let mut channels = args.split([',', ' ', '/']).filter(|c| !c.is_empty());
+            let mut channel = || channels.next()?.trim().parse::<u8>().ok();
+            return Some(Rgb(channel()?, channel()?, channel()?));
+        }
+
+        NAMED_COLORS
+            .iter()
+            .find(|(name, _)| value.eq_ignore_ascii_case(name))
+            .map(|(_, rgb)| *rgb)
+    }
+
+    fn luminance(self) -> f32 {
+        0.2126 * SRGB_TO_LINEAR[usize::from(self.0)]
+            + 0.7152 * SRGB_TO_LINEAR[usize::from(self.1)]
+            + 0.0722 * SRGB_TO_LINEAR[usize::from(self.2)]
+    }
+
+    fn contrast(self, other: Rgb) -> f32 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+}
+
+/// Returns the value of a property in an inline style, ignoring case.
+fn style_property<'a>(style: &'a str, property: &str) -> Option<&'a str> {
+    style
+        .rsplit(';')
+        .filter_map(|declaration| declaration.split_once(':'))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case(property))
+        .map(|(_, value)| value.trim())
+        .next()
+}
+
+/// Parses a font size in pixels, converting points and assuming 16px for `em` and `rem`.
+fn font_size(value: &str) -> Option<f32> {
+    let value = value.trim_end_matches("!important").trim_end();
+    let (number, factor) = if let Some(px) = value.strip_suffix("px") {
+        (px, 1.0)
+    } else if let Some(pt) = value.strip_suffix("pt") {
+        (pt, 4.0 / 3.0)
+    } else if let Some(em) = value
+        .strip_suffix("rem")
+        .or_else(|| value.strip_suffix("em"))
+    {
+        (em, 16.0)
+    } else {
+        return None;
+    };
+
+    number.trim().parse::<f32>().ok().map(|n| n * factor)
+}
+
+/// Returns the background color a tag sets, from its inline style or `bgcolor` attribute.
+fn background(tag: &html::Tag<'_>) -> Option<Rgb> {
+    let style = tag.attribute("style").map_or("", |a| a.value);
+
+    style_property(style, "background-color")
+        .and_then(Rgb::parse)
+        .or_else(|| {
+            style_property(style, "background")
+                .and_then(|b| b.split_whitespace().find_map(Rgb::parse))
+        })
+        .or_else(|| tag.attribute("bgcolor").and_then(|a| Rgb::parse(a.value)))
+}
+
+/// An open element and the colors it sets or inherits.
+struct Element<'a> {
+    name: &'a str,
+    color: Option<Rgb>,
+    background: Option<Rgb>,
+}
+
+/// An open table and whether it is marked or used as a data table.
+struct Table {
+    span: Range<usize>,
+    ok: bool,
+}
+
+/// Checks an HTML body for common accessibility problems: images without alt text, text with
+/// too little contrast to its background, layout tables that aren't marked as such and fonts
+/// that are too small to read.
+///
+/// Colors and font sizes are only taken from inline styles and the `color` and `bgcolor`
+/// attributes, as stylesheets are poorly supported by mail clients anyway.
+///
+/// ```
+/// use brief::mail::lint::{self, AccessibilityOptions, FindingKind};
+///
+/// let html = r#"<table role="presentation"><tr><td style="font-size: 10px">
+///     <img src="cid:logo"></td></tr></table>"#;
+/// let findings = lint::accessibility(html, AccessibilityOptions::default());
+///
+/// assert_eq!(findings[0].kind, FindingKind::FontTooSmall { size: 10.0 });
+/// assert_eq!(findings[1].kind, FindingKind::MissingAltText);
+/// ```
+pub fn accessibility(html: &str, options: AccessibilityOptions) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut elements: Vec<Element<'_>> = Vec::new();
+    let mut tables: Vec<Table> = Vec::new();
+
+    for tag in html::tags(html) {
+        if tag.closing {
+            if let Some(i) = elements.iter().rposition(|e| tag.is(e.name)) {
+                elements.truncate(i);
+            }
+            if tag.is("table") {
+                if let Some(table) = tables.pop().filter(|t| !t.ok) {
+                    findings.push(Finding {
+                        kind: FindingKind::LayoutTableWithoutRole,
+                        span: table.span,
+                    });
+                }
+            }
+            continue;
+        }
+
+        let span = tag.span.clone();
+
+        if tag.is("img") && tag.attribute("alt").is_none() {
+            findings.push(Finding {
+                kind: FindingKind::MissingAltText,
+                span: span.clone(),
+            });
+        }
+
+        if tag.is("table") {
+            let role = tag.attribute("role").map(|a| a.value.trim());
+            let ok = role.is_some_and(|r| {
+                r.eq_ignore_ascii_case("presentation") || r.eq_ignore_ascii_case("none")
+            });
+            tables.push(Table {
+                span: span.clone(),
+                ok,
+            });
+        } else if tag.is("th") {
+            if let Some(table) = tables.last_mut() {
+                table.ok = true;
+            }
+        }
+
+        let style = tag.attribute("style").map_or("", |a| a.value);
+        let color = style_property(style, "color")
+            .or_else(|| tag.attribute("color").map(|a| a.value))
+            .and_then(Rgb::parse);
+        let background = background(&tag);
+
+        let inherited = elements.last();
+        let effective_color = color.or(inherited.and_then(|e| e.color));
+        let effective_background = background.or(inherited.and_then(|e| e.background));
+
+        if color.is_some() || background.is_some() {
+            if let (Some(fg), Some(bg)) = (effective_color, effective_background) {
+                let ratio = fg.contrast(bg);
+                if ratio < options.min_contrast {
+                    findings.push(Finding {
+                        kind: FindingKind::InsufficientContrast { ratio },
+                        span: span.clone(),
+                    });
+                }
+            }
+        }
+
+        if let Some(size) = style_property(style, "font-size").and_then(font_size) {
+            if size < options.min_font_size {
+                findings.push(Finding {
+                    kind: FindingKind::FontTooSmall { size },
+                    span: span.clone(),
+                });
+            }
+        }
+
+        if !VOID_ELEMENTS.iter().any(|v| tag.is(v)) {
+            elements.push(Element {
+                name: tag.name,
+                color: effective_color,
+                background: effective_background,
+            });
+        }
+    }
+
+    findings.extend(tables.into_iter().filter(|t| !t.ok).map(|t| Finding {
+        kind: FindingKind::LayoutTableWithoutRole,
+        span: t.span,
+    }));
+
+    findings
+}
+
+/// Checks an HTML body for problems with dark mode rendering, which currently means
+/// hard-coded white backgrounds. See [`dark_mode::prepare`](super::dark_mode::prepare) for
+/// adding the markup that lets clients render a dark version.
+///
+/// ```
+/// use brief::mail::lint::{self, FindingKind};
+///
+/// let findings = lint::dark_mode(r##"<body bgcolor="#FFFFFF"><p>Hi</p></body>"##);
+/// assert_eq!(findings[0].kind, FindingKind::WhiteBackground);
+/// ```
+pub fn dark_mode(html: &str) -> Vec<Finding> {
+    html::tags(html)
+        .filter(|tag| !tag.closing)
+        .filter(|tag| background(tag).is_some_and(|bg| bg.luminance() >= 0.9))
+        .map(|tag| Finding {
+            kind: FindingKind::WhiteBackground,
+            span: tag.span,
+        })
+        .collect()
+}
+
+/// Checks text for bidi control characters that can reorder the text around them: embeddings
+/// and overrides, and isolates that aren't closed on the same line or that close nothing.
+/// Isolates created by [`bidi::isolate`] are fine.
+///
+/// ```
+/// use brief::mail::lint::{self, FindingKind};
+///
+/// let findings = lint::bidi("Pay to \u{202e}3202 NAVI");
+/// assert_eq!(findings[0].kind, FindingKind::UnisolatedBidiControl { control: '\u{202e}' });
+/// ```
+pub fn bidi(text: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut open: Vec<(usize, char)> = Vec::new();
+    let finding = |at: usize, control: char| Finding {
+        kind: FindingKind::UnisolatedBidiControl { control },
+        span: at..at + control.len_utf8(),
+    };
+
+    for (i, c) in text.char_indices() {
+        if c == '\n' {
+            findings.extend(open.drain(..).map(|(at, c)| finding(at, c)));
+        } else if bidi::is_embedding_or_override(c) {
+            findings.push(finding(i, c));
+        } else if bidi::is_isolate_initiator(c) {
+            open.push((i, c));
+        } else if bidi::is_isolate_terminator(c) && open.pop().is_none() {
+            findings.push(finding(i, c));
+        }
+    }
+    findings.extend(open.drain(..).map(|(at, c)| finding(at, c)));
+
+    findings.sort_by_key(|f| f.span.start);
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{accessibility, bidi, dark_mode, AccessibilityOptions, FindingKind, Rgb};
+
+    fn kinds(html: &str) -> std::vec::Vec<FindingKind> {
+        accessibility(html, AccessibilityOptions::default())
+            .into_iter()
+            .map(|f| f.kind)
+            .collect()
+    }
+
+    #[test]
+    fn it_parses_colors() {
+        assert_eq!(Rgb::parse("#FfF"), Some(Rgb(255, 255, 255)));
+        assert_eq!(Rgb::parse("#102030 !important"), Some(Rgb(16, 32, 48)));
+        assert_eq!(Rgb::parse("RGB(1, 2, 3)"), Some(Rgb(1, 2, 3)));
+        assert_eq!(Rgb::parse("rgba(1 2 3 / 50%)"), Some(Rgb(1, 2, 3)));
+        assert_eq!(Rgb::parse("Navy"), Some(Rgb(0, 0, 128)));
+        assert_eq!(Rgb::parse("#12"), None);
+    }
+
+    #[test]
+    fn it_computes_wcag_contrast_ratios() {
+        let ratio = Rgb(0, 0, 0).contrast(Rgb(255, 255, 255));
+        assert!((ratio - 21.0).abs() < 0.01);
+
+        let ratio = Rgb(119, 119, 119).contrast(Rgb(255, 255, 255));
+        assert!((ratio - 4.48).abs() < 0.01);
+    }
+
+    #[test]
+    fn it_flags_images_without_alt_text() {
+        assert_eq!(kinds(r#"<img src="a.png">"#), [FindingKind::MissingAltText]);
+        assert!(kinds(r#"<img src="a.png" alt="">"#).is_empty());
+    }
+
+    #[test]
+    fn it_flags_low_contrast_including_inherited_colors() {
+        let html = r##"<td bgcolor="#ffffff"><span style="color: #ccc">faint</span></td>
+            <p style="background: url(x.png) #000; color: white">fine</p>"##;
+
+        assert!(matches!(
+            kinds(html)[..],
+            [FindingKind::InsufficientContrast { ratio }] if ratio < 2.0
+        ));
+    }
+
+    #[test]
+    fn it_flags_layout_tables_without_a_role() {
+        assert_eq!(
+            kinds("<table><tr><td>x</td></tr></table>"),
+            [FindingKind::LayoutTableWithoutRole]
+        );
+        assert!(kinds("<table><tr><th>h</th></tr></table>").is_empty());
+        assert!(kinds(r#"<table role="none"><tr><td>x</td></tr></table>"#).is_empty());
+    }
+
+    #[test]
+    fn it_flags_small_fonts() {
+        assert_eq!(
+            kinds(r#"<p style="font-size:9pt">x</p><p style="font-size: 1em">y</p>"#),
+            [FindingKind::FontTooSmall { size: 12.0 }]
+        );
+    }
+
+    #[test]
+    fn it_flags_white_backgrounds_for_dark_mode() {
+        let html = r##"<div style="background:#fafafa"><td bgcolor="#eee"></td><p style="background-color: white">"##;
+        let findings = dark_mode(html);
+
+        assert_eq!(findings.len(), 2);
+        assert_eq!(
+            &html[findings[1].span.clone()],
+            r#"<p style="background-color: white">"#
+        );
+    }
+
+    #[test]
+    fn it_flags_unisolated_bidi_controls() {
+        let text = "ok \u{2068}name\u{2069}\nbad \u{2067}open\nstray\u{2069} \u{202b}x\u{202c}";
+        let controls: std::vec::Vec<_> = bidi(text)
+            .into_iter()
+            .map(|f| (f.kind, &text[f.span]))
+            .collect();
+
+        assert_eq!(
+            controls,
+            [
+                (
+                    FindingKind::UnisolatedBidiControl {
+                        control: '\u{2067}'
+                    },
+                    "\u{2067}"
+                ),
+                (
+                    FindingKind::UnisolatedBidiControl {
+                        control: '\u{2069}'
+                    },
+                    "\u{2069}"
+                ),
+                (
+                    FindingKind::UnisolatedBidiControl {
+                        control: '\u{202b}'
+                    },
+                    "\u{202b}"
+                ),
+                (
+                    FindingKind::UnisolatedBidiControl {
+                        control: '\u{202c}'
+                    },
+                    "\u{202c}"
+                ),
+            ]
+        );
+    }
+}
@@ -1,20 +1,2126 @@
-use super::Header;
+use core::{
+    cell::OnceCell,
+    fmt::{self, Write},
+    marker::PhantomData,
+};
 
-pub struct MailData<'a> {
-    headers: &'a [Header],
-    data: &'a [u8],
+use alloc::{
+    borrow::Cow,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use super::{
+    encoded_word, filename, header, subject, transfer_encoding::TransferEncoding, Address,
+    DateTime, Header, HeaderMap, Mailbox, MailboxList, MessageId, ParseMailboxListError,
+};
+
+/// The content type of a plain text body.
+const TEXT: &str = "text/plain; charset=utf-8";
+/// The content type of an HTML body.
+const HTML: &str = "text/html; charset=utf-8";
+
+/// The number of body lines the pretty-printer shows, unless the alternate flag is set.
+const PREVIEW_LINES: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SerializeMailError {
+    /// A header has a line longer than 998 octets that can't be folded. Contains the length of
+    /// the line.
+    LineTooLong(usize),
+    /// A header has a CR or LF that isn't part of a CRLF followed by whitespace, so it would
+    /// end the header and start another.
+    BareLineBreak,
+    /// A custom header has a name that is empty, has a colon, or has a character that isn't
+    /// printable ASCII.
+    InvalidHeaderName,
 }
 
+impl core::fmt::Display for SerializeMailError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::LineTooLong(len) => write!(
+                f,
+                "header line of {len} octets is longer than {} octets",
+                header::MAX_LINE_LENGTH
+            ),
+            Self::BareLineBreak => f.write_str("header has a line break that isn't a fold"),
+            Self::InvalidHeaderName => f.write_str("header has an invalid name"),
+        }
+    }
+}
+
+impl core::error::Error for SerializeMailError {}
+
+/// The mail already has a `Delivered-To` header with the recipient, so it went around in a loop.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DeliveryLoopError;
+
+impl fmt::Display for DeliveryLoopError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("mail was already delivered to the recipient")
+    }
+}
+
+impl core::error::Error for DeliveryLoopError {}
+
+/// Represents a mail: its headers and its body
+///
+/// A mail is serialized as an RFC 5322 message, with CRLF line endings and a blank line between
+/// the headers and the body:
+/// ```
+/// use brief::mail::{Header, HeaderMap, Mail};
+///
+/// let mut headers = HeaderMap::new();
+/// headers.append(Header::From("alice@example.com".try_into().unwrap()));
+/// headers.append(Header::Subject("Hello"));
+///
+/// let mail = Mail::new(headers, b"Hi Bob,\nhow are you?");
+/// assert_eq!(
+///     mail.to_bytes().unwrap(),
+///     b"From: alice@example.com\r\nSubject: Hello\r\n\r\nHi Bob,\r\nhow are you?"
+/// );
+/// ```
+///
+/// Headers are folded to keep their lines within 78 octets where possible.
+///
+/// RFC 5322 requires every message to have a `Date` and a `From` header, which are not added
+/// automatically.
+///
+/// A mail can also carry [metadata](Mail::metadata), like internal order or user IDs, which is
+/// never serialized.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct Mail<'a> {
-    data: MailData<'a>,
+    headers: HeaderMap<'a>,
+    body: &'a [u8],
+    metadata: BTreeMap<String, String>,
+}
+
+impl<'a> Mail<'a> {
+    pub fn new(headers: HeaderMap<'a>, body: &'a [u8]) -> Self {
+        Self {
+            headers,
+            body,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn headers(&self) -> &HeaderMap<'a> {
+        &self.headers
+    }
+
+    pub fn headers_mut(&mut self) -> &mut HeaderMap<'a> {
+        &mut self.headers
+    }
+
+    pub fn body(&self) -> &'a [u8] {
+        self.body
+    }
+
+    /// Returns the metadata of the mail: opaque key-value pairs for the application, which are
+    /// not part of the message.
+    ///
+    /// ```
+    /// use brief::mail::{HeaderMap, Mail};
+    ///
+    /// let mut mail = Mail::new(HeaderMap::new(), b"Hi!");
+    /// mail.metadata_mut().insert("order".into(), "1234".into());
+    ///
+    /// assert_eq!(mail.metadata()["order"], "1234");
+    /// assert_eq!(mail.to_bytes().unwrap(), b"\r\nHi!");
+    /// ```
+    pub fn metadata(&self) -> &BTreeMap<String, String> {
+        &self.metadata
+    }
+
+    pub fn metadata_mut(&mut self) -> &mut BTreeMap<String, String> {
+        &mut self.metadata
+    }
+
+    /// Sends the mail to a catch-all instead of its recipients, so a staging environment can't
+    /// mail real customers. The `To`, `Cc` and `Bcc` headers are replaced by a `To` header with
+    /// the catch-all, and every original list is kept in an `X-Original-To` header.
+    ///
+    /// ```
+    /// use brief::mail::{Header, HeaderMap, Mail, MailboxList};
+    ///
+    /// let headers = HeaderMap::from_iter([
+    ///     Header::To("alice@example.com".try_into().unwrap()),
+    ///     Header::Cc("bob@example.com".try_into().unwrap()),
+    /// ]);
+    /// let mut mail = Mail::new(headers, b"");
+    /// mail.redirect_recipients(MailboxList::try_from("staging@example.org").unwrap());
+    ///
+    /// assert_eq!(
+    ///     mail.to_bytes().unwrap(),
+    ///     b"To: staging@example.org\r\n\
+    ///       X-Original-To: alice@example.com\r\n\
+    ///       X-Original-To: bob@example.com\r\n\r\n"
+    /// );
+    /// ```
+    pub fn redirect_recipients(&mut self, catch_all: MailboxList<'a>) {
+        let originals: Vec<_> = self
+            .headers
+            .iter()
+            .filter_map(|h| match h {
+                Header::To(list) | Header::Cc(list) | Header::Bcc(list) => {
+                    Some(Header::OriginalTo(*list))
+                }
+                _ => None,
+            })
+            .collect();
+
+        for name in ["To", "Cc", "Bcc"] {
+            self.headers.remove(name);
+        }
+        self.headers.append(Header::To(catch_all));
+        self.headers.extend(originals);
+    }
+
+    /// Records the delivery of the mail to a local mailbox or a forwarding address, by adding a
+    /// `Delivered-To` header above all others. Fails without changing the mail if it already has
+    /// a `Delivered-To` header with the recipient, since delivering it again would loop.
+    ///
+    /// Domains are compared ignoring case, users exactly.
+    ///
+    /// ```
+    /// use brief::mail::{Address, DeliveryLoopError, Header, HeaderMap, Mail};
+    ///
+    /// let mut mail = Mail::new(HeaderMap::from_iter([Header::Subject("Hi")]), b"");
+    /// let alias = Address::try_from("team@example.com").unwrap();
+    ///
+    /// mail.deliver_to(alias.clone()).unwrap();
+    /// mail.deliver_to("alice@example.org".try_into().unwrap()).unwrap();
+    /// assert_eq!(
+    ///     mail.to_bytes().unwrap(),
+    ///     b"Delivered-To: alice@example.org\r\n\
+    ///       Delivered-To: team@example.com\r\n\
+    ///       Subject: Hi\r\n\r\n"
+    /// );
+    ///
+    /// assert_eq!(mail.deliver_to(alias), Err(DeliveryLoopError));
+    /// ```
+    pub fn deliver_to(&mut self, recipient: Address<'a>) -> Result<(), DeliveryLoopError> {
+        let delivered = self.headers.iter().any(|h| match h {
+            Header::DeliveredTo(address) => {
+                address.user() == recipient.user()
+                    && address.domain().eq_ignore_ascii_case(recipient.domain())
+            }
+            _ => false,
+        });
+        if delivered {
+            return Err(DeliveryLoopError);
+        }
+
+        self.headers.prepend(Header::DeliveredTo(recipient));
+        Ok(())
+    }
+
+    /// Starts a reply to the mail. The builder has the `Reply-To` mailboxes, or else the `From`
+    /// mailboxes, as recipients, the subject with a `Re:` prefix, and the `In-Reply-To` and
+    /// `References` headers that thread the reply. Returns `None` if the mail has neither a
+    /// `Reply-To` nor a `From` header.
+    ///
+    /// ```
+    /// use brief::mail::{Address, DateTime, Header, HeaderMap, Mail, MessageId};
+    ///
+    /// let headers = HeaderMap::from_iter([
+    ///     Header::From("Alice <alice@example.com>".try_into().unwrap()),
+    ///     Header::Subject("Lunch"),
+    ///     Header::MessageId("1@example.com"),
+    /// ]);
+    /// let original = Mail::new(headers, b"Are you free today?");
+    ///
+    /// let reply = original
+    ///     .reply()
+    ///     .unwrap()
+    ///     .from(Address::try_from("bob@example.com").unwrap())
+    ///     .date(DateTime::from_timestamp(1704067200, 0).unwrap())
+    ///     .message_id(MessageId::try_from("<2@example.com>").unwrap())
+    ///     .text_body("Sure!");
+    ///
+    /// let mail = reply.build().unwrap();
+    /// let headers: Vec<_> = mail.headers().iter().map(|h| h.to_string()).collect();
+    /// assert_eq!(
+    ///     headers[..6],
+    ///     [
+    ///         "From: bob@example.com",
+    ///         "To: Alice <alice@example.com>",
+    ///         "Subject: Re: Lunch",
+    ///         "Date: Mon, 1 Jan 2024 00:00:00 +0000",
+    ///         "Message-ID: <2@example.com>",
+    ///         "In-Reply-To: <1@example.com>",
+    ///     ]
+    /// );
+    /// ```
+    pub fn reply(&self) -> Option<MailBuilder<'a, Missing, Present>> {
+        let recipients = self
+            .headers
+            .typed::<header::ReplyTo>()
+            .or_else(|| self.headers.typed::<header::From>())?;
+
+        let mut builder = self.thread(MailBuilder::new(), subject::is_reply, "Re");
+        for mailbox in &recipients {
+            push(&mut builder.to, mailbox);
+        }
+
+        if let Some(id) = self.message_id() {
+            push_id(&mut builder.in_reply_to, id);
+        }
+
+        Some(builder.into_state())
+    }
+
+    /// Starts forwarding the mail. The builder has the body of the mail as it was encoded,
+    /// with its content type, the subject with a `Fwd:` prefix, and a `References` header that
+    /// threads it; only the recipients have to be added.
+    ///
+    /// ```
+    /// use brief::mail::{Address, DateTime, Header, HeaderMap, Mail};
+    ///
+    /// let headers = HeaderMap::from_iter([Header::Subject("Fwd: Lunch")]);
+    /// let original = Mail::new(headers, b"Are you free today?");
+    ///
+    /// let forward = original
+    ///     .forward()
+    ///     .from(Address::try_from("bob@example.com").unwrap())
+    ///     .to(Address::try_from("carol@example.com").unwrap())
+    ///     .date(DateTime::from_timestamp(1704067200, 0).unwrap());
+    ///
+    /// let mail = forward.build().unwrap();
+    /// assert_eq!(mail.headers().get("Subject"), Some(&Header::Subject("Fwd: Lunch")));
+    /// assert_eq!(mail.body(), b"Are you free today?");
+    /// ```
+    pub fn forward(&self) -> MailBuilder<'a> {
+        let mut builder = MailBuilder::new().body(self.body);
+
+        let content_type = self.headers.typed::<header::ContentType>();
+        let encoding = self.headers.typed::<header::ContentTransferEncoding>();
+        if content_type.is_some() || encoding.is_some() {
+            builder.body_type = content_type;
+            // without a known encoding, the body is sent as it is
+            builder.body_encoding = Some(encoding.and_then(|e| e.parse().ok()).unwrap_or(
+                match self.body.is_ascii() {
+                    true => TransferEncoding::SevenBit,
+                    false => TransferEncoding::EightBit,
+                },
+            ));
+        }
+
+        self.thread(builder, subject::is_forward, "Fwd")
+    }
+
+    /// Prefixes the subject of the builder and adds the `References` of a reply or forward.
+    fn thread<F, R, D>(
+        &self,
+        mut builder: MailBuilder<'a, F, R, D>,
+        is_prefixed: fn(&str) -> bool,
+        prefix: &str,
+    ) -> MailBuilder<'a, F, R, D> {
+        if let Some(subject) = self.headers.typed::<header::Subject>() {
+            builder.subject = Some(match is_prefixed(subject) {
+                true => Cow::Borrowed(subject),
+                false => Cow::Owned(format!("{prefix}: {subject}")),
+            });
+        }
+
+        let references = self.headers.typed::<header::References>().unwrap_or("");
+        for id in MessageId::parse_list(references).flatten() {
+            push_id(&mut builder.references, id);
+        }
+        if let Some(id) = self.message_id() {
+            push_id(&mut builder.references, id);
+        }
+
+        builder
+    }
+
+    /// Returns the `Message-ID` of the mail, if it has a valid one.
+    fn message_id(&self) -> Option<MessageId<'a>> {
+        let id = self.headers.typed::<header::MessageId>()?;
+        MessageId::parse_inner(id).ok()
+    }
+
+    /// Compares the mail with another, for example to check that a migration from another
+    /// library produces the same messages.
+    ///
+    /// ```
+    /// use brief::mail::{diff::ChangedHeader, Header, HeaderMap, Mail};
+    ///
+    /// let old = Mail::new(HeaderMap::from_iter([Header::Subject("Hello")]), b"Hi!");
+    /// let new = Mail::new(HeaderMap::from_iter([Header::Subject("Hey")]), b"Hi!");
+    /// let diff = old.diff(&new);
+    ///
+    /// assert_eq!(
+    ///     diff.changed_headers,
+    ///     [ChangedHeader { name: "Subject".into(), old: "Hello".into(), new: "Hey".into() }]
+    /// );
+    /// assert!(diff.added_headers.is_empty());
+    /// ```
+    #[cfg(feature = "diff")]
+    pub fn diff(&self, other: &Mail<'_>) -> super::diff::MailDiff {
+        super::diff::mail(self, other)
+    }
+
+    /// Returns whether the mail contains UTF-8 that can't be encoded, like an address with
+    /// UTF-8, so it can only be sent to servers that support the SMTPUTF8 extension.
+    ///
+    /// ```
+    /// use brief::mail::{Header, HeaderMap, Mail, MailboxList};
+    ///
+    /// let to = MailboxList::parse_international("jöran@bücher.de").unwrap();
+    /// let headers = HeaderMap::from_iter([Header::To(to), Header::Subject("Grüße")]);
+    ///
+    /// assert!(Mail::new(headers, b"").requires_smtputf8());
+    /// ```
+    pub fn requires_smtputf8(&self) -> bool {
+        self.headers.iter().any(|h| h.requires_smtputf8())
+    }
+
+    /// Serializes the mail as an RFC 5322 message. Fails if a header has a line that is longer
+    /// than 998 octets and can't be folded, a line break that isn't a fold, or an invalid name.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SerializeMailError> {
+        let head = self.head()?;
+        let mut bytes = Vec::with_capacity(head.len() + self.body.len());
+        bytes.extend_from_slice(head.as_bytes());
+
+        let result: Result<(), core::convert::Infallible> = write_crlf(self.body, &mut |b| {
+            bytes.extend_from_slice(b);
+            Ok(())
+        });
+        let Ok(()) = result;
+
+        Ok(bytes)
+    }
+
+    /// Serializes the mail as an RFC 5322 message into a writer. Fails with
+    /// [`InvalidData`](std::io::ErrorKind::InvalidData) if a header has a line that is longer
+    /// than 998 octets and can't be folded.
+    ///
+    /// ```
+    /// use brief::mail::{Header, HeaderMap, Mail};
+    ///
+    /// let headers = HeaderMap::from_iter([Header::Subject("Hello")]);
+    /// let mut out = Vec::new();
+    /// Mail::new(headers, b"Hi!").write_to(&mut out).unwrap();
+    ///
+    /// assert_eq!(out, b"Subject: Hello\r\n\r\nHi!");
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn write_to<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        let head = self
+            .head()
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+
+        writer.write_all(head.as_bytes())?;
+        write_crlf(self.body, &mut |b| writer.write_all(b))
+    }
+
+    /// Serializes the mail into an async writer, like [`Mail::write_to`].
+    ///
+    /// ```
+    /// use brief::mail::{Header, HeaderMap, Mail};
+    ///
+    /// # async fn send(socket: &mut tokio::io::DuplexStream) -> std::io::Result<()> {
+    /// let headers = HeaderMap::from_iter([Header::Subject("Hello")]);
+    /// Mail::new(headers, b"Hi!").write_to_async(socket).await
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub async fn write_to_async<W: tokio::io::AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+    ) -> std::io::Result<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let head = self
+            .head()
+            .map_err(|error| std::io::Error::new(std::io::ErrorKind::InvalidData, error))?;
+
+        writer.write_all(head.as_bytes()).await?;
+        for chunk in CrlfChunks::new(self.body) {
+            writer.write_all(chunk).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Serializes the folded headers and the blank line that ends them.
+    fn head(&self) -> Result<String, SerializeMailError> {
+        let mut head = String::new();
+        for h in &self.headers {
+            if !header::is_valid_name(h.name()) {
+                return Err(SerializeMailError::InvalidHeaderName);
+            }
+            let folded = header::fold(&format!("{}", h))?;
+            head.push_str(&folded);
+            head.push_str("\r\n");
+        }
+        head.push_str("\r\n");
+
+        Ok(head)
+    }
+}
+
+/// Writes a body with CRLF line breaks, since lines may end in a bare LF or CR.
+fn write_crlf<E>(body: &[u8], write: &mut impl FnMut(&[u8]) -> Result<(), E>) -> Result<(), E> {
+    CrlfChunks::new(body).try_for_each(write)
+}
+
+/// Iterator over the chunks of a body with CRLF line breaks, which replace bare LFs and CRs.
+struct CrlfChunks<'a> {
+    rest: Option<&'a [u8]>,
+    line_break: bool,
+}
+
+impl<'a> CrlfChunks<'a> {
+    fn new(body: &'a [u8]) -> Self {
+        Self {
+            rest: Some(body),
+            line_break: false,
+        }
+    }
+}
+
+impl<'a> Iterator for CrlfChunks<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if core::mem::take(&mut self.line_break) {
+            return Some(b"\r\n");
+        }
+
+        let rest = self.rest?;
+        // a line break at the start of the rest follows a bare line break, so it is bare too
+        let bare = rest.iter().enumerate().position(|(i, b)| match b {
+            b'\n' => i == 0 || rest[i - 1] != b'\r',
+            b'\r' => rest.get(i + 1) != Some(&b'\n'),
+            _ => false,
+        });
+
+        match bare {
+            Some(i) => {
+                self.rest = Some(&rest[i + 1..]);
+                self.line_break = true;
+                Some(&rest[..i])
+            }
+            None => {
+                self.rest = None;
+                Some(rest)
+            }
+        }
+    }
+}
+
+/// Pretty-prints the mail for debugging: a table of the headers with encoded words decoded,
+/// a summary of the body, and its first lines. The alternate flag (`{:#}`) shows the whole
+/// body.
+///
+/// ```
+/// use brief::mail::{Header, HeaderMap, Mail};
+///
+/// let headers = HeaderMap::from_iter([
+///     Header::From("alice@example.com".try_into().unwrap()),
+///     Header::Subject("Grüße"),
+/// ]);
+/// let mail = Mail::new(headers, b"Hi Bob,\nhow are you?");
+///
+/// assert_eq!(
+///     format!("{mail}"),
+///     "From:    alice@example.com\n\
+///      Subject: Grüße\n\
+///      --- body: text/plain, 7bit, 20 bytes ---\n\
+///      Hi Bob,\n\
+///      how are you?\n"
+/// );
+/// ```
+impl fmt::Display for Mail<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .headers
+            .iter()
+            .map(|h| h.name().len())
+            .max()
+            .unwrap_or(0)
+            + 1;
+        for h in &self.headers {
+            let body = header::unfold(&h.body().to_string()).to_string();
+            let name = format!("{}:", h.name());
+            writeln!(f, "{name:width$} {}", encoded_word::decode(&body))?;
+        }
+
+        let value = |name| {
+            self.headers
+                .get(name)
+                .map(|h| header::unfold(&h.body().to_string()).to_string())
+        };
+        writeln!(
+            f,
+            "--- body: {}, {}, {} bytes ---",
+            value("Content-Type").unwrap_or_else(|| "text/plain".into()),
+            value("Content-Transfer-Encoding").unwrap_or_else(|| "7bit".into()),
+            self.body.len()
+        )?;
+
+        let Ok(body) = core::str::from_utf8(self.body) else {
+            return writeln!(f, "(binary)");
+        };
+        let limit = match f.alternate() {
+            true => usize::MAX,
+            false => PREVIEW_LINES,
+        };
+        for line in body.lines().take(limit) {
+            writeln!(f, "{}", line.trim_end_matches('\r'))?;
+        }
+
+        let more = body.lines().count().saturating_sub(limit);
+        if more > 0 {
+            writeln!(f, "... {more} more lines")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildMailError {
+    /// A mailbox that can't be parsed back from how it is written.
+    InvalidMailbox(ParseMailboxListError),
+    /// The content type of an attachment or inline part has a line break or another
+    /// character that can't be written in a header.
+    InvalidContentType,
 }
 
-pub struct MailBuilder {
-    //
+impl fmt::Display for BuildMailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidMailbox(_) => f.write_str("invalid mailbox"),
+            Self::InvalidContentType => f.write_str("invalid content type"),
+        }
+    }
 }
 
-impl MailBuilder {
+impl core::error::Error for BuildMailError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidMailbox(error) => Some(error),
+            Self::InvalidContentType => None,
+        }
+    }
+}
+
+/// Marks a required part of a [`MailBuilder`] that hasn't been given yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Missing;
+
+/// Marks a required part of a [`MailBuilder`] that has been given.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Present;
+
+/// Builds a [`Mail`] from its sender, recipients, subject and body
+///
+/// The mailbox methods can be called repeatedly to add more mailboxes, which are joined into
+/// lists, skipping addresses that are already in the list. The mail borrows the lists from the
+/// builder:
+/// ```
+/// use brief::mail::{Address, DateTime, MailBuilder, Mailbox, MessageId};
+///
+/// let alice = Mailbox::try_new(Some("Alice"), "alice@example.com".try_into().unwrap()).unwrap();
+/// let bob = Address::try_from("bob@example.com").unwrap();
+///
+/// let builder = MailBuilder::new()
+///     .from(alice)
+///     .to(bob)
+///     .cc(Address::try_from("carol@example.com").unwrap())
+///     .date(DateTime::from_timestamp(1704067200, 0).unwrap())
+///     .message_id(MessageId::try_from("<1234@example.com>").unwrap())
+///     .subject("Hi")
+///     .text_body("hello");
+/// let mail = builder.build().unwrap();
+///
+/// assert_eq!(
+///     mail.to_bytes().unwrap(),
+///     b"From: Alice <alice@example.com>\r\n\
+///       To: bob@example.com\r\n\
+///       Cc: carol@example.com\r\n\
+///       Subject: Hi\r\n\
+///       Date: Mon, 1 Jan 2024 00:00:00 +0000\r\n\
+///       Message-ID: <1234@example.com>\r\n\
+///       MIME-Version: 1.0\r\n\
+///       Content-Type: text/plain; charset=utf-8\r\n\
+///       Content-Transfer-Encoding: 7bit\r\n\r\n\
+///       hello"
+/// );
+/// ```
+///
+/// With the `std` feature, the current date is used if none is given. The type parameters track
+/// whether a `From` mailbox, a recipient and, without `std`, a date have been given, so
+/// [`MailBuilder::build`] can only be called once they are:
+/// ```compile_fail
+/// use brief::mail::{Address, MailBuilder};
+///
+/// let alice = Address::try_from("alice@example.com").unwrap();
+/// let builder = MailBuilder::new().from(alice).subject("No recipients");
+/// builder.build();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailBuilder<'a, F = Missing, R = Missing, D = Missing> {
+    from: Vec<Mailbox<'a>>,
+    sender: Option<Mailbox<'a>>,
+    reply_to: Vec<Mailbox<'a>>,
+    to: Vec<Mailbox<'a>>,
+    cc: Vec<Mailbox<'a>>,
+    bcc: Vec<Mailbox<'a>>,
+    /// The `From`, `Reply-To`, `To`, `Cc` and `Bcc` lists joined for their headers, once the
+    /// mail is first built.
+    lists: OnceCell<[String; 5]>,
+    subject: Option<Cow<'a, str>>,
+    date: OnceCell<String>,
+    /// The message ID without angle brackets, or an empty string if none could be generated.
+    message_id: OnceCell<String>,
+    in_reply_to: String,
+    references: String,
+    headers: Vec<Header<'a>>,
+    text: Option<&'a str>,
+    html: Option<&'a str>,
+    inline: Vec<Attachment<'a>>,
+    attachments: Vec<Attachment<'a>>,
+    /// The content type of the body, if it was set with its transfer encoding.
+    body_type: Option<&'a str>,
+    /// The transfer encoding the body is already encoded with, like the body of a forwarded
+    /// mail, which is then sent as it is.
+    body_encoding: Option<TransferEncoding>,
+    /// Whether text may be sent as 8bit instead of quoted-printable.
+    eight_bit: bool,
+    /// The content type, transfer encoding and body once the body is encoded, when the mail is
+    /// first built.
+    encoded: OnceCell<(String, TransferEncoding, Vec<u8>)>,
+    body: &'a [u8],
+    state: PhantomData<(F, R, D)>,
+}
+
+impl<'a> MailBuilder<'a> {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            from: Vec::new(),
+            sender: None,
+            reply_to: Vec::new(),
+            to: Vec::new(),
+            cc: Vec::new(),
+            bcc: Vec::new(),
+            lists: OnceCell::new(),
+            subject: None,
+            date: OnceCell::new(),
+            message_id: OnceCell::new(),
+            in_reply_to: String::new(),
+            references: String::new(),
+            headers: Vec::new(),
+            text: None,
+            html: None,
+            inline: Vec::new(),
+            attachments: Vec::new(),
+            body_type: None,
+            body_encoding: None,
+            eight_bit: false,
+            encoded: OnceCell::new(),
+            body: b"",
+            state: PhantomData,
+        }
+    }
+}
+
+impl Default for MailBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, F, R, D> MailBuilder<'a, F, R, D> {
+    /// Adds a mailbox to the `From` header.
+    pub fn from(mut self, mailbox: impl Into<Mailbox<'a>>) -> MailBuilder<'a, Present, R, D> {
+        push(&mut self.from, mailbox.into());
+        self.lists = OnceCell::new();
+        self.into_state()
+    }
+
+    /// Sets the `Sender` header, for a mail sent on behalf of the `From` mailboxes.
+    pub fn sender(mut self, mailbox: impl Into<Mailbox<'a>>) -> Self {
+        self.sender = Some(mailbox.into());
+        self
+    }
+
+    /// Adds a mailbox to the `Reply-To` header.
+    pub fn reply_to(mut self, mailbox: impl Into<Mailbox<'a>>) -> Self {
+        push(&mut self.reply_to, mailbox.into());
+        self.lists = OnceCell::new();
+        self
+    }
+
+    /// Adds a mailbox to the `To` header, and removes its address from the `Cc` and `Bcc`
+    /// headers, so every recipient gets the mail once.
+    pub fn to(mut self, mailbox: impl Into<Mailbox<'a>>) -> MailBuilder<'a, F, Present, D> {
+        let mailbox = mailbox.into();
+        remove(&mut self.cc, &mailbox.address);
+        remove(&mut self.bcc, &mailbox.address);
+        push(&mut self.to, mailbox);
+        self.lists = OnceCell::new();
+        self.into_state()
+    }
+
+    /// Adds a mailbox to the `Cc` header, unless its address is in the `To` header, and removes
+    /// its address from the `Bcc` header.
+    pub fn cc(mut self, mailbox: impl Into<Mailbox<'a>>) -> MailBuilder<'a, F, Present, D> {
+        let mailbox = mailbox.into();
+        if !contains(&self.to, &mailbox.address) {
+            remove(&mut self.bcc, &mailbox.address);
+            push(&mut self.cc, mailbox);
+        }
+        self.lists = OnceCell::new();
+        self.into_state()
+    }
+
+    /// Adds a mailbox to the `Bcc` header, unless its address is in the `To` or `Cc` header.
+    pub fn bcc(mut self, mailbox: impl Into<Mailbox<'a>>) -> MailBuilder<'a, F, Present, D> {
+        let mailbox = mailbox.into();
+        if !contains(&self.to, &mailbox.address) && !contains(&self.cc, &mailbox.address) {
+            push(&mut self.bcc, mailbox);
+        }
+        self.lists = OnceCell::new();
+        self.into_state()
+    }
+
+    pub fn subject(mut self, subject: &'a str) -> Self {
+        self.subject = Some(Cow::Borrowed(subject));
+        self
+    }
+
+    /// Sets the `Date` header.
+    pub fn date(mut self, date: DateTime) -> MailBuilder<'a, F, R, Present> {
+        self.date = OnceCell::from(date.to_string());
+        self.into_state()
+    }
+
+    /// Sets the `Message-ID` header.
+    pub fn message_id(mut self, id: MessageId<'_>) -> Self {
+        self.message_id = OnceCell::from(id.as_str().to_string());
+        self
+    }
+
+    /// Adds an identifier to the `In-Reply-To` header, for a reply to the message with it.
+    pub fn in_reply_to(mut self, id: MessageId<'_>) -> Self {
+        push_id(&mut self.in_reply_to, id);
+        self
+    }
+
+    /// Adds an identifier to the `References` header, which lists the messages of a thread.
+    pub fn references(mut self, id: MessageId<'_>) -> Self {
+        push_id(&mut self.references, id);
+        self
+    }
+
+    /// Adds any other header, after the subject, date and message IDs.
+    pub fn header(mut self, header: Header<'a>) -> Self {
+        self.headers.push(header);
+        self
+    }
+
+    /// Sets a plain text body. If an HTML body is set too, the mail has both as a
+    /// `multipart/alternative` body, so the recipient's client can choose one.
+    ///
+    /// ```
+    /// use brief::mail::{header, Address, DateTime, MailBuilder};
+    ///
+    /// let alice = Address::try_from("alice@example.com").unwrap();
+    /// let builder = MailBuilder::new()
+    ///     .from(alice.clone())
+    ///     .to(alice)
+    ///     .date(DateTime::from_timestamp(1704067200, 0).unwrap())
+    ///     .text_body("Hi!")
+    ///     .html_body("<p>Hi!</p>");
+    /// let mail = builder.build().unwrap();
+    ///
+    /// let content_type = mail.headers().typed::<header::ContentType>().unwrap();
+    /// assert!(content_type.starts_with("multipart/alternative; boundary="));
+    /// ```
+    pub fn text_body(mut self, text: &'a str) -> Self {
+        self.text = Some(text);
+        self.encoded = OnceCell::new();
+        self
+    }
+
+    /// Sets an HTML body. If a plain text body is set too, the mail has both as a
+    /// `multipart/alternative` body.
+    pub fn html_body(mut self, html: &'a str) -> Self {
+        self.html = Some(html);
+        self.encoded = OnceCell::new();
+        self
+    }
+
+    /// Sends non-ASCII text as 8bit instead of quoted-printable, which keeps the mail smaller
+    /// and readable as it is, but can only be sent to servers with the `8BITMIME` extension.
+    pub fn allow_8bit(mut self) -> Self {
+        self.eight_bit = true;
+        self.encoded = OnceCell::new();
+        self
+    }
+
+    /// Sets a body without a content type, which is plain ASCII text unless a `Content-Type`
+    /// header is added. Replaces a text or HTML body.
+    pub fn body(mut self, body: &'a [u8]) -> Self {
+        self.body = body;
+        self.body_type = None;
+        self.body_encoding = None;
+        self.text = None;
+        self.html = None;
+        self.encoded = OnceCell::new();
+        self
+    }
+
+    /// Adds an attachment, which makes the body `multipart/mixed` with the text, HTML or plain
+    /// body as its first part. The name is sanitized with [`filename::sanitize`], keeping the
+    /// original in an `x-original-filename*` parameter if sanitizing changed it, and the content
+    /// is encoded as base64.
+    ///
+    /// ```
+    /// use brief::mail::{header, Address, DateTime, MailBuilder};
+    ///
+    /// let alice = Address::try_from("alice@example.com").unwrap();
+    /// let builder = MailBuilder::new()
+    ///     .from(alice.clone())
+    ///     .to(alice)
+    ///     .date(DateTime::from_timestamp(1704067200, 0).unwrap())
+    ///     .text_body("See the attached report.")
+    ///     .attachment("report.csv", "text/csv", b"month,total\n");
+    /// let mail = builder.build().unwrap();
+    ///
+    /// let content_type = mail.headers().typed::<header::ContentType>().unwrap();
+    /// assert!(content_type.starts_with("multipart/mixed; boundary="));
+    /// let body = std::str::from_utf8(mail.body()).unwrap();
+    /// assert!(body.contains("Content-Disposition: attachment; filename=\"report.csv\"\r\n"));
+    /// assert!(body.contains("bW9udGgsdG90YWwK"));
+    /// ```
+    ///
+    /// [`filename::sanitize`]: super::filename::sanitize
+    pub fn attachment(mut self, name: &'a str, content_type: &'a str, content: &'a [u8]) -> Self {
+        self.attachments.push(Attachment {
+            name: Cow::Borrowed(name),
+            content_type: Cow::Borrowed(content_type),
+            content: Cow::Borrowed(content),
+            content_id: None,
+        });
+        self.encoded = OnceCell::new();
+        self
+    }
+
+    /// Adds the file at the path as an attachment, with its file name and a content type that
+    /// is guessed from its extension. Fails if the file can't be read.
+    #[cfg(feature = "std")]
+    pub fn attachment_from_path(
+        mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read(path)?;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+        self.attachments.push(Attachment {
+            name: Cow::Owned(name),
+            content_type: Cow::Borrowed(content_type(extension)),
+            content: Cow::Owned(content),
+            content_id: None,
+        });
+        self.encoded = OnceCell::new();
+        Ok(self)
+    }
+
+    /// Adds a part that is shown inline, like an image in the HTML body, and returns its
+    /// generated content ID. The HTML body refers to the part with the `cid:` URL of the ID,
+    /// and is sent in a `multipart/related` body with its inline parts.
+    ///
+    /// Since the HTML body needs the content ID, this method takes the builder by reference:
+    /// ```
+    /// use brief::mail::{header, Address, DateTime, MailBuilder};
+    ///
+    /// let alice = Address::try_from("alice@example.com").unwrap();
+    /// let mut builder = MailBuilder::new()
+    ///     .from(alice.clone())
+    ///     .to(alice)
+    ///     .date(DateTime::from_timestamp(1704067200, 0).unwrap());
+    ///
+    /// let logo = builder.inline("logo.png", "image/png", b"\x89PNG");
+    /// let html = format!(r#"<img src="{}">"#, logo.url());
+    /// let builder = builder.html_body(&html);
+    ///
+    /// let mail = builder.build().unwrap();
+    /// let content_type = mail.headers().typed::<header::ContentType>().unwrap();
+    /// assert!(content_type.starts_with("multipart/related; boundary="));
+    /// let body = std::str::from_utf8(mail.body()).unwrap();
+    /// assert!(body.contains(&format!("Content-ID: {logo}\r\n")));
+    /// ```
+    pub fn inline(&mut self, name: &'a str, content_type: &'a str, content: &'a [u8]) -> ContentId {
+        let left = content_hash(&[content], self.inline.len() as u64);
+        let id = ContentId {
+            id: format!("{left:016x}.{}@brief.invalid", self.inline.len()),
+        };
+
+        self.inline.push(Attachment {
+            name: Cow::Borrowed(name),
+            content_type: Cow::Borrowed(content_type),
+            content: Cow::Borrowed(content),
+            content_id: Some(id.clone()),
+        });
+        self.encoded = OnceCell::new();
+        id
+    }
+
+    fn into_state<F2, R2, D2>(self) -> MailBuilder<'a, F2, R2, D2> {
+        MailBuilder {
+            from: self.from,
+            sender: self.sender,
+            reply_to: self.reply_to,
+            to: self.to,
+            cc: self.cc,
+            bcc: self.bcc,
+            lists: self.lists,
+            subject: self.subject,
+            date: self.date,
+            message_id: self.message_id,
+            in_reply_to: self.in_reply_to,
+            references: self.references,
+            headers: self.headers,
+            text: self.text,
+            html: self.html,
+            inline: self.inline,
+            attachments: self.attachments,
+            body_type: self.body_type,
+            body_encoding: self.body_encoding,
+            eight_bit: self.eight_bit,
+            encoded: self.encoded,
+            body: self.body,
+            state: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<D> MailBuilder<'_, Present, Present, D> {
+    /// Builds the mail, with the current date and a generated message ID for the domain of the
+    /// first `From` mailbox if none were given. Both are kept, so building again gives the same
+    /// mail. Fails for mailboxes that were created from invalid fields, and for invalid content
+    /// types.
+    pub fn build(&self) -> Result<Mail<'_>, BuildMailError> {
+        let (date, message_id) = self.date_and_message_id();
+        let mut headers = self.headers_with(date, message_id)?;
+
+        let (content_type, encoding, body) = self.content();
+        append_content_headers(&mut headers, content_type, encoding);
+        Ok(Mail::new(headers, body))
+    }
+
+    /// Writes the mail as an RFC 5322 message, like [`Mail::write_to`], but encodes the body
+    /// while writing it. Large attachments are then only held in memory once. Fails like
+    /// [`MailBuilder::build`], with [`InvalidInput`](std::io::ErrorKind::InvalidInput), or
+    /// like [`Mail::write_to`].
+    ///
+    /// ```
+    /// use brief::mail::{Address, DateTime, MailBuilder};
+    ///
+    /// let alice = Address::try_from("alice@example.com").unwrap();
+    /// let report = vec![0; 1 << 20];
+    /// let builder = MailBuilder::new()
+    ///     .from(alice.clone())
+    ///     .to(alice)
+    ///     .date(DateTime::from_timestamp(1704067200, 0).unwrap())
+    ///     .attachment("report.bin", "application/octet-stream", &report);
+    ///
+    /// let mut out = std::io::sink();
+    /// builder.write_to(&mut out).unwrap();
+    /// ```
+    pub fn write_to<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        use std::io::{Error, ErrorKind};
+
+        let part = self.part();
+        let (date, message_id) = self.date_and_message_id();
+        let mut headers = self
+            .headers_with(date, message_id)
+            .map_err(|error| Error::new(ErrorKind::InvalidInput, error))?;
+        if let Some(part) = &part {
+            append_content_headers(&mut headers, part.content_type(), part.encoding());
+        }
+
+        let head = Mail::new(headers, b"")
+            .head()
+            .map_err(|error| Error::new(ErrorKind::InvalidData, error))?;
+        writer.write_all(head.as_bytes())?;
+
+        let mut write = |b: &[u8]| writer.write_all(b);
+        match part {
+            Some(part) => part.write(&mut write),
+            None => write_crlf(self.body, &mut write),
+        }
+    }
+
+    /// Returns the date and message ID, which are the current date and a generated message ID
+    /// for the domain of the first `From` mailbox if none were given.
+    fn date_and_message_id(&self) -> (&str, Option<&String>) {
+        let date = self.date.get_or_init(|| DateTime::now().to_string());
+
+        let message_id = self.message_id.get_or_init(|| {
+            self.from
+                .first()
+                .and_then(|m| MessageId::generate(m.address.domain()).ok())
+                .map_or_else(String::new, |id| id.as_message_id().as_str().to_string())
+        });
+
+        (date, Some(message_id).filter(|id| !id.is_empty()))
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl MailBuilder<'_, Present, Present, Present> {
+    /// Builds the mail. Fails for mailboxes that were created from invalid fields, and for invalid
+    /// content types.
+    pub fn build(&self) -> Result<Mail<'_>, BuildMailError> {
+        // the typestate ensures the date was given
+        let date = self.date.get().map_or("", String::as_str);
+        let mut headers = self.headers_with(date, self.message_id.get())?;
+
+        let (content_type, encoding, body) = self.content();
+        append_content_headers(&mut headers, content_type, encoding);
+        Ok(Mail::new(headers, body))
+    }
+}
+
+impl<F, R, D> MailBuilder<'_, F, R, D> {
+    /// Returns the headers of the mail, without the headers about its content.
+    fn headers_with<'b>(
+        &'b self,
+        date: &'b str,
+        message_id: Option<&'b String>,
+    ) -> Result<HeaderMap<'b>, BuildMailError> {
+        // content types are written into the headers of the parts as they are
+        let content_types = self.inline.iter().chain(&self.attachments);
+        if !content_types
+            .map(|a| &*a.content_type)
+            .all(is_valid_content_type)
+        {
+            return Err(BuildMailError::InvalidContentType);
+        }
+
+        let [from, reply_to, to, cc, bcc] = self.lists.get_or_init(|| {
+            [&self.from, &self.reply_to, &self.to, &self.cc, &self.bcc].map(|list| join(list))
+        });
+
+        let mut headers = HeaderMap::new();
+        headers.append(Header::From(list(from)?));
+        if let Some(sender) = &self.sender {
+            headers.append(Header::Sender(sender.clone()));
+        }
+
+        let lists = [
+            (reply_to, Header::ReplyTo as fn(_) -> _),
+            (to, Header::To),
+            (cc, Header::Cc),
+            (bcc, Header::Bcc),
+        ];
+        for (value, header) in lists {
+            if !value.is_empty() {
+                headers.append(header(list(value)?));
+            }
+        }
+
+        if let Some(subject) = &self.subject {
+            headers.append(Header::Subject(subject));
+        }
+        headers.append(Header::Date(date));
+        if let Some(id) = message_id {
+            headers.append(Header::MessageId(id));
+        }
+        if !self.in_reply_to.is_empty() {
+            headers.append(Header::InReplyTo(&self.in_reply_to));
+        }
+        if !self.references.is_empty() {
+            headers.append(Header::References(&self.references));
+        }
+        headers.extend(self.headers.iter().cloned());
+
+        Ok(headers)
+    }
+
+    /// Returns the content type, transfer encoding and body of the mail, encoding the body the
+    /// first time it can't be sent as it is.
+    fn content(&self) -> (Option<&str>, TransferEncoding, &[u8]) {
+        let part = match self.part() {
+            None => return (None, TransferEncoding::SevenBit, self.body),
+            Some(Part::Single {
+                content_type,
+                encoding: encoding @ (TransferEncoding::SevenBit | TransferEncoding::EightBit),
+                content,
+                ..
+            }) => return (content_type, encoding, content),
+            Some(part) => part,
+        };
+
+        let (content_type, encoding, body) = self.encoded.get_or_init(|| {
+            let mut body = Vec::new();
+            let result: Result<(), core::convert::Infallible> = part.write(&mut |b| {
+                body.extend_from_slice(b);
+                Ok(())
+            });
+            let Ok(()) = result;
+
+            let content_type = part.content_type().unwrap_or_default().to_string();
+            (content_type, part.encoding(), body)
+        });
+
+        (Some(content_type), *encoding, body)
+    }
+
+    /// Returns the parts of the body, or `None` for a body without a content type.
+    fn part(&self) -> Option<Part<'_>> {
+        // inline parts are related to the HTML body that refers to them, and attachments are
+        // mixed with everything else
+        let related = |part| with_parts("related", part, &self.inline);
+        let text = self
+            .text
+            .map(|text| Part::single(Some(TEXT), text.as_bytes(), self.eight_bit));
+        let html = self
+            .html
+            .map(|html| Part::single(Some(HTML), html.as_bytes(), self.eight_bit));
+
+        let body = match (text, html) {
+            (Some(text), Some(html)) => {
+                let parts = [Some(text), related(Some(html))];
+                Some(Part::multipart("alternative", parts.into_iter().flatten()))
+            }
+            (None, None)
+                if self.inline.is_empty()
+                    && self.attachments.is_empty()
+                    && self.body_encoding.is_none() =>
+            {
+                return None
+            }
+            (text, html) => related(html.or(text).or_else(|| self.body_part())),
+        };
+
+        with_parts("mixed", body, &self.attachments)
+    }
+}
+
+impl<F, R, D> MailBuilder<'_, F, R, D> {
+    /// Returns the part with the body that was set without a text or HTML body, if any.
+    fn body_part(&self) -> Option<Part<'_>> {
+        match self.body_encoding {
+            Some(encoding) => Some(Part::Single {
+                content_type: self.body_type,
+                headers: String::new(),
+                encoding,
+                encoded: true,
+                content: self.body,
+            }),
+            None => (!self.body.is_empty()).then(|| Part::single(None, self.body, self.eight_bit)),
+        }
+    }
+}
+
+/// Appends the headers about the content of a mail, if it has a content type.
+fn append_content_headers<'b>(
+    headers: &mut HeaderMap<'b>,
+    content_type: Option<&'b str>,
+    encoding: TransferEncoding,
+) {
+    if let Some(content_type) = content_type {
+        headers.append(Header::MimeVersion);
+        headers.append(Header::ContentType(content_type));
+        headers.append(Header::ContentTransferEncoding(encoding.as_str()));
+    }
+}
+
+/// Identifies an inline part of a mail, so the HTML body can refer to it with a `cid:` URL.
+/// Created by [`MailBuilder::inline`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ContentId {
+    id: String,
+}
+
+impl ContentId {
+    /// Returns the identifier without the angle brackets.
+    pub fn as_str(&self) -> &str {
+        &self.id
+    }
+
+    /// Returns the `cid:` URL that refers to the part, as in `<img src="cid:...">`.
+    pub fn url(&self) -> String {
+        format!("cid:{}", self.id)
+    }
+}
+
+impl fmt::Display for ContentId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.id)
+    }
+}
+
+/// A file attached to a mail, or shown inline if it has a content ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Attachment<'a> {
+    name: Cow<'a, str>,
+    content_type: Cow<'a, str>,
+    content: Cow<'a, [u8]>,
+    content_id: Option<ContentId>,
+}
+
+impl Attachment<'_> {
+    /// Returns the part with the attachment, which is encoded as base64.
+    fn part(&self) -> Part<'_> {
+        let disposition = match self.content_id {
+            Some(_) => "inline",
+            None => "attachment",
+        };
+        let mut headers = format!(
+            "Content-Disposition: {disposition}; {}\r\n",
+            filename_parameter(&self.name),
+        );
+        if let Some(id) = &self.content_id {
+            headers.push_str(&format!("Content-ID: {id}\r\n"));
+        }
+
+        Part::Single {
+            content_type: Some(&self.content_type),
+            headers,
+            encoding: TransferEncoding::Base64,
+            encoded: false,
+            content: &self.content,
+        }
+    }
+}
+
+/// A part of a multipart body, or a whole body, which is encoded as it is written.
+enum Part<'b> {
+    Single {
+        /// The content type, or `None` for plain ASCII text.
+        content_type: Option<&'b str>,
+        /// The headers between the `Content-Type` and `Content-Transfer-Encoding`, each
+        /// followed by a CRLF.
+        headers: String,
+        encoding: TransferEncoding,
+        /// Whether the content is already encoded, so it is written as it is.
+        encoded: bool,
+        content: &'b [u8],
+    },
+    Multipart {
+        content_type: String,
+        boundary: String,
+        parts: Vec<Part<'b>>,
+    },
+}
+
+impl<'b> Part<'b> {
+    /// Returns a part with the content, which is encoded if it doesn't fit the limits of SMTP,
+    /// or of servers without `8BITMIME` unless `eight_bit` is true.
+    fn single(content_type: Option<&'b str>, content: &'b [u8], eight_bit: bool) -> Self {
+        Self::Single {
+            content_type,
+            headers: String::new(),
+            encoding: TransferEncoding::choose_with(content, eight_bit),
+            encoded: false,
+            content,
+        }
+    }
+
+    /// Returns a multipart part with the given subtype.
+    fn multipart(subtype: &str, parts: impl IntoIterator<Item = Self>) -> Self {
+        let parts: Vec<_> = parts.into_iter().collect();
+        let mut texts = Vec::new();
+        for part in &parts {
+            part.texts(&mut texts);
+        }
+
+        let boundary = boundary(&texts);
+        Self::Multipart {
+            content_type: format!("multipart/{subtype}; boundary=\"{boundary}\""),
+            boundary,
+            parts,
+        }
+    }
+
+    fn content_type(&self) -> Option<&str> {
+        match self {
+            Self::Single { content_type, .. } => *content_type,
+            Self::Multipart { content_type, .. } => Some(content_type),
+        }
+    }
+
+    /// Returns the encoding of the part, which is 7bit or 8bit for a multipart part.
+    fn encoding(&self) -> TransferEncoding {
+        match self {
+            Self::Single { encoding, .. } => *encoding,
+            Self::Multipart { parts, .. } => {
+                match parts
+                    .iter()
+                    .any(|p| p.encoding() == TransferEncoding::EightBit)
+                {
+                    true => TransferEncoding::EightBit,
+                    false => TransferEncoding::SevenBit,
+                }
+            }
+        }
+    }
+
+    /// Collects the text of the part that a boundary must not occur in. Encoded content is
+    /// skipped, since a boundary can't occur in it.
+    fn texts<'s>(&'s self, texts: &mut Vec<&'s [u8]>) {
+        match self {
+            Self::Single {
+                content_type,
+                headers,
+                encoding,
+                content,
+                ..
+            } => {
+                texts.extend(content_type.map(str::as_bytes));
+                texts.push(headers.as_bytes());
+                if let TransferEncoding::SevenBit | TransferEncoding::EightBit = encoding {
+                    texts.push(content);
+                }
+            }
+            Self::Multipart {
+                content_type,
+                parts,
+                ..
+            } => {
+                texts.push(content_type.as_bytes());
+                for part in parts {
+                    part.texts(texts);
+                }
+            }
+        }
+    }
+
+    /// Writes the body of the part, with CRLF line breaks.
+    fn write<E>(&self, write: &mut impl FnMut(&[u8]) -> Result<(), E>) -> Result<(), E> {
+        match self {
+            Self::Single {
+                encoding: TransferEncoding::SevenBit | TransferEncoding::EightBit,
+                content,
+                ..
+            }
+            | Self::Single {
+                encoded: true,
+                content,
+                ..
+            } => write_crlf(content, write),
+            Self::Single {
+                encoding, content, ..
+            } => encoding.write(content, write),
+            Self::Multipart {
+                boundary, parts, ..
+            } => {
+                for part in parts {
+                    write(format!("--{boundary}\r\n").as_bytes())?;
+                    if let Some(content_type) = part.content_type() {
+                        write(format!("Content-Type: {content_type}\r\n").as_bytes())?;
+                    }
+                    if let Self::Single { headers, .. } = part {
+                        write(headers.as_bytes())?;
+                    }
+                    write(
+                        format!("Content-Transfer-Encoding: {}\r\n\r\n", part.encoding())
+                            .as_bytes(),
+                    )?;
+                    part.write(write)?;
+                    write(b"\r\n")?;
+                }
+                write(format!("--{boundary}--\r\n").as_bytes())
+            }
+        }
+    }
+}
+
+/// Returns a multipart part with the first part and the attachments, or the first part as it
+/// is if there are no attachments.
+fn with_parts<'b>(
+    subtype: &str,
+    first: Option<Part<'b>>,
+    attachments: &'b [Attachment<'_>],
+) -> Option<Part<'b>> {
+    if attachments.is_empty() {
+        return first;
+    }
+
+    let parts = first
+        .into_iter()
+        .chain(attachments.iter().map(|a| a.part()));
+    Some(Part::multipart(subtype, parts))
+}
+
+/// Returns the `filename` parameter of a `Content-Disposition` header for the sanitized name,
+/// which is encoded as specified by RFC 2231 if it isn't ASCII. If sanitizing changed the name,
+/// the original is kept in an `x-original-filename*` parameter, which clients don't save files
+/// under.
+fn filename_parameter(name: &str) -> String {
+    let sanitized = filename::sanitize(name);
+    let name = sanitized.to_string();
+    let mut parameter = match name.is_ascii() {
+        true => {
+            let escaped = name.replace('\\', "\\\\").replace('"', "\\\"");
+            format!("filename=\"{escaped}\"")
+        }
+        false => format!("filename*={}", extended_value(&name)),
+    };
+
+    if sanitized.is_changed() {
+        // writing to a string can't fail
+        let _ = write!(
+            parameter,
+            ";\r\n x-original-filename*={}",
+            extended_value(sanitized.original())
+        );
+    }
+    parameter
+}
+
+/// Returns a parameter value encoded as specified by RFC 2231, as UTF-8 with percent escapes.
+fn extended_value(value: &str) -> String {
+    let mut encoded = String::from("utf-8''");
+    for byte in value.bytes() {
+        match byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            true => encoded.push(char::from(byte)),
+            false => {
+                // writing to a string can't fail
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+    encoded
+}
+
+/// Returns whether a content type can be written in a header: printable ASCII and spaces, so
+/// it can't end the header early.
+fn is_valid_content_type(content_type: &str) -> bool {
+    !content_type.trim().is_empty()
+        && content_type
+            .bytes()
+            .all(|b| b.is_ascii_graphic() || b == b' ' || b == b'\t')
+}
+
+/// Guesses the content type of a file from its extension.
+#[cfg(feature = "std")]
+fn content_type(extension: &str) -> &'static str {
+    match extension.to_ascii_lowercase().as_str() {
+        "txt" => "text/plain",
+        "csv" => "text/csv",
+        "htm" | "html" => "text/html",
+        "ics" => "text/calendar",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gif" => "image/gif",
+        "jpeg" | "jpg" => "image/jpeg",
+        "png" => "image/png",
+        "svg" => "image/svg+xml",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Returns a boundary that doesn't occur in any of the bodies. It starts with `=_`, which can't
+/// occur in base64 or quoted-printable text either.
+fn boundary(bodies: &[&[u8]]) -> String {
+    let mut attempt = 0;
+    loop {
+        let boundary = format!("=_{:016x}", content_hash(bodies, attempt));
+        let occurs = bodies.iter().any(|body| {
+            body.windows(boundary.len())
+                .any(|w| w == boundary.as_bytes())
+        });
+        if !occurs {
+            return boundary;
+        }
+        attempt += 1;
+    }
+}
+
+/// Returns an FNV-1a hash of the bodies for a boundary or content ID, so building the same mail
+/// twice gives the same bytes, which golden files and diffs rely on.
+fn content_hash(bodies: &[&[u8]], attempt: u64) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325 ^ attempt;
+    for byte in bodies.iter().flat_map(|body| body.iter()) {
+        hash = (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Returns whether two addresses are the same, comparing their domains case-insensitively.
+fn same_address(a: &Address<'_>, b: &Address<'_>) -> bool {
+    a.user() == b.user() && a.domain().eq_ignore_ascii_case(b.domain())
+}
+
+/// Returns whether a list has a mailbox with the address.
+fn contains(list: &[Mailbox<'_>], address: &Address<'_>) -> bool {
+    list.iter().any(|m| same_address(&m.address, address))
+}
+
+/// Removes the mailboxes with the address from a list.
+fn remove(list: &mut Vec<Mailbox<'_>>, address: &Address<'_>) {
+    list.retain(|m| !same_address(&m.address, address));
+}
+
+/// Appends a mailbox to a list, unless the list already has its address. The domains of
+/// addresses are compared case-insensitively.
+fn push<'a>(list: &mut Vec<Mailbox<'a>>, mailbox: Mailbox<'a>) {
+    if !contains(list, &mailbox.address) {
+        list.push(mailbox);
+    }
+}
+
+/// Joins a list into the body of its header, separated by commas.
+fn join(list: &[Mailbox<'_>]) -> String {
+    let mut joined = String::new();
+    for mailbox in list {
+        if !joined.is_empty() {
+            joined.push_str(", ");
+        }
+        // writing to a string can't fail
+        let _ = match mailbox.name.map(str::trim) {
+            Some(name) if !name.is_empty() => write!(joined, "{mailbox}"),
+            _ => write!(joined, "{}", mailbox.address),
+        };
+    }
+    joined
+}
+
+/// Appends an identifier to a whitespace-separated list, unless the list already has it.
+fn push_id(list: &mut String, id: MessageId<'_>) {
+    if MessageId::parse_list(list).any(|i| i == Ok(id)) {
+        return;
+    }
+
+    if !list.is_empty() {
+        list.push(' ');
+    }
+    // writing to a string can't fail
+    let _ = write!(list, "{id}");
+}
+
+/// Parses a list that was joined by [`join`], which only fails for mailboxes that were created
+/// from invalid fields.
+fn list(value: &str) -> Result<MailboxList<'_>, BuildMailError> {
+    MailboxList::try_from(value)
+        .or_else(|_| MailboxList::parse_international(value))
+        .map_err(BuildMailError::InvalidMailbox)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, vec::Vec};
+
+    use super::{
+        filename_parameter, BuildMailError, DeliveryLoopError, Mail, MailBuilder,
+        SerializeMailError,
+    };
+    use crate::mail::{
+        header, transfer_encoding::TransferEncoding, Address, DateTime, Header, HeaderMap, Mailbox,
+        MessageId,
+    };
+
+    #[test]
+    fn it_normalizes_line_endings_in_the_body() {
+        let headers = HeaderMap::from_iter([Header::MimeVersion]);
+        let mail = Mail::new(headers, b"\na\rb\r\nc\n\r");
+
+        assert_eq!(
+            mail.to_bytes().unwrap(),
+            b"MIME-Version: 1.0\r\n\r\n\r\na\r\nb\r\nc\r\n\r\n"
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn it_writes_to_async_writers() {
+        use core::{
+            future::Future,
+            pin::pin,
+            task::{Context, Poll, Waker},
+        };
+
+        let headers = HeaderMap::from_iter([Header::Subject("Hi")]);
+        let mail = Mail::new(headers, b"a\nb\r");
+
+        // writing to a vector never waits
+        let mut out = Vec::new();
+        let poll =
+            pin!(mail.write_to_async(&mut out)).poll(&mut Context::from_waker(Waker::noop()));
+        assert!(matches!(poll, Poll::Ready(Ok(()))));
+        assert_eq!(out, mail.to_bytes().unwrap());
+    }
+
+    #[test]
+    fn it_serializes_a_mail_without_headers() {
+        let mail = Mail::new(HeaderMap::new(), b"body");
+        assert_eq!(mail.to_bytes().unwrap(), b"\r\nbody");
+    }
+
+    #[test]
+    fn it_redirects_all_recipients() {
+        let headers = HeaderMap::from_iter([
+            Header::Subject("Hi"),
+            Header::Bcc("a@x.com, b@y.com".try_into().unwrap()),
+        ]);
+        let mut mail = Mail::new(headers, b"");
+        mail.redirect_recipients("c@z.com".try_into().unwrap());
+
+        assert!(!mail.headers().contains("Bcc"));
+        assert_eq!(mail.headers().get_all("To").count(), 1);
+        assert_eq!(
+            mail.headers().get("X-Original-To"),
+            Some(&Header::OriginalTo("a@x.com, b@y.com".try_into().unwrap()))
+        );
+    }
+
+    #[test]
+    fn it_refuses_to_deliver_in_a_loop() {
+        let headers = HeaderMap::from_iter([
+            Header::DeliveredTo("list@example.com".try_into().unwrap()),
+            Header::Subject("Hi"),
+        ]);
+        let mut mail = Mail::new(headers, b"");
+
+        assert_eq!(
+            mail.deliver_to("list@EXAMPLE.com".try_into().unwrap()),
+            Err(DeliveryLoopError)
+        );
+        assert_eq!(mail.headers().len(), 2);
+
+        // users are case-sensitive
+        assert!(mail
+            .deliver_to("List@example.com".try_into().unwrap())
+            .is_ok());
+        assert_eq!(mail.headers().get_all("Delivered-To").count(), 2);
+    }
+
+    #[test]
+    fn it_truncates_the_body_unless_alternate() {
+        let body = "line\n".repeat(12);
+        let mail = Mail::new(HeaderMap::new(), body.as_bytes());
+
+        let preview = format!("{mail}");
+        assert!(preview.starts_with("--- body: text/plain, 7bit, 60 bytes ---\n"));
+        assert!(preview.ends_with("line\n... 2 more lines\n"));
+        assert_eq!(format!("{mail:#}").lines().count(), 13);
+
+        let mail = Mail::new(HeaderMap::new(), b"\xff\xfe");
+        assert!(format!("{mail}").ends_with("(binary)\n"));
+    }
+
+    #[test]
+    fn it_fails_to_serialize_overlong_header_lines() {
+        let subject = "x".repeat(1000);
+        let headers = HeaderMap::from_iter([Header::Subject(&subject)]);
+
+        assert_eq!(
+            Mail::new(headers, b"").to_bytes(),
+            Err(SerializeMailError::LineTooLong(1001))
+        );
+    }
+
+    #[test]
+    fn it_fails_to_serialize_injected_headers() {
+        let headers = HeaderMap::from_iter([Header::Custom("X-A", "v\r\nBcc: x@y.z")]);
+        assert_eq!(
+            Mail::new(headers, b"").to_bytes(),
+            Err(SerializeMailError::BareLineBreak)
+        );
+
+        let headers = HeaderMap::from_iter([Header::Custom("Bcc: x@y.z\r\nX-A", "v")]);
+        assert_eq!(
+            Mail::new(headers, b"").to_bytes(),
+            Err(SerializeMailError::InvalidHeaderName)
+        );
+    }
+    #[test]
+    fn it_builds_a_mail() {
+        let alice = Mailbox::try_from("Alice <alice@example.com>").unwrap();
+        let bob = Address::try_from("bob@example.com").unwrap();
+        let jöran = Address::parse_international("jöran@bücher.de").unwrap();
+
+        let builder = MailBuilder::new()
+            .from(alice)
+            .to(bob.clone())
+            .to(Mailbox {
+                name: Some("Doe, John"),
+                address: jöran,
+            })
+            .to(Address::try_from("bob@EXAMPLE.com").unwrap())
+            .bcc(bob)
+            .subject("Grüße")
+            .date(DateTime::from_timestamp(1704067200, 0).unwrap())
+            .message_id(MessageId::try_from("<3@example.com>").unwrap())
+            .in_reply_to(MessageId::try_from("<2@example.com>").unwrap())
+            .references(MessageId::try_from("<1@example.com>").unwrap())
+            .references(MessageId::try_from("<2@example.com>").unwrap())
+            .references(MessageId::try_from("<1@example.com>").unwrap())
+            .text_body("Hallo Jöran!");
+        let mail = builder.build().unwrap();
+
+        assert!(mail.requires_smtputf8());
+        assert_eq!(
+            mail.to_bytes().unwrap(),
+            "From: Alice <alice@example.com>\r\n\
+             To: bob@example.com, \"Doe, John\" <jöran@bücher.de>\r\n\
+             Subject: =?UTF-8?B?R3LDvMOfZQ==?=\r\n\
+             Date: Mon, 1 Jan 2024 00:00:00 +0000\r\n\
+             Message-ID: <3@example.com>\r\n\
+             In-Reply-To: <2@example.com>\r\n\
+             References: <1@example.com> <2@example.com>\r\n\
+             MIME-Version: 1.0\r\n\
+             Content-Type: text/plain; charset=utf-8\r\n\
+             Content-Transfer-Encoding: quoted-printable\r\n\r\n\
+             Hallo J=C3=B6ran!"
+                .as_bytes()
+        );
+    }
+
+    #[test]
+    fn it_adds_every_recipient_once() {
+        let alice = Address::try_from("alice@example.com").unwrap();
+        let bob = Address::try_from("bob@example.com").unwrap();
+        let carol = Address::try_from("carol@example.com").unwrap();
+
+        let builder = MailBuilder::new()
+            .from(alice.clone())
+            .bcc(alice.clone())
+            .bcc(bob.clone())
+            .bcc(carol.clone())
+            .cc(bob.clone())
+            .cc(carol.clone())
+            .to(Address::try_from("carol@EXAMPLE.com").unwrap())
+            .to(alice)
+            .bcc(bob)
+            .bcc(carol)
+            .date(DateTime::from_timestamp(0, 0).unwrap());
+        let mail = builder.build().unwrap();
+
+        let list = |name| match mail.headers().get(name) {
+            Some(Header::To(list) | Header::Cc(list) | Header::Bcc(list)) => list.as_str(),
+            _ => "",
+        };
+        assert_eq!(list("To"), "carol@EXAMPLE.com, alice@example.com");
+        assert_eq!(list("Cc"), "bob@example.com");
+        assert_eq!(list("Bcc"), "");
+    }
+
+    #[test]
+    fn it_builds_a_mail_with_names_that_need_quoting() {
+        let alice = Address::try_from("alice@example.com").unwrap();
+        let quoted = Mailbox::try_new(Some("<Alice>"), alice.clone()).unwrap();
+
+        let date = DateTime::from_timestamp(0, 0).unwrap();
+        let builder = MailBuilder::new().from(alice).to(quoted.clone()).date(date);
+        let mail = builder.build().unwrap();
+
+        let to = mail.headers().typed::<header::To>().unwrap();
+        assert_eq!(to.as_str(), "\"<Alice>\" <alice@example.com>");
+        assert_eq!(to.iter().next(), Some(quoted));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_adds_the_current_date_and_a_message_id() {
+        let alice = Address::try_from("alice@example.com").unwrap();
+        let builder = MailBuilder::new().from(alice.clone()).to(alice);
+
+        let mail = builder.build().unwrap();
+        assert!(mail.headers().contains("Date"));
+        let id = mail
+            .headers()
+            .typed::<crate::mail::header::MessageId>()
+            .unwrap();
+        assert!(id.ends_with("@example.com"));
+        assert_eq!(builder.build().unwrap(), mail);
+    }
+
+    #[test]
+    fn it_builds_an_alternative_body() {
+        let alice = Address::try_from("alice@example.com").unwrap();
+        let builder = MailBuilder::new()
+            .from(alice.clone())
+            .to(alice)
+            .date(DateTime::from_timestamp(0, 0).unwrap())
+            .html_body("<p>Grüße</p>")
+            .text_body("Hi")
+            .allow_8bit();
+        let mail = builder.build().unwrap();
+
+        let content_type = mail.headers().typed::<header::ContentType>().unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/alternative; boundary=\"")
+            .and_then(|b| b.strip_suffix('"'))
+            .unwrap();
+        assert!(boundary.starts_with("=_"));
+        assert_eq!(
+            mail.headers().typed::<header::ContentTransferEncoding>(),
+            Some("8bit")
+        );
+        assert_eq!(
+            core::str::from_utf8(mail.body()).unwrap(),
+            format!(
+                "--{boundary}\r\n\
+                 Content-Type: text/plain; charset=utf-8\r\n\
+                 Content-Transfer-Encoding: 7bit\r\n\r\n\
+                 Hi\r\n\
+                 --{boundary}\r\n\
+                 Content-Type: text/html; charset=utf-8\r\n\
+                 Content-Transfer-Encoding: 8bit\r\n\r\n\
+                 <p>Grüße</p>\r\n\
+                 --{boundary}--\r\n"
+            )
+        );
+        assert_eq!(builder.build().unwrap(), mail);
+
+        let builder = builder.body(b"plain");
+        let mail = builder.build().unwrap();
+        assert!(!mail.headers().contains("Content-Type"));
+    }
+
+    #[test]
+    fn it_encodes_long_lines_as_quoted_printable() {
+        let alice = Address::try_from("alice@example.com").unwrap();
+        let html = "<td>ü</td>".repeat(100);
+        let builder = MailBuilder::new()
+            .from(alice.clone())
+            .to(alice)
+            .date(DateTime::from_timestamp(0, 0).unwrap())
+            .html_body(&html);
+        let mail = builder.build().unwrap();
+
+        assert_eq!(
+            mail.headers().typed::<header::ContentTransferEncoding>(),
+            Some("quoted-printable")
+        );
+        assert!(mail.body().starts_with(b"<td>=C3=BC</td>"));
+        assert!(mail.to_bytes().is_ok());
+    }
+
+    #[test]
+    fn it_builds_a_mail_with_attachments() {
+        let alice = Address::try_from("alice@example.com").unwrap();
+        let content = [0u8; 60];
+        let builder = MailBuilder::new()
+            .from(alice.clone())
+            .to(alice)
+            .date(DateTime::from_timestamp(0, 0).unwrap())
+            .text_body("Hi")
+            .html_body("<p>Hi</p>")
+            .attachment("../Grüße.bin", "application/octet-stream", &content);
+        let mail = builder.build().unwrap();
+
+        let content_type = mail.headers().typed::<header::ContentType>().unwrap();
+        assert!(content_type.starts_with("multipart/mixed; boundary=\"=_"));
+        assert_eq!(
+            mail.headers().typed::<header::ContentTransferEncoding>(),
+            Some("7bit")
+        );
+
+        let body = core::str::from_utf8(mail.body()).unwrap();
+        assert!(body.contains("Content-Type: multipart/alternative; boundary="));
+        assert!(body.contains(
+            "Content-Type: application/octet-stream\r\n\
+             Content-Disposition: attachment; filename*=utf-8''Gr%C3%BC%C3%9Fe.bin;\r\n \
+             x-original-filename*=utf-8''..%2FGr%C3%BC%C3%9Fe.bin\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n"
+        ));
+        assert!(body.contains(&format!("{}\r\nAAAA\r\n", "A".repeat(76))));
+        assert_eq!(builder.build().unwrap(), mail);
+    }
+
+    #[test]
+    fn it_relates_inline_parts_to_the_html_body() {
+        let alice = Address::try_from("alice@example.com").unwrap();
+        let mut builder = MailBuilder::new()
+            .from(alice.clone())
+            .to(alice)
+            .date(DateTime::from_timestamp(0, 0).unwrap())
+            .text_body("Hi")
+            .attachment("a.txt", "text/plain", b"a");
+        let logo = builder.inline("logo.png", "image/png", b"png");
+        assert_ne!(builder.inline("logo.png", "image/png", b"png"), logo);
+
+        let html = format!("<img src=\"{}\">", logo.url());
+        let builder = builder.html_body(&html);
+        let mail = builder.build().unwrap();
+
+        let body = core::str::from_utf8(mail.body()).unwrap();
+        let content_types: Vec<_> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("Content-Type: "))
+            .map(|value| value.split(';').next().unwrap())
+            .collect();
+        assert_eq!(
+            content_types,
+            [
+                "multipart/alternative",
+                "text/plain",
+                "multipart/related",
+                "text/html",
+                "image/png",
+                "image/png",
+                "text/plain",
+            ]
+        );
+        assert!(body.contains(&format!(
+            "Content-Disposition: inline; filename=\"logo.png\"\r\n\
+             Content-ID: <{}>\r\n",
+            logo.as_str()
+        )));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_writes_the_same_mail_it_builds() {
+        let alice = Address::try_from("alice@example.com").unwrap();
+        let builder = MailBuilder::new()
+            .from(alice.clone())
+            .to(alice)
+            .text_body("line\nbreaks\n");
+
+        let mut out = Vec::new();
+        builder.write_to(&mut out).unwrap();
+        assert_eq!(out, builder.build().unwrap().to_bytes().unwrap());
+
+        // boundaries are derived from the content, so they are the same for every write
+        let builder = builder.attachment("a.bin", "application/octet-stream", b"\0\xff");
+        let mut out = Vec::new();
+        builder.write_to(&mut out).unwrap();
+        assert_eq!(out, builder.build().unwrap().to_bytes().unwrap());
+        let out = std::string::String::from_utf8(out).unwrap();
+        assert!(out.contains("\r\n\r\nAP8=\r\n--=_"));
+        assert!(out.ends_with("--\r\n"));
+    }
+
+    #[test]
+    fn it_fails_to_build_a_mail_with_an_injected_content_type() {
+        let alice = Address::try_from("alice@example.com").unwrap();
+        let builder = MailBuilder::new()
+            .from(alice.clone())
+            .to(alice)
+            .date(DateTime::from_timestamp(0, 0).unwrap())
+            .attachment("a.txt", "text/plain\r\nBcc: eve@example.com", b"a");
+
+        assert_eq!(builder.build(), Err(BuildMailError::InvalidContentType));
+    }
+
+    #[test]
+    fn it_quotes_attachment_filenames() {
+        assert_eq!(
+            filename_parameter("say \"hi\".txt"),
+            "filename=\"say \\\"hi\\\".txt\""
+        );
+        assert_eq!(
+            filename_parameter("a/b\r\n.txt"),
+            "filename=\"b.txt\";\r\n x-original-filename*=utf-8''a%2Fb%0D%0A.txt"
+        );
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_attaches_files() {
+        // the process ID keeps concurrent test runs apart
+        let name = format!("brief-attachment-test-{}.PNG", std::process::id());
+        let path = std::env::temp_dir().join(&name);
+        std::fs::write(&path, b"png").unwrap();
+
+        let builder = MailBuilder::new().attachment_from_path(&path).unwrap();
+        assert_eq!(builder.attachments[0].name, name);
+        assert_eq!(builder.attachments[0].content_type, "image/png");
+        assert_eq!(&*builder.attachments[0].content, b"png");
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(MailBuilder::new().attachment_from_path(&path).is_err());
+    }
+
+    #[test]
+    fn it_forwards_an_encoded_body_with_attachments() {
+        let headers = HeaderMap::from_iter([
+            Header::Subject("Report"),
+            Header::MimeVersion,
+            Header::ContentType("text/plain; charset=utf-8"),
+            Header::ContentTransferEncoding("base64"),
+        ]);
+        let original = Mail::new(headers, b"R3LDvMOfZQ==");
+
+        let builder = original
+            .forward()
+            .from(Address::try_from("bob@example.com").unwrap())
+            .to(Address::try_from("carol@example.com").unwrap())
+            .date(DateTime::from_timestamp(0, 0).unwrap())
+            .attachment("a.txt", "text/plain", b"a");
+        let mail = builder.build().unwrap();
+
+        assert_eq!(mail.headers().get_all("Content-Type").count(), 1);
+        assert_eq!(
+            mail.headers().get_all("Content-Transfer-Encoding").count(),
+            1
+        );
+        assert_eq!(mail.headers().get_all("MIME-Version").count(), 1);
+        let body = core::str::from_utf8(mail.body()).unwrap();
+        assert!(body.contains(
+            "Content-Type: text/plain; charset=utf-8\r\n\
+             Content-Transfer-Encoding: base64\r\n\r\n\
+             R3LDvMOfZQ==\r\n"
+        ));
+
+        // without other parts, the body keeps its content headers
+        let builder = original
+            .forward()
+            .from(Address::try_from("bob@example.com").unwrap())
+            .to(Address::try_from("carol@example.com").unwrap())
+            .date(DateTime::from_timestamp(0, 0).unwrap());
+        let mail = builder.build().unwrap();
+        assert_eq!(
+            mail.headers().typed::<header::ContentType>(),
+            Some("text/plain; charset=utf-8")
+        );
+        assert_eq!(
+            mail.headers().typed::<header::ContentTransferEncoding>(),
+            Some("base64")
+        );
+        assert_eq!(mail.body(), b"R3LDvMOfZQ==");
+    }
+
+    #[test]
+    fn it_threads_replies_and_forwards() {
+        let headers = HeaderMap::from_iter([
+            Header::From("alice@example.com".try_into().unwrap()),
+            Header::ReplyTo("list@example.com".try_into().unwrap()),
+            Header::Subject("RE: Lunch"),
+            Header::MessageId("2@example.com"),
+            Header::References("<1@example.com>"),
+            Header::ContentType("text/html"),
+        ]);
+        let original = Mail::new(headers, b"<p>Hi</p>");
+
+        let reply = original.reply().unwrap();
+        let list = Address::try_from("list@example.com").unwrap();
+        assert_eq!(reply.to, [Mailbox::from(list)]);
+        assert_eq!(reply.subject.as_deref(), Some("RE: Lunch"));
+        assert_eq!(reply.in_reply_to, "<2@example.com>");
+        assert_eq!(reply.references, "<1@example.com> <2@example.com>");
+
+        let forward = original.forward();
+        assert_eq!(forward.subject.as_deref(), Some("Fwd: RE: Lunch"));
+        assert_eq!(forward.references, "<1@example.com> <2@example.com>");
+        assert!(forward.headers.is_empty());
+        assert_eq!(forward.body_type, Some("text/html"));
+        assert_eq!(forward.body_encoding, Some(TransferEncoding::SevenBit));
+        assert_eq!(forward.body, b"<p>Hi</p>");
+
+        let original = Mail::new(HeaderMap::from_iter([Header::Subject("Hi")]), b"");
+        assert!(original.reply().is_none());
     }
 }
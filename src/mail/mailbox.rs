@@ -1,6 +1,19 @@
-use super::{address::ParseAddressError, validate_part, Address, InvalidPartError};
+use core::fmt::{self, Write};
 
-#[derive(Debug)]
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+#[cfg(feature = "alloc")]
+use super::OwnedAddress;
+use super::{
+    address::ParseAddressError,
+    encoded_word,
+    syntax::{is_atext, parse_exact, Cursor},
+    validate::validate_name,
+    Address, InvalidPartError, ValidationOptions,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ParseMailboxError {
     MissingAngleBrackets,
     MissingOpeningAngleBracket,
@@ -10,6 +23,41 @@ pub enum ParseMailboxError {
     InvalidAddress(ParseAddressError),
 }
 
+impl fmt::Display for ParseMailboxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MissingAngleBrackets => "mailbox is missing angle brackets",
+            Self::MissingOpeningAngleBracket => "mailbox is missing an opening angle bracket",
+            Self::MissingClosingAngleBracket => "mailbox is missing a closing angle bracket",
+            Self::WrongOrderAngleBrackets => "mailbox has angle brackets in the wrong order",
+            Self::InvalidName(_) => "invalid name",
+            Self::InvalidAddress(_) => "invalid address",
+        })
+    }
+}
+
+impl core::error::Error for ParseMailboxError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidName(error) => Some(error),
+            Self::InvalidAddress(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl ParseMailboxError {
+    /// Moves the offset of an invalid character, for a mailbox that was parsed from a slice of
+    /// the input.
+    pub(crate) fn offset(self, by: usize) -> Self {
+        match self {
+            Self::InvalidName(error) => Self::InvalidName(error.offset(by)),
+            Self::InvalidAddress(error) => Self::InvalidAddress(error.offset(by)),
+            error => error,
+        }
+    }
+}
+
 impl From<ParseAddressError> for ParseMailboxError {
     fn from(value: ParseAddressError) -> Self {
         Self::InvalidAddress(value)
@@ -41,20 +89,79 @@ impl<'a> Mailbox<'a> {
     /// ```
     pub fn try_new(name: Option<&'a str>, address: Address<'a>) -> Result<Self, ParseMailboxError> {
         if let Some(name) = name {
-            validate_part(name).map_err(|e| ParseMailboxError::InvalidName(e))?;
+            validate_name(name).map_err(ParseMailboxError::InvalidName)?;
         }
 
         // TODO: can't throw 'InvalidAddress' but TryFrom impl can
 
         Ok(Self { name, address })
     }
-}
 
-impl<'a> TryFrom<&'a str> for Mailbox<'a> {
-    type Error = ParseMailboxError;
+    pub fn name(&self) -> Option<&'a str> {
+        self.name
+    }
 
-    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
-        match (value.find('<'), value.find('>')) {
+    pub fn address(&self) -> &Address<'a> {
+        &self.address
+    }
+
+    /// Splits the mailbox into its name and address.
+    ///
+    /// ```
+    /// use brief::mail::Mailbox;
+    ///
+    /// let (name, address) = Mailbox::try_from("John <john@example.com>").unwrap().into_parts();
+    /// assert_eq!(name, Some("John"));
+    /// assert_eq!(address.user(), "john");
+    /// ```
+    pub fn into_parts(self) -> (Option<&'a str>, Address<'a>) {
+        (self.name, self.address)
+    }
+
+    /// Like [`Mailbox::try_from`], but allows UTF-8 in the address, as RFC 6532 does.
+    ///
+    /// ```
+    /// use brief::mail::Mailbox;
+    ///
+    /// let mailbox = Mailbox::parse_international("Jöran <jöran@bücher.de>").unwrap();
+    /// assert!(mailbox.requires_smtputf8());
+    /// ```
+    pub fn parse_international(value: &'a str) -> Result<Self, ParseMailboxError> {
+        Self::parse(value, Address::parse_international)
+    }
+
+    /// Like [`Mailbox::try_from`], but validates the address against the grammar of the given
+    /// options.
+    ///
+    /// ```
+    /// use brief::mail::{Mailbox, ValidationOptions};
+    ///
+    /// let options = ValidationOptions::smtp();
+    /// assert!(Mailbox::parse_with("John <john@example.com>", options).is_ok());
+    /// assert!(Mailbox::parse_with("John <john@example.com (work)>", options).is_err());
+    /// ```
+    pub fn parse_with(
+        value: &'a str,
+        options: ValidationOptions,
+    ) -> Result<Self, ParseMailboxError> {
+        Self::parse(value, |address| Address::parse_with(address, options))
+    }
+
+    /// Returns whether the address of the mailbox contains UTF-8, so mail to or from it can
+    /// only be sent to servers that support the SMTPUTF8 extension. The name doesn't matter,
+    /// since it is written as encoded words.
+    pub fn requires_smtputf8(&self) -> bool {
+        self.address.requires_smtputf8()
+    }
+
+    fn parse(
+        value: &'a str,
+        parse_address: impl FnOnce(&'a str) -> Result<Address<'a>, ParseAddressError>,
+    ) -> Result<Self, ParseMailboxError> {
+        // brackets in the quoted strings and comments of the name don't count
+        let left = find_unquoted(value, b'<');
+        let right = find_unquoted(value, b'>');
+        match (left, right) {
             (None, None) => Err(ParseMailboxError::MissingAngleBrackets),
             (None, Some(_)) => Err(ParseMailboxError::MissingOpeningAngleBracket),
             (Some(_), None) => Err(ParseMailboxError::MissingClosingAngleBracket),
@@ -63,22 +170,280 @@ impl<'a> TryFrom<&'a str> for Mailbox<'a> {
                     return Err(ParseMailboxError::WrongOrderAngleBrackets);
                 }
 
-                // we can unwrap here because we are sure the string includes a '<'.
-                let (name_str, rest) = value.split_once('<').unwrap();
-                let address_str = rest.split_once('>').unwrap().0;
+                // the whitespace before the angle brackets separates them from the name
+                let name = value[..left].trim();
+                let address = value[left + 1..].split_once('>').map_or("", |(a, _)| a);
+
+                // a quoted name without escapes is the text between the quotes, which is quoted
+                // again when it is written
+                let name = match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+                    Some(inner) if !inner.contains(['"', '\\']) => inner,
+                    _ => name,
+                };
+                let address = parse_address(address).map_err(|e| e.offset(left + 1))?;
+
+                Ok(Self {
+                    name: (!name.is_empty()).then_some(name),
+                    address,
+                })
+            }
+        }
+    }
+
+    /// Copies the mailbox into an [`OwnedMailbox`], which doesn't borrow its input.
+    ///
+    /// ```
+    /// use brief::mail::{Mailbox, OwnedMailbox};
+    ///
+    /// fn parse(input: String) -> OwnedMailbox {
+    ///     Mailbox::try_from(input.as_str()).unwrap().into_owned()
+    /// }
+    ///
+    /// let mailbox = parse(String::from("name <user@domain.com>"));
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn into_owned(self) -> OwnedMailbox {
+        OwnedMailbox {
+            name: self.name.map(String::from),
+            address: self.address.into_owned(),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Mailbox<'a> {
+    type Error = ParseMailboxError;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        Self::parse(value, Address::try_from)
+    }
+}
+
+/// Creates a mailbox without a name.
+impl<'a> From<Address<'a>> for Mailbox<'a> {
+    fn from(address: Address<'a>) -> Self {
+        Self {
+            name: None,
+            address,
+        }
+    }
+}
+
+impl fmt::Display for Mailbox<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name.map(str::trim) {
+            Some(name) if !name.is_empty() => {
+                write!(f, "{} <{}>", DisplayName(name), self.address)
+            }
+            _ => write!(f, "<{}>", self.address),
+        }
+    }
+}
+
+impl Mailbox<'_> {
+    /// Returns the mailbox as it is written, but with the name as it is instead of as encoded
+    /// words, so it parses back to the same name with [`Mailbox::parse_international`].
+    #[cfg(feature = "serde")]
+    pub(crate) fn unencoded(&self) -> impl fmt::Display + '_ {
+        struct Unencoded<'a, 'b>(&'b Mailbox<'a>);
+
+        impl fmt::Display for Unencoded<'_, '_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let Mailbox { name, address } = self.0;
+                match name.map(str::trim) {
+                    Some(name) if !name.is_empty() => {
+                        DisplayName(name).fmt_unencoded(f)?;
+                        write!(f, " <{}>", address)
+                    }
+                    _ => write!(f, "<{}>", address),
+                }
+            }
+        }
+
+        Unencoded(self)
+    }
+}
+
+/// Returns the offset of the first occurrence of a byte outside quoted strings and comments.
+fn find_unquoted(value: &str, target: u8) -> Option<usize> {
+    let (mut quoted, mut escaped, mut comment) = (false, false, 0usize);
+    for (i, b) in value.bytes().enumerate() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match b {
+            b'\\' if quoted || comment > 0 => escaped = true,
+            b'"' if comment == 0 => quoted = !quoted,
+            b'(' if !quoted => comment += 1,
+            b')' if !quoted => comment = comment.saturating_sub(1),
+            _ if b == target && !quoted && comment == 0 => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// A display name as it is written in a header: as atoms if it only contains atom characters
+/// and whitespace, as encoded words if it contains non-ASCII characters, and as a quoted string
+/// otherwise. A name that was parsed as a quoted string is kept as it is.
+struct DisplayName<'a>(&'a str);
 
-                let name = (!name_str.is_empty()).then(|| name_str).or_else(|| None);
-                let address = Address::try_from(address_str)?;
+impl fmt::Display for DisplayName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.0;
+        if parse_exact(name, true, Cursor::quoted_string).is_ok() {
+            let inner = &name[1..name.len() - 1];
+            return match name.is_ascii() {
+                true => f.write_str(name),
+                // encoded words can't be quoted, so they replace the quotes
+                false if !inner.contains('\\') => encoded_word::encode(inner).fmt(f),
+                false => encoded_word::encode(name).fmt(f),
+            };
+        }
+
+        if encoded_word::needs_encoding(name) {
+            return encoded_word::encode(name).fmt(f);
+        }
+        if name.chars().all(|c| is_atext(c) || c == ' ' || c == '\t') {
+            return f.write_str(name);
+        }
 
-                Ok(Self { name, address })
+        self.quote(f)
+    }
+}
+
+impl DisplayName<'_> {
+    /// Writes the name as a quoted string.
+    fn quote(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_char('"')?;
+        for c in self.0.chars() {
+            if c == '"' || c == '\\' {
+                f.write_char('\\')?;
             }
+            f.write_char(c)?;
+        }
+        f.write_char('"')
+    }
+
+    /// Writes the name like [`fmt::Display`], but keeps non-ASCII characters as they are.
+    #[cfg(feature = "serde")]
+    fn fmt_unencoded(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.0;
+        let is_text = |c: char| is_atext(c) || c == ' ' || c == '\t' || !c.is_ascii();
+        if parse_exact(name, true, Cursor::quoted_string).is_ok() || name.chars().all(is_text) {
+            return f.write_str(name);
+        }
+
+        self.quote(f)
+    }
+}
+
+/// Represents a mailbox that owns its name and address
+///
+/// An `OwnedMailbox` can be stored in long-lived structs, while [`Mailbox`] borrows from the
+/// string it was parsed from. The two convert into each other:
+/// ```
+/// use brief::mail::{Mailbox, OwnedMailbox};
+///
+/// let owned = OwnedMailbox::try_from("name <user@domain.com>").unwrap();
+/// let borrowed: Mailbox = owned.as_mailbox();
+///
+/// assert_eq!(OwnedMailbox::from(borrowed), owned);
+/// ```
+///
+/// It can also be parsed with [`str::parse`]:
+/// ```
+/// use brief::mail::OwnedMailbox;
+///
+/// let mailbox: OwnedMailbox = "name <user@domain.com>".parse().unwrap();
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd, Hash, Eq, Ord)]
+pub struct OwnedMailbox {
+    pub name: Option<String>,
+    pub address: OwnedAddress,
+}
+
+#[cfg(feature = "alloc")]
+impl OwnedMailbox {
+    /// Tries to create a mailbox from a name and address, returning an error if the name is
+    /// invalid.
+    ///
+    /// ```
+    /// use brief::mail::OwnedMailbox;
+    ///
+    /// let mailbox = OwnedMailbox::try_new(Some("name"), "user@domain.com".try_into().unwrap()).unwrap();
+    /// ```
+    pub fn try_new(name: Option<&str>, address: OwnedAddress) -> Result<Self, ParseMailboxError> {
+        if let Some(name) = name {
+            validate_name(name).map_err(ParseMailboxError::InvalidName)?;
+        }
+
+        Ok(Self {
+            name: name.map(String::from),
+            address,
+        })
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn address(&self) -> &OwnedAddress {
+        &self.address
+    }
+
+    /// Splits the mailbox into its name and address.
+    pub fn into_parts(self) -> (Option<String>, OwnedAddress) {
+        (self.name, self.address)
+    }
+
+    /// Borrows the mailbox as a [`Mailbox`].
+    pub fn as_mailbox(&self) -> Mailbox<'_> {
+        Mailbox {
+            name: self.name.as_deref(),
+            address: self.address.as_address(),
         }
     }
 }
 
+#[cfg(feature = "alloc")]
+impl From<Mailbox<'_>> for OwnedMailbox {
+    fn from(value: Mailbox<'_>) -> Self {
+        value.into_owned()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> From<&'a OwnedMailbox> for Mailbox<'a> {
+    fn from(value: &'a OwnedMailbox) -> Self {
+        value.as_mailbox()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl TryFrom<&str> for OwnedMailbox {
+    type Error = ParseMailboxError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Mailbox::try_from(value).map(Mailbox::into_owned)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::str::FromStr for OwnedMailbox {
+    type Err = ParseMailboxError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::mail::Mailbox;
+    use crate::mail::{InvalidPartError, Mailbox, ParseAddressError, ParseMailboxError};
 
     #[test]
     fn it_creates_a_mailbox() {
@@ -104,6 +469,46 @@ mod tests {
         assert!(mailbox.is_ok());
     }
 
+    #[test]
+    fn it_creates_a_mailbox_with_a_non_ascii_name() {
+        let mailbox = Mailbox::try_new(Some("Zoë"), "zoe@domain.com".try_into().unwrap());
+        assert!(mailbox.is_ok());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn it_encodes_non_ascii_names() {
+        use alloc::string::ToString;
+
+        let mailbox = Mailbox::try_from("Zoë <zoe@domain.com>").unwrap();
+        assert_eq!(mailbox.to_string(), "=?UTF-8?B?Wm/Dqw==?= <zoe@domain.com>");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn it_quotes_names_with_specials() {
+        use crate::mail::Address;
+        use alloc::string::ToString;
+
+        let address = Address::try_from("j@x.com").unwrap();
+        let display = |name| {
+            Mailbox {
+                name: Some(name),
+                address: address.clone(),
+            }
+            .to_string()
+        };
+
+        assert_eq!(display("John Doe"), "John Doe <j@x.com>");
+        assert_eq!(display("J. Doe"), "\"J. Doe\" <j@x.com>");
+        assert_eq!(
+            display("Doe, \"JD\" \\ John"),
+            r#""Doe, \"JD\" \\ John" <j@x.com>"#
+        );
+        assert_eq!(display(" \"Doe, John\" "), "\"Doe, John\" <j@x.com>");
+        assert_eq!(display("\"Zoë, A\""), "=?UTF-8?B?Wm/DqywgQQ==?= <j@x.com>");
+    }
+
     #[test]
     fn it_fails_when_the_brackets_are_invali() {
         let cases = [
@@ -119,4 +524,58 @@ mod tests {
 
         assert!(cases.iter().any(|c| *c))
     }
+
+    #[test]
+    fn it_reports_offsets_in_the_whole_mailbox() {
+        assert_eq!(
+            Mailbox::try_from("John <jo,hn@example.com>"),
+            Err(ParseMailboxError::InvalidAddress(
+                ParseAddressError::InvalidUser(InvalidPartError::ContainsForbiddenCharacter {
+                    ch: ',',
+                    at: 8
+                })
+            ))
+        );
+        assert_eq!(
+            Mailbox::try_new(Some(""), "user@domain.com".try_into().unwrap()),
+            Err(ParseMailboxError::InvalidName(InvalidPartError::IsEmpty))
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn it_parses_the_mailboxes_it_writes() {
+        use crate::mail::Address;
+        use alloc::string::ToString;
+
+        let address = Address::try_from("j@x.com").unwrap();
+        for name in ["<Alice>", "Doe, John", "a;b", "J. \"JD\" Doe"] {
+            let mailbox = Mailbox::try_new(Some(name), address.clone()).unwrap();
+            let written = mailbox.to_string();
+            let parsed = Mailbox::try_from(written.as_str()).unwrap();
+
+            assert_eq!(parsed.address, mailbox.address);
+            assert_eq!(parsed.to_string(), written);
+        }
+
+        let mailbox = Mailbox::try_from(r#""Doe, John" <john@x.com>"#).unwrap();
+        assert_eq!(mailbox.name, Some("Doe, John"));
+        let mailbox = Mailbox::try_from("Alice (<work>) <alice@x.com>").unwrap();
+        assert_eq!(mailbox.name, Some("Alice (<work>)"));
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn it_converts_between_borrowed_and_owned_mailboxes() {
+        use crate::mail::OwnedMailbox;
+        use alloc::string::String;
+
+        let input = String::from("name <user@domain.com>");
+        let owned = Mailbox::try_from(input.as_str()).unwrap().into_owned();
+        drop(input);
+
+        assert_eq!(owned.name(), Some("name"));
+        assert_eq!(OwnedMailbox::from(owned.as_mailbox()), owned);
+        assert!(OwnedMailbox::try_from("user@domain.com").is_err());
+    }
 }
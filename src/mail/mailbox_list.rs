@@ -0,0 +1,394 @@
+use core::fmt::{self, Write};
+
+use super::{mailbox::ParseMailboxError, syntax::offset_of, Address, Mailbox};
+
+/// The line length that folding keeps to, as recommended by RFC 5322.
+const LINE_LENGTH: usize = 78;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMailboxListError {
+    IsEmpty,
+    InvalidMailbox(ParseMailboxError),
+}
+
+impl fmt::Display for ParseMailboxListError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IsEmpty => f.write_str("mailbox list is empty"),
+            Self::InvalidMailbox(_) => f.write_str("invalid mailbox"),
+        }
+    }
+}
+
+impl core::error::Error for ParseMailboxListError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidMailbox(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseMailboxError> for ParseMailboxListError {
+    fn from(value: ParseMailboxError) -> Self {
+        Self::InvalidMailbox(value)
+    }
+}
+
+/// Represents a comma-separated list of mailboxes, as used in the To, Cc and Bcc headers
+///
+/// Commas in quoted names, comments and angle brackets don't separate mailboxes, and mailboxes
+/// can be bare addresses:
+/// ```
+/// use brief::mail::MailboxList;
+///
+/// let list = MailboxList::try_from(r#""Doe, John" <john@x.com>, b@y.com"#).unwrap();
+/// let addresses: Vec<_> = list.iter().map(|m| m.address).collect();
+///
+/// assert_eq!(addresses, ["john@x.com".try_into().unwrap(), "b@y.com".try_into().unwrap()]);
+/// ```
+///
+/// Displaying the list joins the mailboxes with `", "`, folding the line before a mailbox that
+/// would make it longer than 78 characters. Every mailbox is written like [`Mailbox`] writes
+/// it, so names are quoted or encoded as needed, and bare addresses stay bare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MailboxList<'a> {
+    list: &'a str,
+    /// Whether the list was parsed with [`MailboxList::parse_international`].
+    utf8: bool,
+}
+
+impl<'a> MailboxList<'a> {
+    /// Returns an iterator over the mailboxes in the list.
+    pub fn iter(&self) -> Mailboxes<'a> {
+        Mailboxes {
+            items: items(self.list),
+            utf8: self.utf8,
+        }
+    }
+
+    /// Like [`MailboxList::try_from`], but allows UTF-8 in addresses, as RFC 6532 does.
+    pub fn parse_international(value: &'a str) -> Result<Self, ParseMailboxListError> {
+        Self::parse(value, true)
+    }
+
+    /// Returns whether any address in the list contains UTF-8, so mail to or from it can only be
+    /// sent to servers that support the SMTPUTF8 extension.
+    pub fn requires_smtputf8(&self) -> bool {
+        self.iter().any(|m| m.requires_smtputf8())
+    }
+
+    fn parse(value: &'a str, utf8: bool) -> Result<Self, ParseMailboxListError> {
+        let mut items = items(value).peekable();
+        if items.peek().is_none() {
+            return Err(ParseMailboxListError::IsEmpty);
+        }
+
+        for item in items {
+            parse_item(item, utf8).map_err(|e| e.offset(offset_of(value, item)))?;
+        }
+
+        Ok(Self { list: value, utf8 })
+    }
+
+    /// Returns the list as it was written.
+    pub fn as_str(&self) -> &'a str {
+        self.list
+    }
+
+    /// Returns the number of mailboxes in the list.
+    pub fn len(&self) -> usize {
+        items(self.list).count()
+    }
+
+    /// Always returns `false`, since a list can't be created without mailboxes.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Folds the list for a header value that starts at the given column, for example after
+    /// `To: `.
+    ///
+    /// ```
+    /// use brief::mail::MailboxList;
+    ///
+    /// let list = MailboxList::try_from("a@x.com, b@y.com").unwrap();
+    /// assert_eq!(format!("To: {}", list.folded(4)), "To: a@x.com, b@y.com");
+    /// ```
+    pub fn folded(&self, column: usize) -> Folded<'a> {
+        Folded {
+            list: *self,
+            column,
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a str> for MailboxList<'a> {
+    type Error = ParseMailboxListError;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        Self::parse(value, false)
+    }
+}
+
+impl<'a> IntoIterator for &MailboxList<'a> {
+    type Item = Mailbox<'a>;
+    type IntoIter = Mailboxes<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl fmt::Display for MailboxList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.folded(0).fmt(f)
+    }
+}
+
+/// A folded mailbox list. Created by [`MailboxList::folded`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Folded<'a> {
+    list: MailboxList<'a>,
+    column: usize,
+}
+
+impl fmt::Display for Folded<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut column = self.column;
+
+        for (i, mailbox) in self.list.iter().enumerate() {
+            let item = Item(&mailbox);
+            let mut len = Len(0);
+            // counting the length can't fail
+            let _ = write!(len, "{item}");
+
+            if i > 0 {
+                f.write_str(",")?;
+                column += 1;
+
+                if column + 1 + len.0 > LINE_LENGTH {
+                    f.write_str("\r\n ")?;
+                    column = 1;
+                } else {
+                    f.write_str(" ")?;
+                    column += 1;
+                }
+            }
+
+            item.fmt(f)?;
+            column += len.0;
+        }
+
+        Ok(())
+    }
+}
+
+/// A mailbox in a list as it is written: as a bare address if it has no name.
+struct Item<'a, 'b>(&'b Mailbox<'a>);
+
+impl fmt::Display for Item<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.name.map(str::trim) {
+            Some(name) if !name.is_empty() => self.0.fmt(f),
+            _ => self.0.address.fmt(f),
+        }
+    }
+}
+
+/// Counts the bytes written to it.
+struct Len(usize);
+
+impl fmt::Write for Len {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+/// Iterator over the mailboxes in a [`MailboxList`].
+#[derive(Debug, Clone)]
+pub struct Mailboxes<'a> {
+    items: Items<'a>,
+    utf8: bool,
+}
+
+impl<'a> Iterator for Mailboxes<'a> {
+    type Item = Mailbox<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // every item was parsed successfully when the list was created
+        let utf8 = self.utf8;
+        self.items.find_map(|item| parse_item(item, utf8).ok())
+    }
+}
+
+/// Parses a mailbox in a list, which may be a bare address.
+fn parse_item(item: &str, utf8: bool) -> Result<Mailbox<'_>, ParseMailboxError> {
+    match (item.contains(['<', '>']), utf8) {
+        (true, false) => Mailbox::try_from(item),
+        (true, true) => Mailbox::parse_international(item),
+        (false, false) => Ok(Mailbox {
+            name: None,
+            address: Address::try_from(item)?,
+        }),
+        (false, true) => Ok(Mailbox {
+            name: None,
+            address: Address::parse_international(item)?,
+        }),
+    }
+}
+
+/// Iterator over the trimmed, non-empty items of a comma-separated list.
+#[derive(Debug, Clone)]
+struct Items<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for Items<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.rest.is_empty() {
+            let rest = self.rest;
+            let mut end = rest.len();
+            let (mut quoted, mut escaped, mut angle) = (false, false, false);
+            let mut comment = 0usize;
+
+            for (i, b) in rest.bytes().enumerate() {
+                if escaped {
+                    escaped = false;
+                    continue;
+                }
+
+                match b {
+                    b'\\' if quoted || comment > 0 => escaped = true,
+                    b'"' if comment == 0 => quoted = !quoted,
+                    b'(' if !quoted => comment += 1,
+                    b')' if !quoted => comment = comment.saturating_sub(1),
+                    b'<' if !quoted && comment == 0 => angle = true,
+                    b'>' if !quoted && comment == 0 => angle = false,
+                    b',' if !quoted && comment == 0 && !angle => {
+                        end = i;
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+
+            let item = rest[..end].trim();
+            self.rest = rest.get(end + 1..).unwrap_or("");
+
+            // empty items are allowed by the obsolete syntax of RFC 5322, so skip them
+            if !item.is_empty() {
+                return Some(item);
+            }
+        }
+
+        None
+    }
+}
+
+/// Returns an iterator over the items of a comma-separated list.
+fn items(list: &str) -> Items<'_> {
+    Items { rest: list }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{items, MailboxList, ParseMailboxListError};
+    use crate::mail::{ParseAddressError, ParseMailboxError};
+
+    #[test]
+    fn it_splits_on_commas_outside_quotes_comments_and_brackets() {
+        let list = r#""Doe, John" <j@x.com>, b@y.com (Bob, work),, <"c,d"@z.com>"#;
+        let split: std::vec::Vec<_> = items(list).collect();
+
+        assert_eq!(
+            split,
+            [
+                r#""Doe, John" <j@x.com>"#,
+                "b@y.com (Bob, work)",
+                r#"<"c,d"@z.com>"#
+            ]
+        );
+        assert_eq!(MailboxList::try_from(list).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn it_fails_to_create_an_empty_or_invalid_list() {
+        assert!(MailboxList::try_from(" , ").is_err());
+        assert!(MailboxList::try_from("a@x.com, b").is_err());
+        assert!(MailboxList::try_from("a@x.com, <b@y.com").is_err());
+
+        // offsets are relative to the whole list
+        let error = MailboxList::try_from("a@x.com, b@y .com").unwrap_err();
+        let ParseMailboxListError::InvalidMailbox(ParseMailboxError::InvalidAddress(
+            ParseAddressError::InvalidDomain(error),
+        )) = error
+        else {
+            panic!("expected an invalid domain, got {error:?}");
+        };
+        assert_eq!(error.span(), Some(13..14));
+    }
+
+    #[test]
+    fn it_yields_names_and_bare_addresses() {
+        let list = MailboxList::try_from("Alice <a@x.com>, b@y.com").unwrap();
+        let mut mailboxes = list.iter();
+
+        assert_eq!(mailboxes.next().unwrap().name, Some("Alice"));
+        assert_eq!(mailboxes.next().unwrap().name, None);
+        assert!(mailboxes.next().is_none());
+    }
+
+    #[test]
+    fn it_parses_international_lists() {
+        let list = "a@x.com, Jöran <jöran@bücher.de>";
+        assert!(MailboxList::try_from(list).is_err());
+
+        let list = MailboxList::parse_international(list).unwrap();
+        assert_eq!(list.iter().count(), 2);
+        assert!(list.requires_smtputf8());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn it_folds_long_lists() {
+        use alloc::string::ToString;
+
+        let list = MailboxList::try_from(
+            "first.person@example.com,second.person@example.com, third.person@example.com",
+        )
+        .unwrap();
+
+        assert_eq!(
+            list.to_string(),
+            "first.person@example.com, second.person@example.com, third.person@example.com"
+        );
+        assert_eq!(
+            list.folded(4).to_string(),
+            "first.person@example.com, second.person@example.com,\r\n third.person@example.com"
+        );
+        assert_eq!(
+            list.folded(60).to_string(),
+            "first.person@example.com,\r\n second.person@example.com, third.person@example.com"
+        );
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn it_writes_mailboxes_instead_of_the_input() {
+        use alloc::string::ToString;
+
+        let list = MailboxList::try_from("Zoë <zoe@x.com>, b@y.com (Bob)").unwrap();
+        assert!(!list.requires_smtputf8());
+        assert_eq!(
+            list.to_string(),
+            "=?UTF-8?B?Wm/Dqw==?= <zoe@x.com>, b@y.com"
+        );
+
+        let list = MailboxList::try_from("Evil\r\nBcc: victim@x.com <a@b.com>").unwrap();
+        assert!(!list.to_string().contains('\n'));
+    }
+}
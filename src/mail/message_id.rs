@@ -0,0 +1,303 @@
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+use super::{
+    syntax::{parse_exact, Cursor},
+    InvalidPartError,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseMessageIdError {
+    MissingAngleBrackets,
+    MissingAtSign,
+    /// The part before the `@` isn't a dot-atom.
+    InvalidLeft(InvalidPartError),
+    /// The part after the `@` isn't a dot-atom or a domain literal.
+    InvalidRight(InvalidPartError),
+}
+
+impl fmt::Display for ParseMessageIdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::MissingAngleBrackets => "message ID is missing angle brackets",
+            Self::MissingAtSign => "message ID is missing an '@'",
+            Self::InvalidLeft(_) => "invalid part before the '@'",
+            Self::InvalidRight(_) => "invalid part after the '@'",
+        })
+    }
+}
+
+impl core::error::Error for ParseMessageIdError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::InvalidLeft(error) | Self::InvalidRight(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Represents a message identifier, as used in the Message-ID, In-Reply-To and References
+/// headers
+///
+/// A message ID is written in angle brackets, with a unique part and a domain around an `@`:
+/// ```
+/// use brief::mail::MessageId;
+///
+/// let id = MessageId::try_from("<1234.5678@example.com>").unwrap();
+///
+/// assert_eq!(id.as_str(), "1234.5678@example.com");
+/// assert_eq!(id.right(), "example.com");
+/// assert_eq!(id.to_string(), "<1234.5678@example.com>");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MessageId<'a> {
+    /// The identifier without the angle brackets.
+    id: &'a str,
+    /// The offset of the `@` in the identifier.
+    at: usize,
+}
+
+impl<'a> MessageId<'a> {
+    /// Returns the identifier without the angle brackets, as the [`Header::MessageId`] body.
+    ///
+    /// [`Header::MessageId`]: super::Header::MessageId
+    pub fn as_str(&self) -> &'a str {
+        self.id
+    }
+
+    /// Returns the part before the `@`, which makes the identifier unique.
+    pub fn left(&self) -> &'a str {
+        &self.id[..self.at]
+    }
+
+    /// Returns the part after the `@`, usually the domain of the sender.
+    pub fn right(&self) -> &'a str {
+        &self.id[self.at + 1..]
+    }
+
+    /// Parses the identifiers in the body of an In-Reply-To or References header, which may be
+    /// separated by whitespace.
+    ///
+    /// ```
+    /// use brief::mail::MessageId;
+    ///
+    /// let ids: Vec<_> = MessageId::parse_list("<a@x.com>\r\n <b@y.com><c@z.com>")
+    ///     .map(|id| id.unwrap().as_str())
+    ///     .collect();
+    /// assert_eq!(ids, ["a@x.com", "b@y.com", "c@z.com"]);
+    /// ```
+    pub fn parse_list(value: &'a str) -> MessageIds<'a> {
+        MessageIds { rest: value }
+    }
+
+    /// Generates a new message ID for the given domain, from the current time and random bits.
+    ///
+    /// ```
+    /// use brief::mail::MessageId;
+    ///
+    /// let id = MessageId::generate("example.com").unwrap();
+    /// assert_eq!(id.as_message_id().right(), "example.com");
+    /// assert_ne!(id, MessageId::generate("example.com").unwrap());
+    /// ```
+    #[cfg(feature = "std")]
+    pub fn generate(domain: &str) -> Result<OwnedMessageId, ParseMessageIdError> {
+        use alloc::format;
+        use core::sync::atomic::{AtomicU64, Ordering};
+        use std::hash::{BuildHasher, Hasher};
+
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+        parse_exact(domain, true, Cursor::domain).map_err(ParseMessageIdError::InvalidRight)?;
+
+        let time = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |since| since.as_nanos());
+
+        // every RandomState has its own random keys
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+        hasher.write_u32(std::process::id());
+
+        let left = format!("{time:x}.{:016x}", hasher.finish());
+        Ok(OwnedMessageId {
+            at: left.len(),
+            id: format!("{left}@{domain}"),
+        })
+    }
+
+    /// Copies the identifier into an [`OwnedMessageId`], which doesn't borrow its input.
+    #[cfg(feature = "alloc")]
+    pub fn into_owned(self) -> OwnedMessageId {
+        OwnedMessageId {
+            id: String::from(self.id),
+            at: self.at,
+        }
+    }
+
+    /// Parses an identifier without the angle brackets.
+    pub(crate) fn parse_inner(id: &'a str) -> Result<Self, ParseMessageIdError> {
+        let mut cursor = Cursor::new(id, true);
+        let left = cursor
+            .dot_atom()
+            .map_err(ParseMessageIdError::InvalidLeft)?;
+        if !cursor.eat('@') {
+            return Err(match cursor.peek() {
+                None => ParseMessageIdError::MissingAtSign,
+                Some(c) => ParseMessageIdError::InvalidLeft(cursor.unexpected(c)),
+            });
+        }
+
+        let right = &id[left.len() + 1..];
+        parse_exact(right, true, Cursor::domain)
+            .map_err(|e| ParseMessageIdError::InvalidRight(e.offset(left.len() + 1)))?;
+
+        Ok(Self { id, at: left.len() })
+    }
+}
+
+impl<'a> TryFrom<&'a str> for MessageId<'a> {
+    type Error = ParseMessageIdError;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        let id = value
+            .strip_prefix('<')
+            .and_then(|v| v.strip_suffix('>'))
+            .ok_or(ParseMessageIdError::MissingAngleBrackets)?;
+
+        // offsets in errors are relative to the value, including the opening bracket
+        Self::parse_inner(id).map_err(|e| match e {
+            ParseMessageIdError::InvalidLeft(e) => ParseMessageIdError::InvalidLeft(e.offset(1)),
+            ParseMessageIdError::InvalidRight(e) => ParseMessageIdError::InvalidRight(e.offset(1)),
+            e => e,
+        })
+    }
+}
+
+impl fmt::Display for MessageId<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<{}>", self.id)
+    }
+}
+
+/// Iterator over the identifiers in a list. Created by [`MessageId::parse_list`].
+#[derive(Debug, Clone)]
+pub struct MessageIds<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for MessageIds<'a> {
+    type Item = Result<MessageId<'a>, ParseMessageIdError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.rest.trim_start();
+        if rest.is_empty() {
+            return None;
+        }
+
+        let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+        self.rest = &rest[end..];
+        Some(MessageId::try_from(&rest[..end]))
+    }
+}
+
+/// Represents a message identifier that owns its text
+///
+/// Generated identifiers are owned, and can be borrowed as a [`MessageId`]:
+/// ```
+/// use brief::mail::OwnedMessageId;
+///
+/// let id: OwnedMessageId = "<1234@example.com>".parse().unwrap();
+/// assert_eq!(id.as_message_id().left(), "1234");
+/// ```
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OwnedMessageId {
+    id: String,
+    at: usize,
+}
+
+#[cfg(feature = "alloc")]
+impl OwnedMessageId {
+    pub fn as_message_id(&self) -> MessageId<'_> {
+        MessageId {
+            id: &self.id,
+            at: self.at,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::str::FromStr for OwnedMessageId {
+    type Err = ParseMessageIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MessageId::try_from(s).map(MessageId::into_owned)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for OwnedMessageId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_message_id().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MessageId, ParseMessageIdError};
+    use crate::mail::InvalidPartError;
+
+    #[test]
+    fn it_parses_message_ids() {
+        let id = MessageId::try_from("<a.b@[192.0.2.1]>").unwrap();
+        assert_eq!((id.left(), id.right()), ("a.b", "[192.0.2.1]"));
+
+        assert_eq!(
+            MessageId::try_from("a@b.com"),
+            Err(ParseMessageIdError::MissingAngleBrackets)
+        );
+        assert_eq!(
+            MessageId::try_from("<ab.com>"),
+            Err(ParseMessageIdError::MissingAtSign)
+        );
+        assert_eq!(
+            MessageId::try_from("<a b@c.com>"),
+            Err(ParseMessageIdError::InvalidLeft(
+                InvalidPartError::ContainsForbiddenCharacter { ch: ' ', at: 2 }
+            ))
+        );
+        assert_eq!(
+            MessageId::try_from("<a@b@c.com>"),
+            Err(ParseMessageIdError::InvalidRight(
+                InvalidPartError::ContainsForbiddenCharacter { ch: '@', at: 4 }
+            ))
+        );
+    }
+
+    #[test]
+    fn it_fails_on_invalid_ids_in_a_list() {
+        let mut ids = MessageId::parse_list(" <a@x.com> b@y.com");
+        assert!(ids.next().unwrap().is_ok());
+        assert_eq!(
+            ids.next(),
+            Some(Err(ParseMessageIdError::MissingAngleBrackets))
+        );
+        assert_eq!(ids.next(), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn it_generates_unique_ids() {
+        let a = MessageId::generate("example.com").unwrap();
+        let b = MessageId::generate("example.com").unwrap();
+        assert_ne!(a, b);
+        assert_eq!(
+            MessageId::try_from(std::format!("{a}").as_str()).unwrap(),
+            a.as_message_id()
+        );
+
+        assert!(MessageId::generate("not a domain").is_err());
+    }
+}
@@ -1,18 +1,139 @@
 mod address;
-mod header;
+#[cfg(feature = "alloc")]
+pub mod alignment;
+mod base64;
+pub mod bidi;
+#[cfg(feature = "alloc")]
+pub mod dark_mode;
+mod date;
+#[cfg(feature = "diff")]
+pub mod diff;
+pub mod domain;
+pub mod encoded_word;
+pub mod filename;
+#[cfg(all(feature = "std", feature = "diff"))]
+pub mod golden;
+pub mod header;
+#[cfg(feature = "alloc")]
+mod header_map;
+mod html;
+#[cfg(feature = "lettre")]
+pub mod lettre;
+pub mod links;
+#[cfg(feature = "alloc")]
+pub mod lint;
+#[cfg(feature = "alloc")]
+#[allow(clippy::module_inception)]
 mod mail;
 mod mailbox;
+mod mailbox_list;
+mod message_id;
+mod options;
+mod punycode;
+#[cfg(feature = "serde")]
+mod serde;
+pub mod subject;
+pub mod syntax;
+pub mod text;
+#[cfg(feature = "alloc")]
+pub mod tracking;
+pub mod transfer_encoding;
 mod validate;
 
-#[derive(Debug)]
+/// An invalid part of an address
+///
+/// Errors about a character carry its byte offset in the parsed input, so it can be pointed out
+/// to the user:
+/// ```
+/// use brief::mail::{Address, InvalidPartError, ParseAddressError};
+///
+/// let Err(ParseAddressError::InvalidUser(error)) = Address::try_from("john<doe@example.com")
+/// else {
+///     panic!("expected an invalid user");
+/// };
+///
+/// assert_eq!(error, InvalidPartError::ContainsForbiddenCharacter { ch: '<', at: 4 });
+/// assert_eq!(error.span(), Some(4..5));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum InvalidPartError {
     IsEmpty,
-    ContainsForbiddenCharacter(char),
-    ContainsNonAsciiCharacter(char),
+    ContainsForbiddenCharacter {
+        ch: char,
+        /// The byte offset of the character.
+        at: usize,
+    },
+    ContainsNonAsciiCharacter {
+        ch: char,
+        /// The byte offset of the character.
+        at: usize,
+    },
+    /// The part doesn't match the given rule of the RFC 5322 grammar.
+    InvalidSyntax(Rule),
 }
 
-pub use address::Address;
+impl InvalidPartError {
+    /// Returns the byte range of the offending character, if the error is about one.
+    pub fn span(&self) -> Option<core::ops::Range<usize>> {
+        match *self {
+            Self::ContainsForbiddenCharacter { ch, at }
+            | Self::ContainsNonAsciiCharacter { ch, at } => Some(at..at + ch.len_utf8()),
+            _ => None,
+        }
+    }
+
+    /// Moves the offset of the offending character, for a part that was parsed from a slice of
+    /// the input.
+    pub(crate) fn offset(self, by: usize) -> Self {
+        match self {
+            Self::ContainsForbiddenCharacter { ch, at } => {
+                Self::ContainsForbiddenCharacter { ch, at: at + by }
+            }
+            Self::ContainsNonAsciiCharacter { ch, at } => {
+                Self::ContainsNonAsciiCharacter { ch, at: at + by }
+            }
+            error => error,
+        }
+    }
+}
+
+impl core::fmt::Display for InvalidPartError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::IsEmpty => f.write_str("is empty"),
+            Self::ContainsForbiddenCharacter { ch, at } => {
+                write!(f, "contains forbidden character {ch:?} at offset {at}")
+            }
+            Self::ContainsNonAsciiCharacter { ch, at } => {
+                write!(f, "contains non-ASCII character {ch:?} at offset {at}")
+            }
+            Self::InvalidSyntax(rule) => write!(f, "is not a valid {rule}"),
+        }
+    }
+}
+
+impl core::error::Error for InvalidPartError {}
+
+#[cfg(feature = "alloc")]
+pub use address::OwnedAddress;
+pub use address::{Address, ParseAddressError};
+pub use date::{DateTime, OutOfRangeError};
+pub use domain::{Domain, ParseDomainError};
 pub use header::Header;
-pub use mail::{Mail, MailBuilder};
-pub use mailbox::Mailbox;
-pub use validate::validate_part;
+#[cfg(feature = "alloc")]
+pub use header_map::HeaderMap;
+#[cfg(feature = "alloc")]
+pub use mail::{
+    BuildMailError, ContentId, DeliveryLoopError, Mail, MailBuilder, Missing, Present,
+    SerializeMailError,
+};
+#[cfg(feature = "alloc")]
+pub use mailbox::OwnedMailbox;
+pub use mailbox::{Mailbox, ParseMailboxError};
+pub use mailbox_list::{MailboxList, Mailboxes, ParseMailboxListError};
+#[cfg(feature = "alloc")]
+pub use message_id::OwnedMessageId;
+pub use message_id::{MessageId, MessageIds, ParseMessageIdError};
+pub use options::{Grammar, ValidationOptions};
+pub use syntax::Rule;
+pub use validate::{validate_part, validate_part_with};
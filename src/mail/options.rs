@@ -0,0 +1,81 @@
+/// The grammar addresses are validated against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Grammar {
+    /// The `addr-spec` rule of RFC 5322, with quoted local parts, comments and domain literals.
+    #[default]
+    Rfc5322,
+    /// The "valid email address" of the WHATWG HTML standard, as browsers validate
+    /// `<input type="email">`: atoms and dots in any order, `@`, and a host name.
+    Html5,
+    /// The `Mailbox` rule of RFC 5321, which is what servers accept in SMTP commands: no
+    /// comments, and a host name or an IP address literal as the domain.
+    Smtp,
+}
+
+/// Options for how strictly addresses and mailboxes are validated
+///
+/// Different applications need different rules: a sign-up form may want the permissive grammar
+/// browsers use, while a mail server wants what it can actually send to.
+/// ```
+/// use brief::mail::{Address, ValidationOptions};
+///
+/// let value = "john..doe@example.com";
+/// assert!(Address::try_from(value).is_err());
+/// assert!(Address::parse_with(value, ValidationOptions::html5()).is_ok());
+///
+/// let value = "john (work) @example.com";
+/// assert!(Address::try_from(value).is_ok());
+/// assert!(Address::parse_with(value, ValidationOptions::smtp()).is_err());
+/// ```
+///
+/// Every grammar enforces the length limits of RFC 5321.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ValidationOptions {
+    grammar: Grammar,
+    utf8: bool,
+}
+
+impl ValidationOptions {
+    /// Validates against RFC 5322, which is the default.
+    pub const fn strict() -> Self {
+        Self::with_grammar(Grammar::Rfc5322)
+    }
+
+    /// Validates against the grammar of the WHATWG HTML standard.
+    pub const fn html5() -> Self {
+        Self::with_grammar(Grammar::Html5)
+    }
+
+    /// Validates against RFC 5321, so addresses can be used in SMTP commands as they are.
+    pub const fn smtp() -> Self {
+        Self::with_grammar(Grammar::Smtp)
+    }
+
+    pub const fn with_grammar(grammar: Grammar) -> Self {
+        Self {
+            grammar,
+            utf8: false,
+        }
+    }
+
+    /// Sets whether UTF-8 is allowed in addresses, as RFC 6532 does. Sending mail to such an
+    /// address [requires SMTPUTF8](super::Address::requires_smtputf8).
+    ///
+    /// ```
+    /// use brief::mail::{Address, ValidationOptions};
+    ///
+    /// let options = ValidationOptions::smtp().allow_utf8(true);
+    /// assert!(Address::parse_with("jöran@bücher.de", options).is_ok());
+    /// ```
+    pub const fn allow_utf8(self, utf8: bool) -> Self {
+        Self { utf8, ..self }
+    }
+
+    pub const fn grammar(&self) -> Grammar {
+        self.grammar
+    }
+
+    pub const fn allows_utf8(&self) -> bool {
+        self.utf8
+    }
+}
@@ -0,0 +1,198 @@
+//! Punycode (RFC 3492), which encodes Unicode domain labels as ASCII.
+
+use core::fmt::{self, Write};
+
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+/// The longest label that can be decoded, which is the longest possible domain label.
+pub(crate) const MAX_DECODED_LEN: usize = 63;
+
+fn adapt(mut delta: u32, len: u32, first: bool) -> u32 {
+    delta /= if first { DAMP } else { 2 };
+    delta += delta / len;
+
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+fn threshold(k: u32, bias: u32) -> u32 {
+    k.saturating_sub(bias).clamp(T_MIN, T_MAX)
+}
+
+fn encode_digit(digit: u32) -> char {
+    match digit {
+        0..=25 => char::from(b'a' + digit as u8),
+        _ => char::from(b'0' + (digit - 26) as u8),
+    }
+}
+
+fn decode_digit(c: u8) -> Option<u32> {
+    match c {
+        b'a'..=b'z' => Some(u32::from(c - b'a')),
+        b'A'..=b'Z' => Some(u32::from(c - b'A')),
+        b'0'..=b'9' => Some(u32::from(c - b'0') + 26),
+        _ => None,
+    }
+}
+
+/// Writes the Punycode encoding of a label, without the `xn--` prefix. The label is passed as a
+/// function returning its characters, since they are iterated over more than once.
+pub(crate) fn encode<I: Iterator<Item = char>>(
+    label: impl Fn() -> I,
+    f: &mut impl Write,
+) -> fmt::Result {
+    let mut basic = 0;
+    for c in label().filter(char::is_ascii) {
+        f.write_char(c)?;
+        basic += 1;
+    }
+
+    let len = label().count() as u32;
+    if basic > 0 {
+        f.write_char('-')?;
+    }
+
+    let (mut n, mut delta, mut bias) = (INITIAL_N, 0u32, INITIAL_BIAS);
+    let mut handled = basic;
+
+    while handled < len {
+        let Some(m) = label().map(u32::from).filter(|&c| c >= n).min() else {
+            break;
+        };
+        delta = (m - n)
+            .checked_mul(handled + 1)
+            .and_then(|d| delta.checked_add(d))
+            .ok_or(fmt::Error)?;
+        n = m;
+
+        for c in label().map(u32::from) {
+            if c < n {
+                delta = delta.checked_add(1).ok_or(fmt::Error)?;
+            }
+            if c != n {
+                continue;
+            }
+
+            let mut q = delta;
+            let mut k = BASE;
+            loop {
+                let t = threshold(k, bias);
+                if q < t {
+                    break;
+                }
+                f.write_char(encode_digit(t + (q - t) % (BASE - t)))?;
+                q = (q - t) / (BASE - t);
+                k += BASE;
+            }
+            f.write_char(encode_digit(q))?;
+
+            bias = adapt(delta, handled + 1, handled == basic);
+            delta = 0;
+            handled += 1;
+        }
+
+        delta += 1;
+        n += 1;
+    }
+
+    Ok(())
+}
+
+/// Decodes a label encoded as Punycode, without the `xn--` prefix, into a buffer. Returns the
+/// number of decoded characters, or `None` if the label is not valid Punycode.
+pub(crate) fn decode(input: &str, out: &mut [char; MAX_DECODED_LEN]) -> Option<usize> {
+    let (basic, encoded) = match input.rfind('-') {
+        Some(i) => (&input[..i], &input[i + 1..]),
+        None => ("", input),
+    };
+
+    let mut len = 0;
+    for c in basic.chars() {
+        if !c.is_ascii() {
+            return None;
+        }
+        *out.get_mut(len)? = c;
+        len += 1;
+    }
+
+    let (mut n, mut i, mut bias) = (INITIAL_N, 0u32, INITIAL_BIAS);
+    let mut bytes = encoded.bytes().peekable();
+
+    while bytes.peek().is_some() {
+        let old_i = i;
+        let mut w = 1u32;
+        let mut k = BASE;
+
+        loop {
+            let digit = decode_digit(bytes.next()?)?;
+            i = digit.checked_mul(w).and_then(|d| i.checked_add(d))?;
+
+            let t = threshold(k, bias);
+            if digit < t {
+                break;
+            }
+            w = w.checked_mul(BASE - t)?;
+            k += BASE;
+        }
+
+        let count = len as u32 + 1;
+        bias = adapt(i - old_i, count, old_i == 0);
+        n = n.checked_add(i / count)?;
+        i %= count;
+
+        if len == MAX_DECODED_LEN {
+            return None;
+        }
+        let c = char::from_u32(n)?;
+        out.copy_within(i as usize..len, i as usize + 1);
+        out[i as usize] = c;
+        len += 1;
+        i += 1;
+    }
+
+    Some(len)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::{decode, encode, MAX_DECODED_LEN};
+    use alloc::string::String;
+
+    #[test]
+    fn it_encodes_and_decodes_labels() {
+        for (label, encoded) in [
+            ("bücher", "bcher-kva"),
+            ("münchen", "mnchen-3ya"),
+            ("例子", "fsqu00a"),
+            ("ドメイン名例", "eckwd4c7cu47r2wf"),
+            ("abc", "abc-"),
+        ] {
+            let mut out = String::new();
+            encode(|| label.chars(), &mut out).unwrap();
+            assert_eq!(out, encoded);
+
+            let mut buf = ['\0'; MAX_DECODED_LEN];
+            let len = decode(encoded, &mut buf).unwrap();
+            assert_eq!(buf[..len].iter().collect::<String>(), label);
+        }
+    }
+
+    #[test]
+    fn it_rejects_invalid_punycode() {
+        let mut buf = ['\0'; MAX_DECODED_LEN];
+        assert!(decode("bcher-kv!", &mut buf).is_none());
+        assert!(decode("bcher-k", &mut buf).is_none());
+        assert!(decode("ü-kva", &mut buf).is_none());
+    }
+}
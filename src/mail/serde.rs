@@ -0,0 +1,401 @@
+//! Serialization with [serde](https://serde.rs).
+//!
+//! Addresses, mailboxes and mailbox lists are serialized as strings, with names as they are
+//! rather than as encoded words. They are validated like [`TryFrom<&str>`] when deserialized,
+//! falling back to the international syntax of RFC 6532, so invalid input is rejected right
+//! away. Headers are
+//! serialized as a name and a value, as given rather than encoded, and mails as their headers,
+//! body and metadata.
+//!
+//! The borrowed types can only be deserialized from input that contains their strings as they
+//! are, so for example JSON strings with escapes fail. Use [`OwnedAddress`] and
+//! [`OwnedMailbox`] to deserialize configuration files.
+
+#[cfg(feature = "alloc")]
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+
+use ::serde::{de, ser::SerializeStruct, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{header, Address, Header, Mailbox, MailboxList, MessageId};
+#[cfg(feature = "alloc")]
+use super::{HeaderMap, Mail, OwnedAddress, OwnedMailbox};
+
+impl Serialize for Address<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Address<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <&str>::deserialize(deserializer)?;
+        Address::try_from(value)
+            .or_else(|_| Address::parse_international(value))
+            .map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Mailbox<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.unencoded())
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Mailbox<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <&str>::deserialize(deserializer)?;
+        Mailbox::try_from(value)
+            .or_else(|_| Mailbox::parse_international(value))
+            .map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for MailboxList<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for MailboxList<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <&str>::deserialize(deserializer)?;
+        MailboxList::try_from(value)
+            .or_else(|_| MailboxList::parse_international(value))
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Serialize for OwnedAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_address().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de> Deserialize<'de> for OwnedAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Address::try_from(value.as_str())
+            .or_else(|_| Address::parse_international(&value))
+            .map(Address::into_owned)
+            .map_err(de::Error::custom)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Serialize for OwnedMailbox {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.as_mailbox().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de> Deserialize<'de> for OwnedMailbox {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        Mailbox::try_from(value.as_str())
+            .or_else(|_| Mailbox::parse_international(&value))
+            .map(Mailbox::into_owned)
+            .map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Header<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut header = serializer.serialize_struct("Header", 2)?;
+        header.serialize_field("name", self.name())?;
+        match self {
+            Header::ReturnPath(address) | Header::DeliveredTo(address) => {
+                header.serialize_field("value", address)?
+            }
+            Header::Sender(mailbox) => header.serialize_field("value", mailbox)?,
+            Header::From(list)
+            | Header::To(list)
+            | Header::Cc(list)
+            | Header::Bcc(list)
+            | Header::ReplyTo(list)
+            | Header::OriginalTo(list) => header.serialize_field("value", list)?,
+            Header::MimeVersion => header.serialize_field("value", "1.0")?,
+            Header::Subject(value)
+            | Header::Date(value)
+            | Header::MessageId(value)
+            | Header::InReplyTo(value)
+            | Header::References(value)
+            | Header::ContentType(value)
+            | Header::ContentTransferEncoding(value)
+            | Header::Custom(_, value) => header.serialize_field("value", value)?,
+        }
+        header.end()
+    }
+}
+
+/// A header as it is serialized, before its value is validated.
+#[derive(Deserialize)]
+struct RawHeader<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Header<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let RawHeader { name, value } = RawHeader::deserialize(deserializer)?;
+        typed_header(name, value)
+    }
+}
+
+/// Returns the typed variant of the header with the given name, or a custom header.
+fn typed_header<'a, E: de::Error>(name: &'a str, value: &'a str) -> Result<Header<'a>, E> {
+    let list = || {
+        MailboxList::try_from(value)
+            .or_else(|_| MailboxList::parse_international(value))
+            .map_err(E::custom)
+    };
+    let is = |other: &str| name.eq_ignore_ascii_case(other);
+
+    // the angle brackets are optional in trace headers
+    let address = || {
+        let address = value.trim().trim_start_matches('<').trim_end_matches('>');
+        Address::try_from(address)
+            .or_else(|_| Address::parse_international(address))
+            .map_err(E::custom)
+    };
+    let ids = || {
+        MessageId::parse_list(value)
+            .try_for_each(|id| id.map(drop))
+            .map_err(E::custom)
+    };
+
+    Ok(match name {
+        _ if is("Return-Path") => Header::ReturnPath(address()?),
+        _ if is("Delivered-To") => Header::DeliveredTo(address()?),
+        _ if is("X-Original-To") => Header::OriginalTo(list()?),
+        _ if is("Sender") => Header::Sender(
+            Mailbox::try_from(value)
+                .or_else(|_| Mailbox::parse_international(value))
+                .map_err(E::custom)?,
+        ),
+        _ if is("From") => Header::From(list()?),
+        _ if is("To") => Header::To(list()?),
+        _ if is("Cc") => Header::Cc(list()?),
+        _ if is("Bcc") => Header::Bcc(list()?),
+        _ if is("Reply-To") => Header::ReplyTo(list()?),
+        _ if is("Subject") => Header::Subject(value),
+        _ if is("Date") => Header::Date(value),
+        // the value is the identifier, with optional angle brackets
+        _ if is("Message-ID") => {
+            let id = value.trim().trim_start_matches('<').trim_end_matches('>');
+            Header::MessageId(MessageId::parse_inner(id).map_err(E::custom)?.as_str())
+        }
+        _ if is("In-Reply-To") => Header::InReplyTo(ids().map(|_| value)?),
+        _ if is("References") => Header::References(ids().map(|_| value)?),
+        _ if is("MIME-Version") => match value.trim() {
+            "1.0" => Header::MimeVersion,
+            _ => return Err(E::invalid_value(de::Unexpected::Str(value), &"1.0")),
+        },
+        _ if is("Content-Type") => Header::ContentType(value),
+        _ if is("Content-Transfer-Encoding") => Header::ContentTransferEncoding(value),
+        _ if header::is_valid_name(name) => Header::Custom(name, value),
+        _ => {
+            return Err(E::invalid_value(
+                de::Unexpected::Str(name),
+                &"a header name",
+            ))
+        }
+    })
+}
+
+#[cfg(feature = "alloc")]
+impl Serialize for HeaderMap<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de: 'a, 'a> Deserialize<'de> for HeaderMap<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Header<'a>>::deserialize(deserializer).map(HeaderMap::from_iter)
+    }
+}
+
+/// Serializes a mail body as a string if it is UTF-8, and as bytes otherwise.
+#[cfg(feature = "alloc")]
+struct Body<'a>(&'a [u8]);
+
+#[cfg(feature = "alloc")]
+impl Serialize for Body<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match core::str::from_utf8(self.0) {
+            Ok(body) => serializer.serialize_str(body),
+            Err(_) => serializer.serialize_bytes(self.0),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de: 'a, 'a> Deserialize<'de> for Body<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BodyVisitor;
+
+        impl<'de> de::Visitor<'de> for BodyVisitor {
+            type Value = Body<'de>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a borrowed string or byte array")
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+                Ok(Body(v.as_bytes()))
+            }
+
+            fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+                Ok(Body(v))
+            }
+        }
+
+        deserializer.deserialize_bytes(BodyVisitor)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Serialize for Mail<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut mail = serializer.serialize_struct("Mail", 3)?;
+        mail.serialize_field("headers", self.headers())?;
+        mail.serialize_field("body", &Body(self.body()))?;
+        mail.serialize_field("metadata", self.metadata())?;
+        mail.end()
+    }
+}
+
+/// A mail as it is serialized.
+#[cfg(feature = "alloc")]
+#[derive(Deserialize)]
+struct RawMail<'a> {
+    #[serde(borrow)]
+    headers: HeaderMap<'a>,
+    #[serde(borrow)]
+    body: Body<'a>,
+    #[serde(default)]
+    metadata: BTreeMap<String, String>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'de: 'a, 'a> Deserialize<'de> for Mail<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let raw = RawMail::deserialize(deserializer)?;
+        let mut mail = Mail::new(raw.headers, raw.body.0);
+        *mail.metadata_mut() = raw.metadata;
+        Ok(mail)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::{string::ToString, vec::Vec};
+
+    use crate::mail::{
+        header::{DeliveredTo, OriginalTo},
+        Address, Header, HeaderMap, Mail, Mailbox, OwnedAddress, OwnedMailbox,
+    };
+
+    #[test]
+    fn it_rejects_invalid_addresses() {
+        let address: OwnedAddress = serde_json::from_str(r#""user@domain.com""#).unwrap();
+        assert_eq!(address.domain(), "domain.com");
+        assert_eq!(
+            serde_json::to_string(&address).unwrap(),
+            r#""user@domain.com""#
+        );
+
+        let error = serde_json::from_str::<OwnedAddress>(r#""user@@domain.com""#).unwrap_err();
+        assert!(error.to_string().starts_with("invalid domain"));
+        assert!(serde_json::from_str::<Vec<OwnedMailbox>>(r#"["<a@b.com", "c@d.com"]"#).is_err());
+
+        let mailbox: Mailbox = serde_json::from_str(r#""Alice <alice@example.com>""#).unwrap();
+        assert_eq!(mailbox.name, Some("Alice"));
+    }
+
+    #[test]
+    fn it_round_trips_international_addresses_and_names() {
+        let address = Address::parse_international("jöran@bücher.de").unwrap();
+        let json = serde_json::to_string(&address).unwrap();
+        assert_eq!(serde_json::from_str::<Address>(&json).unwrap(), address);
+        let owned = address.into_owned();
+        assert_eq!(serde_json::from_str::<OwnedAddress>(&json).unwrap(), owned);
+
+        let mailbox: Mailbox = serde_json::from_str(r#""Zoë <zoe@example.com>""#).unwrap();
+        assert_eq!(mailbox.name, Some("Zoë"));
+
+        for value in ["Zoë <zoe@example.com>", "\"Doe, John\" <john@example.com>"] {
+            let mailbox = Mailbox::try_from(value).unwrap();
+            let json = serde_json::to_string(&mailbox).unwrap();
+            assert_eq!(json, serde_json::to_string(value).unwrap());
+
+            // the quotes are escaped in JSON, so only the owned mailbox can be deserialized
+            let owned = mailbox.into_owned();
+            assert_eq!(serde_json::to_string(&owned).unwrap(), json);
+            assert_eq!(serde_json::from_str::<OwnedMailbox>(&json).unwrap(), owned);
+        }
+    }
+
+    #[test]
+    fn it_rejects_invalid_message_ids_and_header_names() {
+        let json = r#"[{"name":"Message-ID","value":"<1@example.com>"},{"name":"References","value":"<1@example.com> <2@example.com>"}]"#;
+        let headers: HeaderMap = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            headers.iter().next(),
+            Some(&Header::MessageId("1@example.com"))
+        );
+
+        for json in [
+            r#"[{"name":"Message-ID","value":"not an id"}]"#,
+            r#"[{"name":"In-Reply-To","value":"<1@example.com"}]"#,
+            r#"[{"name":"X Campaign","value":"spring"}]"#,
+            r#"[{"name":"X-Campaign:","value":"spring"}]"#,
+        ] {
+            assert!(serde_json::from_str::<HeaderMap>(json).is_err(), "{json}");
+        }
+    }
+
+    #[test]
+    fn it_round_trips_mail() {
+        let headers = HeaderMap::from_iter([
+            Header::From("Zoë <zoe@example.com>".try_into().unwrap()),
+            Header::Subject("Grüße"),
+            Header::MimeVersion,
+            Header::Custom("X-Campaign", "spring"),
+        ]);
+        let mut mail = Mail::new(headers, b"Hi!");
+        mail.metadata_mut().insert("order".into(), "1234".into());
+
+        let json = serde_json::to_string(&mail).unwrap();
+        assert_eq!(
+            json,
+            r#"{"headers":[{"name":"From","value":"Zoë <zoe@example.com>"},{"name":"Subject","value":"Grüße"},{"name":"MIME-Version","value":"1.0"},{"name":"X-Campaign","value":"spring"}],"body":"Hi!","metadata":{"order":"1234"}}"#
+        );
+        assert_eq!(serde_json::from_str::<Mail>(&json).unwrap(), mail);
+
+        let json = r#"{"headers":[{"name":"to","value":"a@"}],"body":""}"#;
+        assert!(serde_json::from_str::<Mail>(json).is_err());
+    }
+
+    #[test]
+    fn it_parses_delivery_headers() {
+        let json = r#"[{"name":"Delivered-To","value":"<alice@example.com>"},{"name":"x-original-to","value":"team@example.com"}]"#;
+        let headers: HeaderMap = serde_json::from_str(json).unwrap();
+
+        assert_eq!(
+            headers.typed::<DeliveredTo>(),
+            Some("alice@example.com".try_into().unwrap())
+        );
+        assert_eq!(
+            headers.typed::<OriginalTo>().map(|list| list.as_str()),
+            Some("team@example.com")
+        );
+    }
+}
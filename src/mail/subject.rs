@@ -0,0 +1,229 @@
+use core::fmt;
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Reply prefixes as used by common mail clients, compared case-insensitively.
+const REPLY_PREFIXES: [&str; 10] = [
+    "re", "aw", "sv", "antw", "odp", "ynt", "rif", "res", "回复", "答复",
+];
+
+/// Forward prefixes as used by common mail clients, compared case-insensitively.
+const FORWARD_PREFIXES: [&str; 9] = ["fwd", "fw", "wg", "tr", "doorst", "pd", "rv", "enc", "转发"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prefix {
+    Reply,
+    Forward,
+}
+
+/// Splits a single reply or forward prefix (like `Re:`, `AW:` or `Fwd[2]:`) off the start of
+/// a subject.
+fn split_prefix(subject: &str) -> Option<(Prefix, &str)> {
+    let subject = subject.trim_start();
+    let (colon, colon_len) = match (subject.find(':'), subject.find('：')) {
+        (Some(a), Some(b)) if b < a => (b, '：'.len_utf8()),
+        (Some(a), _) => (a, 1),
+        (None, Some(b)) => (b, '：'.len_utf8()),
+        (None, None) => return None,
+    };
+
+    let mut head = subject[..colon].trim_end();
+
+    // strip reply counters like `Re[2]:` or `Re(2):`
+    for (open, close) in [('[', ']'), ('(', ')')] {
+        if let Some(rest) = head.strip_suffix(close) {
+            if let Some((name, counter)) = rest.rsplit_once(open) {
+                if !counter.is_empty() && counter.chars().all(|c| c.is_ascii_digit()) {
+                    head = name.trim_end();
+                }
+            }
+        }
+    }
+
+    let rest = &subject[colon + colon_len..];
+    if REPLY_PREFIXES.iter().any(|p| head.eq_ignore_ascii_case(p)) {
+        Some((Prefix::Reply, rest))
+    } else if FORWARD_PREFIXES
+        .iter()
+        .any(|p| head.eq_ignore_ascii_case(p))
+    {
+        Some((Prefix::Forward, rest))
+    } else {
+        None
+    }
+}
+
+/// Removes all leading reply and forward prefixes from a subject.
+///
+/// ```
+/// use brief::mail::subject;
+///
+/// assert_eq!(subject::strip_prefixes("Re: AW: Fwd: lunch"), "lunch");
+/// ```
+pub fn strip_prefixes(subject: &str) -> &str {
+    let mut subject = subject;
+    while let Some((_, rest)) = split_prefix(subject) {
+        subject = rest;
+    }
+
+    subject.trim()
+}
+
+/// Returns whether the subject starts with a reply prefix like `Re:` or `AW:`.
+///
+/// ```
+/// use brief::mail::subject;
+///
+/// assert!(subject::is_reply("Re: lunch"));
+/// assert!(!subject::is_reply("Fwd: Re: lunch"));
+/// ```
+pub fn is_reply(subject: &str) -> bool {
+    matches!(split_prefix(subject), Some((Prefix::Reply, _)))
+}
+
+/// Returns whether the subject starts with a forward prefix like `Fwd:` or `WG:`.
+///
+/// ```
+/// use brief::mail::subject;
+///
+/// assert!(subject::is_forward("Fwd: Re: lunch"));
+/// assert!(!subject::is_forward("Re: Fwd: lunch"));
+/// ```
+pub fn is_forward(subject: &str) -> bool {
+    matches!(split_prefix(subject), Some((Prefix::Forward, _)))
+}
+
+/// A subject without reply and forward prefixes, with all whitespace collapsed into single
+/// spaces. Created by [`normalize`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalized<'a>(&'a str);
+
+impl fmt::Display for Normalized<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, word) in self.0.split_whitespace().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(word)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Normalizes a subject by stripping all reply and forward prefixes and collapsing whitespace.
+///
+/// The result implements `Display`, so normalizing does not allocate.
+///
+/// ```
+/// use brief::mail::subject;
+///
+/// let normalized = subject::normalize("Re: RE:  Sv:  quarterly \t report ");
+/// assert_eq!(format!("{}", normalized), "quarterly report");
+/// ```
+pub fn normalize(subject: &str) -> Normalized<'_> {
+    Normalized(strip_prefixes(subject))
+}
+
+/// A subject cut down to a maximum number of grapheme clusters. Created by [`truncate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Truncated<'a> {
+    text: &'a str,
+    truncated: bool,
+    /// Whether the ellipsis fits, which it doesn't if nothing may be kept.
+    ellipsis: bool,
+}
+
+impl<'a> Truncated<'a> {
+    /// Returns the kept part of the subject, without the ellipsis.
+    pub fn as_str(&self) -> &'a str {
+        self.text
+    }
+
+    /// Returns whether anything was cut off.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+impl fmt::Display for Truncated<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.text)?;
+        if self.ellipsis {
+            f.write_str("…")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Truncates a subject to at most `max` grapheme clusters, including the trailing ellipsis
+/// that is displayed when anything was cut off. Emoji and combined characters are never split.
+///
+/// ```
+/// use brief::mail::subject;
+///
+/// assert_eq!(format!("{}", subject::truncate("Quarterly report", 10)), "Quarterly…");
+/// assert_eq!(format!("{}", subject::truncate("Hi 👋🏽", 10)), "Hi 👋🏽");
+/// ```
+pub fn truncate(subject: &str, max: usize) -> Truncated<'_> {
+    let mut graphemes = subject.grapheme_indices(true);
+    if graphemes.clone().nth(max).is_none() {
+        return Truncated {
+            text: subject,
+            truncated: false,
+            ellipsis: false,
+        };
+    }
+
+    let end = match max {
+        0 => 0,
+        _ => graphemes.nth(max - 1).map(|(i, _)| i).unwrap_or(0),
+    };
+
+    Truncated {
+        text: subject[..end].trim_end(),
+        truncated: true,
+        ellipsis: max > 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_forward, is_reply, normalize, strip_prefixes, truncate};
+    use std::string::ToString;
+
+    #[test]
+    fn it_strips_localized_and_counted_prefixes() {
+        assert_eq!(strip_prefixes("AW: Re[2]: SV : Antw: Doorst: hi"), "hi");
+        assert_eq!(strip_prefixes("回复：转发: 你好"), "你好");
+        assert_eq!(strip_prefixes("Meeting: agenda"), "Meeting: agenda");
+    }
+
+    #[test]
+    fn it_detects_replies_and_forwards() {
+        assert!(is_reply("re: hello"));
+        assert!(is_forward("FW: hello"));
+        assert!(!is_reply("Regarding: hello"));
+        assert!(!is_forward("hello"));
+    }
+
+    #[test]
+    fn it_collapses_whitespace_when_normalizing() {
+        assert_eq!(normalize("  a \r\n\t b  ").to_string(), "a b");
+        assert_eq!(normalize("Re:").to_string(), "");
+    }
+
+    #[test]
+    fn it_truncates_at_grapheme_boundaries() {
+        let subject = "ab👨‍👩‍👧‍👦cd";
+
+        assert_eq!(truncate(subject, 4).to_string(), "ab👨‍👩‍👧‍👦…");
+        assert_eq!(truncate(subject, 3).to_string(), "ab…");
+        assert_eq!(truncate(subject, 5).to_string(), subject);
+        assert!(!truncate(subject, 5).is_truncated());
+        assert_eq!(truncate(subject, 0).to_string(), "");
+        assert!(truncate(subject, 0).is_truncated());
+        assert!(!truncate("", 0).is_truncated());
+    }
+}
@@ -0,0 +1,582 @@
+//! The tokens of the RFC 5322 grammar, for crates that analyze headers themselves
+//!
+//! [`tokens`] splits a header body into atoms, quoted strings, domain literals, comments,
+//! special characters and whitespace:
+//! ```
+//! use brief::mail::syntax::{tokens, Token};
+//!
+//! let tokens: Vec<_> = tokens(r#""Doe, J." (work) <j@x.com>"#, false)
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//!
+//! assert_eq!(tokens[0], Token::QuotedString(r#""Doe, J.""#));
+//! assert_eq!(tokens[2], Token::Comment("(work)"));
+//! assert_eq!(tokens[4..7], [Token::Special('<'), Token::Atom("j"), Token::Special('@')]);
+//! ```
+//!
+//! A [`Cursor`] parses the larger constructs that addresses are made of, like dot-atoms and
+//! local parts.
+
+use core::fmt;
+
+use super::InvalidPartError;
+
+/// The rules of the RFC 5322 grammar that parsing can fail on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Rule {
+    /// Atoms separated by single dots, like `john.doe` or `example.com`
+    DotAtom,
+    /// Text enclosed in double quotes, like `"john doe"`
+    QuotedString,
+    /// A backslash followed by the character it escapes, like `\"`
+    QuotedPair,
+    /// Text enclosed in parentheses, like `(work)`
+    Comment,
+    /// A domain enclosed in square brackets, like `[192.0.2.1]`
+    DomainLiteral,
+    /// A host name of letters, digits and hyphens, as RFC 5321 requires, like `example.com`
+    Hostname,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Rule::DotAtom => "dot-atom",
+            Rule::QuotedString => "quoted string",
+            Rule::QuotedPair => "quoted pair",
+            Rule::Comment => "comment",
+            Rule::DomainLiteral => "domain literal",
+            Rule::Hostname => "host name",
+        })
+    }
+}
+
+/// Returns whether a character is `atext`, which makes up atoms.
+pub fn is_atext(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c)
+}
+
+/// Returns whether a character is `qtext`, which can appear unescaped in a quoted string.
+pub fn is_qtext(c: char) -> bool {
+    matches!(c, '!' | '#'..='[' | ']'..='~')
+}
+
+/// Returns whether a character is `dtext`, which can appear in a domain literal.
+pub fn is_dtext(c: char) -> bool {
+    matches!(c, '!'..='Z' | '^'..='~')
+}
+
+/// Returns whether a character is `ctext`, which can appear unescaped in a comment.
+pub fn is_ctext(c: char) -> bool {
+    matches!(c, '!'..='\'' | '*'..='[' | ']'..='~')
+}
+
+/// Returns whether a character is whitespace that may appear between tokens. The CR and LF of
+/// folded lines only count as whitespace together and followed by a space or tab, see
+/// [`Cursor::whitespace`].
+pub fn is_wsp(c: char) -> bool {
+    matches!(c, ' ' | '\t')
+}
+
+/// The error for a character that isn't allowed where it appears, at the given byte offset.
+pub(crate) fn forbidden(ch: char, at: usize) -> InvalidPartError {
+    if ch.is_ascii() {
+        InvalidPartError::ContainsForbiddenCharacter { ch, at }
+    } else {
+        InvalidPartError::ContainsNonAsciiCharacter { ch, at }
+    }
+}
+
+/// Returns the byte offset of a slice in the string it was taken from.
+pub(crate) fn offset_of(outer: &str, inner: &str) -> usize {
+    inner.as_ptr() as usize - outer.as_ptr() as usize
+}
+
+/// A position in input that is being parsed according to the RFC 5322 grammar.
+#[derive(Debug, Clone)]
+pub struct Cursor<'a> {
+    input: &'a str,
+    pos: usize,
+    /// Whether non-ASCII characters are allowed in atoms, quoted strings and domain literals,
+    /// as RFC 6532 does.
+    utf8: bool,
+}
+
+impl<'a> Cursor<'a> {
+    pub fn new(input: &'a str, utf8: bool) -> Self {
+        Self {
+            input,
+            pos: 0,
+            utf8,
+        }
+    }
+
+    /// Returns the byte offset of the next character.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Returns the input that hasn't been consumed.
+    pub fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    /// Returns whether a character is allowed as a non-ASCII character.
+    fn is_utf8(&self, c: char) -> bool {
+        self.utf8 && !c.is_ascii()
+    }
+
+    /// The error for the next character, which isn't allowed where it appears.
+    pub fn unexpected(&self, c: char) -> InvalidPartError {
+        forbidden(c, self.pos)
+    }
+
+    /// Returns the next character without consuming it.
+    pub fn peek(&self) -> Option<char> {
+        self.input[self.pos..].chars().next()
+    }
+
+    /// Consumes the next character if it is `c`.
+    pub fn eat(&mut self, c: char) -> bool {
+        let matches = self.peek() == Some(c);
+        if matches {
+            self.pos += c.len_utf8();
+        }
+        matches
+    }
+
+    /// Skips whitespace and (nested) comments.
+    pub fn skip_cfws(&mut self) -> Result<(), InvalidPartError> {
+        loop {
+            self.whitespace();
+            if self.peek() != Some('(') {
+                return Ok(());
+            }
+            self.comment()?;
+        }
+    }
+
+    /// Consumes whitespace, including the CRLF of folded lines, returning it.
+    pub fn whitespace(&mut self) -> &'a str {
+        let start = self.pos;
+        loop {
+            match self.fws_len() {
+                0 => return &self.input[start..self.pos],
+                len => self.pos += len,
+            }
+        }
+    }
+
+    /// Returns the length of the whitespace at the cursor: a space or tab, or a CRLF followed by
+    /// one. A CR or LF on its own would end the header, so it isn't whitespace.
+    fn fws_len(&self) -> usize {
+        let rest = self.rest().as_bytes();
+        match rest {
+            [b' ' | b'\t', ..] => 1,
+            [b'\r', b'\n', b' ' | b'\t', ..] => 3,
+            _ => 0,
+        }
+    }
+
+    /// Consumes a comment, which may contain nested comments, returning it including the
+    /// parentheses.
+    pub fn comment(&mut self) -> Result<&'a str, InvalidPartError> {
+        let start = self.pos;
+        if !self.eat('(') {
+            return Err(InvalidPartError::InvalidSyntax(Rule::Comment));
+        }
+
+        let mut depth = 1;
+        while depth > 0 {
+            match self.peek() {
+                None => return Err(InvalidPartError::InvalidSyntax(Rule::Comment)),
+                Some('(') => depth += 1,
+                Some(')') => depth -= 1,
+                Some('\\') => {
+                    self.pos += 1;
+                    self.quoted_pair()?;
+                    continue;
+                }
+                Some(c) if is_ctext(c) || !c.is_ascii() => {}
+                Some(_) if self.fws_len() > 0 => {
+                    self.pos += self.fws_len();
+                    continue;
+                }
+                Some(c) => return Err(self.unexpected(c)),
+            }
+            self.pos += self.peek().map_or(0, char::len_utf8);
+        }
+
+        Ok(&self.input[start..self.pos])
+    }
+
+    /// Consumes an `atom`, a run of atom characters without dots, returning it.
+    pub fn atom(&mut self) -> Result<&'a str, InvalidPartError> {
+        let start = self.pos;
+        while let Some(c) = self.peek().filter(|c| is_atext(*c) || self.is_utf8(*c)) {
+            self.pos += c.len_utf8();
+        }
+
+        match self.peek() {
+            _ if self.pos > start => Ok(&self.input[start..self.pos]),
+            None => Err(InvalidPartError::IsEmpty),
+            Some(c) => Err(self.unexpected(c)),
+        }
+    }
+
+    /// Consumes the character after a backslash.
+    fn quoted_pair(&mut self) -> Result<(), InvalidPartError> {
+        match self.peek() {
+            Some(c) if c.is_ascii_graphic() || c == ' ' || c == '\t' => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(InvalidPartError::InvalidSyntax(Rule::QuotedPair)),
+        }
+    }
+
+    /// Consumes a `dot-atom-text`, returning it.
+    pub fn dot_atom(&mut self) -> Result<&'a str, InvalidPartError> {
+        let start = self.pos;
+        let mut dot_allowed = false;
+
+        while let Some(c) = self.peek() {
+            if is_atext(c) || self.is_utf8(c) {
+                dot_allowed = true;
+            } else if c == '.' {
+                if !dot_allowed {
+                    return Err(InvalidPartError::InvalidSyntax(Rule::DotAtom));
+                }
+                dot_allowed = false;
+            } else {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+
+        if self.pos == start {
+            return match self.peek() {
+                None | Some('@') => Err(InvalidPartError::IsEmpty),
+                Some(c) => Err(self.unexpected(c)),
+            };
+        }
+        if !dot_allowed {
+            return Err(InvalidPartError::InvalidSyntax(Rule::DotAtom));
+        }
+
+        Ok(&self.input[start..self.pos])
+    }
+
+    /// Consumes atoms and dots in any order, as the HTML5 grammar allows, returning them.
+    pub fn loose_dot_atom(&mut self) -> Result<&'a str, InvalidPartError> {
+        let start = self.pos;
+        while let Some(c) = self
+            .peek()
+            .filter(|c| is_atext(*c) || *c == '.' || self.is_utf8(*c))
+        {
+            self.pos += c.len_utf8();
+        }
+
+        match self.peek() {
+            _ if self.pos > start => Ok(&self.input[start..self.pos]),
+            None | Some('@') => Err(InvalidPartError::IsEmpty),
+            Some(c) => Err(self.unexpected(c)),
+        }
+    }
+
+    /// Consumes a `quoted-string`, returning it including the quotes.
+    pub fn quoted_string(&mut self) -> Result<&'a str, InvalidPartError> {
+        let start = self.pos;
+        if !self.eat('"') {
+            return Err(InvalidPartError::InvalidSyntax(Rule::QuotedString));
+        }
+
+        loop {
+            match self.peek() {
+                None => return Err(InvalidPartError::InvalidSyntax(Rule::QuotedString)),
+                Some('"') => break,
+                Some('\\') => {
+                    self.pos += 1;
+                    self.quoted_pair()?;
+                }
+                Some(c) if is_qtext(c) || c == ' ' || c == '\t' || self.is_utf8(c) => {
+                    self.pos += c.len_utf8()
+                }
+                Some(c) => return Err(self.unexpected(c)),
+            }
+        }
+        self.pos += 1;
+
+        Ok(&self.input[start..self.pos])
+    }
+
+    /// Consumes a `domain-literal`, returning it including the brackets.
+    pub fn domain_literal(&mut self) -> Result<&'a str, InvalidPartError> {
+        let start = self.pos;
+        if !self.eat('[') {
+            return Err(InvalidPartError::InvalidSyntax(Rule::DomainLiteral));
+        }
+
+        loop {
+            match self.peek() {
+                None => return Err(InvalidPartError::InvalidSyntax(Rule::DomainLiteral)),
+                Some(']') => break,
+                Some(c) if is_dtext(c) || c == ' ' || c == '\t' || self.is_utf8(c) => {
+                    self.pos += c.len_utf8()
+                }
+                Some(c) => return Err(self.unexpected(c)),
+            }
+        }
+        self.pos += 1;
+
+        Ok(&self.input[start..self.pos])
+    }
+
+    /// Consumes a `local-part`: a dot-atom or a quoted string.
+    pub fn local_part(&mut self) -> Result<&'a str, InvalidPartError> {
+        match self.peek() {
+            Some('"') => self.quoted_string(),
+            _ => self.dot_atom(),
+        }
+    }
+
+    /// Consumes a `domain`: a dot-atom or a domain literal.
+    pub fn domain(&mut self) -> Result<&'a str, InvalidPartError> {
+        match self.peek() {
+            Some('[') => self.domain_literal(),
+            _ => self.dot_atom(),
+        }
+    }
+}
+
+/// A token of the RFC 5322 grammar. Created by [`tokens`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// A run of atom characters, like `john`. Dots are [`Token::Special`].
+    Atom(&'a str),
+    /// A quoted string, including the quotes.
+    QuotedString(&'a str),
+    /// A domain literal, including the brackets.
+    DomainLiteral(&'a str),
+    /// A comment, including the parentheses and any nested comments.
+    Comment(&'a str),
+    /// One of the special characters, like `<`, `@` or `.`.
+    Special(char),
+    /// Whitespace, including the CRLF of folded lines.
+    Whitespace(&'a str),
+}
+
+/// Returns an iterator over the tokens of the input. Allows UTF-8 in atoms, quoted strings and
+/// domain literals if `utf8` is set, as RFC 6532 does. The iterator ends after the first error.
+pub fn tokens(input: &str, utf8: bool) -> Tokens<'_> {
+    Tokens {
+        cursor: Cursor::new(input, utf8),
+        failed: false,
+    }
+}
+
+/// Iterator over the tokens of a string. Created by [`tokens`].
+#[derive(Debug, Clone)]
+pub struct Tokens<'a> {
+    cursor: Cursor<'a>,
+    failed: bool,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Token<'a>, InvalidPartError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let cursor = &mut self.cursor;
+        let token = match cursor.peek()? {
+            _ if cursor.fws_len() > 0 => Ok(Token::Whitespace(cursor.whitespace())),
+            '"' => cursor.quoted_string().map(Token::QuotedString),
+            '(' => cursor.comment().map(Token::Comment),
+            '[' => cursor.domain_literal().map(Token::DomainLiteral),
+            c @ (')' | '<' | '>' | ']' | ':' | ';' | '@' | '\\' | ',' | '.') => {
+                cursor.pos += 1;
+                Ok(Token::Special(c))
+            }
+            _ => cursor.atom().map(Token::Atom),
+        };
+
+        self.failed = token.is_err();
+        Some(token)
+    }
+}
+
+/// Parses input that must consist of exactly one token.
+///
+/// ```
+/// use brief::mail::syntax::{parse_exact, Cursor};
+///
+/// assert_eq!(parse_exact("john.doe", false, Cursor::dot_atom), Ok("john.doe"));
+/// assert!(parse_exact("john doe", false, Cursor::dot_atom).is_err());
+/// ```
+pub fn parse_exact<'a>(
+    input: &'a str,
+    utf8: bool,
+    parse: impl FnOnce(&mut Cursor<'a>) -> Result<&'a str, InvalidPartError>,
+) -> Result<&'a str, InvalidPartError> {
+    let mut cursor = Cursor::new(input, utf8);
+    let token = parse(&mut cursor)?;
+
+    match cursor.peek() {
+        None => Ok(token),
+        Some(c) => Err(cursor.unexpected(c)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_exact, tokens, Cursor, Rule, Token};
+    use crate::mail::InvalidPartError;
+
+    #[test]
+    fn it_parses_dot_atoms() {
+        assert_eq!(
+            parse_exact("john.doe", false, Cursor::dot_atom),
+            Ok("john.doe")
+        );
+        assert_eq!(parse_exact("a+b!c", false, Cursor::dot_atom), Ok("a+b!c"));
+        assert_eq!(
+            parse_exact("john..doe", false, Cursor::dot_atom),
+            Err(InvalidPartError::InvalidSyntax(Rule::DotAtom))
+        );
+        assert_eq!(
+            parse_exact(".john", false, Cursor::dot_atom),
+            Err(InvalidPartError::InvalidSyntax(Rule::DotAtom))
+        );
+        assert_eq!(
+            parse_exact("john.", false, Cursor::dot_atom),
+            Err(InvalidPartError::InvalidSyntax(Rule::DotAtom))
+        );
+        assert_eq!(
+            parse_exact("jo hn", false, Cursor::dot_atom),
+            Err(InvalidPartError::ContainsForbiddenCharacter { ch: ' ', at: 2 })
+        );
+        assert_eq!(
+            parse_exact("jöhn", false, Cursor::dot_atom),
+            Err(InvalidPartError::ContainsNonAsciiCharacter { ch: 'ö', at: 1 })
+        );
+    }
+
+    #[test]
+    fn it_parses_loose_dot_atoms() {
+        assert_eq!(
+            parse_exact(".john..doe.", false, Cursor::loose_dot_atom),
+            Ok(".john..doe.")
+        );
+        assert_eq!(
+            parse_exact("", false, Cursor::loose_dot_atom),
+            Err(InvalidPartError::IsEmpty)
+        );
+        assert_eq!(
+            parse_exact("\"john\"", false, Cursor::loose_dot_atom),
+            Err(InvalidPartError::ContainsForbiddenCharacter { ch: '"', at: 0 })
+        );
+    }
+
+    #[test]
+    fn it_allows_utf8_when_enabled() {
+        assert_eq!(parse_exact("jöhn", true, Cursor::dot_atom), Ok("jöhn"));
+        assert_eq!(
+            parse_exact("\"jöhn dœ\"", true, Cursor::quoted_string),
+            Ok("\"jöhn dœ\"")
+        );
+        assert_eq!(
+            parse_exact("bücher.de", true, Cursor::domain),
+            Ok("bücher.de")
+        );
+    }
+
+    #[test]
+    fn it_parses_quoted_strings() {
+        assert_eq!(
+            parse_exact(r#""john \"j\" doe""#, false, Cursor::quoted_string),
+            Ok(r#""john \"j\" doe""#)
+        );
+        assert_eq!(
+            parse_exact(r#""john"#, false, Cursor::quoted_string),
+            Err(InvalidPartError::InvalidSyntax(Rule::QuotedString))
+        );
+        assert_eq!(
+            parse_exact("\"a\\\n\"", false, Cursor::quoted_string),
+            Err(InvalidPartError::InvalidSyntax(Rule::QuotedPair))
+        );
+    }
+
+    #[test]
+    fn it_skips_nested_comments() {
+        let mut cursor = Cursor::new(" (a (nested \\) comment)) x", false);
+        assert_eq!(cursor.skip_cfws(), Ok(()));
+        assert_eq!(cursor.peek(), Some('x'));
+
+        let mut cursor = Cursor::new("(unterminated (comment)", false);
+        assert_eq!(
+            cursor.skip_cfws(),
+            Err(InvalidPartError::InvalidSyntax(Rule::Comment))
+        );
+    }
+
+    #[test]
+    fn it_parses_domain_literals() {
+        assert_eq!(
+            parse_exact("[192.0.2.1]", false, Cursor::domain),
+            Ok("[192.0.2.1]")
+        );
+        assert_eq!(
+            parse_exact("[192.0.2.1", false, Cursor::domain),
+            Err(InvalidPartError::InvalidSyntax(Rule::DomainLiteral))
+        );
+    }
+
+    #[test]
+    fn it_tokenizes_headers() {
+        let mut tokens = tokens("a.b (c) \"d\"\r\n [e] f\u{1}", false);
+
+        assert_eq!(tokens.next(), Some(Ok(Token::Atom("a"))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Special('.'))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Atom("b"))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Whitespace(" "))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Comment("(c)"))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Whitespace(" "))));
+        assert_eq!(tokens.next(), Some(Ok(Token::QuotedString("\"d\""))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Whitespace("\r\n "))));
+        assert_eq!(tokens.next(), Some(Ok(Token::DomainLiteral("[e]"))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Whitespace(" "))));
+        assert_eq!(tokens.next(), Some(Ok(Token::Atom("f"))));
+        assert_eq!(
+            tokens.next(),
+            Some(Err(InvalidPartError::ContainsForbiddenCharacter {
+                ch: '\u{1}',
+                at: 19
+            }))
+        );
+        assert_eq!(tokens.next(), None);
+    }
+
+    #[test]
+    fn it_only_folds_crlf_followed_by_whitespace() {
+        let mut tokens = tokens("a\r\nBcc", false);
+        assert_eq!(tokens.next(), Some(Ok(Token::Atom("a"))));
+        assert_eq!(
+            tokens.next(),
+            Some(Err(InvalidPartError::ContainsForbiddenCharacter {
+                ch: '\r',
+                at: 1
+            }))
+        );
+
+        let mut cursor = Cursor::new(" \n(a\rb) x", false);
+        assert_eq!(cursor.skip_cfws(), Ok(()));
+        assert_eq!(cursor.peek(), Some('\n'));
+
+        let mut cursor = Cursor::new("(a\r\n b)\r\n\tx", false);
+        assert_eq!(cursor.skip_cfws(), Ok(()));
+        assert_eq!(cursor.peek(), Some('x'));
+    }
+}
@@ -0,0 +1,398 @@
+#[cfg(feature = "alloc")]
+use alloc::string::String;
+
+/// The line that separates a body from its signature. The trailing space is significant.
+pub const SIGNATURE_DELIMITER: &str = "-- ";
+
+/// Returns the quote prefix of a line, like `> ` or `>> > `.
+#[cfg(feature = "alloc")]
+fn quote_prefix(line: &str) -> &str {
+    let len = line
+        .find(|c: char| c != '>' && c != ' ')
+        .unwrap_or(line.len());
+    let prefix = &line[..len];
+
+    // only the whitespace directly after the last '>' belongs to the prefix
+    match prefix.rfind('>') {
+        Some(i) => &line[..(i + 1 + usize::from(prefix[i + 1..].starts_with(' ')))],
+        None => "",
+    }
+}
+
+/// Returns the quote depth of a line. In format=flowed text only leading `>` characters count,
+/// otherwise `> > ` is a depth of two as well.
+#[cfg(feature = "alloc")]
+fn quote_depth(line: &str, flowed: bool) -> usize {
+    if flowed {
+        line.bytes().take_while(|b| *b == b'>').count()
+    } else {
+        quote_prefix(line).bytes().filter(|b| *b == b'>').count()
+    }
+}
+
+/// Returns whether a line is indented like a code block.
+#[cfg(feature = "alloc")]
+fn is_indented(line: &str) -> bool {
+    line.starts_with("    ") || line.starts_with('\t')
+}
+
+/// Hard-wraps a plain-text body so no line is longer than `width` characters.
+///
+/// Quoted lines keep their `>` prefix on every wrapped line. Fenced (```` ``` ````) and
+/// indented code blocks, the signature and words longer than `width` are left as they are.
+///
+/// ```
+/// use brief::mail::text;
+///
+/// let body = text::wrap("> this is quoted text that goes on\nmy reply", 16);
+/// assert_eq!(body, "> this is quoted\n> text that goes\n> on\nmy reply");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn wrap(body: &str, width: usize) -> String {
+    let newline = if body.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut out = String::with_capacity(body.len() + body.len() / width.max(1));
+    let mut in_fence = false;
+    let mut in_signature = false;
+
+    for (i, line) in body.split('\n').enumerate() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if i > 0 {
+            out.push_str(newline);
+        }
+
+        let fence = line.trim_start().starts_with("```");
+        in_fence ^= fence;
+        in_signature |= line == SIGNATURE_DELIMITER;
+
+        if fence || in_fence || in_signature || is_indented(line) || line.chars().count() <= width {
+            out.push_str(line);
+            continue;
+        }
+
+        let prefix = quote_prefix(line);
+        let available = width.saturating_sub(prefix.chars().count()).max(1);
+        let mut len = 0;
+
+        out.push_str(prefix);
+        for word in line[prefix.len()..].split_whitespace() {
+            let word_len = word.chars().count();
+            if len > 0 && len + 1 + word_len > available {
+                out.push_str(newline);
+                out.push_str(prefix);
+                len = 0;
+            }
+            if len > 0 {
+                out.push(' ');
+                len += 1;
+            }
+            out.push_str(word);
+            len += word_len;
+        }
+    }
+
+    out
+}
+
+/// Appends a signature to a plain-text body, separated by the standard `-- ` delimiter line.
+///
+/// ```
+/// use brief::mail::text;
+///
+/// let body = text::append_signature("Hi!\n", "John\nExample Inc.");
+/// assert_eq!(body, "Hi!\n-- \nJohn\nExample Inc.\n");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn append_signature(body: &str, signature: &str) -> String {
+    let newline = if body.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut out = String::with_capacity(body.len() + signature.len() + 8);
+
+    out.push_str(body);
+    if !body.is_empty() && !body.ends_with('\n') {
+        out.push_str(newline);
+    }
+    out.push_str(SIGNATURE_DELIMITER);
+    out.push_str(newline);
+
+    for line in signature.lines() {
+        out.push_str(line);
+        out.push_str(newline);
+    }
+
+    out
+}
+
+/// How attribution lines start, in English, German, French, Spanish, Dutch, Italian,
+/// Portuguese and Swedish.
+const ATTRIBUTION_STARTS: [&str; 8] = ["On ", "Am ", "Le ", "El ", "Op ", "Il ", "Em ", "Den "];
+
+/// The verbs used in attribution lines, in the same languages as [`ATTRIBUTION_STARTS`].
+const ATTRIBUTION_VERBS: [&str; 8] = [
+    "wrote",
+    "schrieb",
+    "a écrit",
+    "escribió",
+    "schreef",
+    "ha scritto",
+    "escreveu",
+    "skrev",
+];
+
+/// Separator lines that clients put above the original message, compared case-insensitively.
+const ORIGINAL_MESSAGE_SEPARATORS: [&str; 6] = [
+    "-----original message-----",
+    "-----ursprüngliche nachricht-----",
+    "-----message d'origine-----",
+    "-----mensaje original-----",
+    "-----oorspronkelijk bericht-----",
+    "-----messaggio originale-----",
+];
+
+/// Returns whether a line starts the quoted history, looking at the next line as well because
+/// some clients wrap long attribution lines.
+fn starts_history(line: &str, next: Option<&str>) -> bool {
+    let line = line.trim();
+
+    if ATTRIBUTION_STARTS.iter().any(|s| line.starts_with(s)) {
+        let attribution = |l: &str| {
+            l.trim_end().ends_with(':') && ATTRIBUTION_VERBS.iter().any(|v| l.contains(v))
+        };
+        let wrapped = |n: &str| {
+            n.trim_end().ends_with(':')
+                && ATTRIBUTION_VERBS
+                    .iter()
+                    .any(|v| line.contains(v) || n.contains(v))
+        };
+
+        return attribution(line) || next.is_some_and(wrapped);
+    }
+
+    if ORIGINAL_MESSAGE_SEPARATORS
+        .iter()
+        .any(|s| line.eq_ignore_ascii_case(s))
+    {
+        return true;
+    }
+
+    // Outlook puts a line of underscores above the headers of the original message
+    line.len() >= 16
+        && line.bytes().all(|b| b == b'_')
+        && next.is_some_and(|n| n.trim_start().starts_with("From:"))
+}
+
+/// Splits a plain-text body into the new content and the quoted history below it, so the
+/// history can be collapsed when displaying the message.
+///
+/// The history starts at an attribution line like `On Mon, Jan 1, 2024, Jane wrote:` (in
+/// several languages), at an original message separator, or at a block of `>` quoted lines
+/// that runs to the end of the body. Quotes that are replied to inline are new content.
+///
+/// ```
+/// use brief::mail::text;
+///
+/// let body = "Sounds good!\n\nOn Mon, 1 Jan 2024 at 10:00, Jane <jane@example.com> wrote:\n> Lunch?\n";
+/// let (new, history) = text::split_quoted(body);
+///
+/// assert_eq!(new, "Sounds good!");
+/// assert!(history.starts_with("On Mon"));
+/// ```
+pub fn split_quoted(body: &str) -> (&str, &str) {
+    let mut offset = 0;
+    let mut quoted_from = None;
+    let mut lines = body.split_inclusive('\n').peekable();
+
+    while let Some(line) = lines.next() {
+        if starts_history(line, lines.peek().copied()) {
+            quoted_from = Some(offset);
+            break;
+        }
+
+        if line.starts_with('>') {
+            quoted_from = quoted_from.or(Some(offset));
+        } else if !line.trim().is_empty() {
+            quoted_from = None;
+        }
+
+        offset += line.len();
+    }
+
+    match quoted_from {
+        Some(at) => (body[..at].trim_end(), &body[at..]),
+        None => (body.trim_end(), ""),
+    }
+}
+
+/// Options for [`quote`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QuoteOptions {
+    /// Leave out the signature of the quoted body.
+    pub strip_signature: bool,
+    /// Leave out lines that would end up quoted deeper than this.
+    pub max_depth: Option<usize>,
+    /// Treat the body as `format=flowed` (RFC 3676): space-stuffing is undone before quoting
+    /// and trailing spaces that mark soft line breaks are kept.
+    pub flowed: bool,
+}
+
+/// Quotes a plain-text body for a reply, incrementing the depth of lines that are already
+/// quoted.
+///
+/// ```
+/// use brief::mail::text::{self, QuoteOptions};
+///
+/// let options = QuoteOptions {
+///     strip_signature: true,
+///     ..Default::default()
+/// };
+///
+/// let quoted = text::quote("Sure!\n> Lunch?\n-- \nJane", options);
+/// assert_eq!(quoted, "> Sure!\n>> Lunch?");
+/// ```
+#[cfg(feature = "alloc")]
+pub fn quote(body: &str, options: QuoteOptions) -> String {
+    let newline = if body.contains("\r\n") { "\r\n" } else { "\n" };
+    let body = body.strip_suffix(newline).unwrap_or(body);
+    let mut out = String::with_capacity(body.len() + body.len() / 8);
+    let mut first = true;
+
+    for line in body.split(newline) {
+        if options.strip_signature && line == SIGNATURE_DELIMITER {
+            break;
+        }
+
+        let depth = quote_depth(line, options.flowed) + 1;
+        if options.max_depth.is_some_and(|max| depth > max) {
+            continue;
+        }
+
+        if !first {
+            out.push_str(newline);
+        }
+        first = false;
+
+        if depth > 1 {
+            out.push('>');
+            out.push_str(line);
+        } else if line.is_empty() {
+            out.push('>');
+        } else {
+            let line = match options.flowed {
+                true => line.strip_prefix(' ').unwrap_or(line),
+                false => line,
+            };
+            out.push_str("> ");
+            out.push_str(line);
+        }
+    }
+
+    out
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::{append_signature, quote, split_quoted, wrap, QuoteOptions};
+
+    #[test]
+    fn it_wraps_long_lines_at_word_boundaries() {
+        assert_eq!(wrap("aaa bbb ccc ddd", 7), "aaa bbb\nccc ddd");
+        assert_eq!(wrap("short\r\nline", 7), "short\r\nline");
+        assert_eq!(
+            wrap("see https://example.com/very/long ok", 10),
+            "see\nhttps://example.com/very/long\nok"
+        );
+    }
+
+    #[test]
+    fn it_preserves_nested_quote_prefixes() {
+        assert_eq!(wrap(">> > aa bb cc", 10), ">> > aa bb\n>> > cc");
+        assert_eq!(wrap(">aa bb cc", 6), ">aa bb\n>cc");
+    }
+
+    #[test]
+    fn it_leaves_code_and_signatures_alone() {
+        let body = "```\nlet x = a very long line;\n```\n    indented code goes here\n-- \nlong signature line";
+        assert_eq!(wrap(body, 10), body);
+    }
+
+    #[test]
+    fn it_appends_a_signature() {
+        assert_eq!(append_signature("", "me"), "-- \nme\n");
+        assert_eq!(append_signature("hi", "me"), "hi\n-- \nme\n");
+        assert_eq!(append_signature("hi\r\n", "me"), "hi\r\n-- \r\nme\r\n");
+    }
+
+    #[test]
+    fn it_quotes_and_increments_existing_quotes() {
+        let quoted = quote("hi\n\n> old\n>> older\n", QuoteOptions::default());
+        assert_eq!(quoted, "> hi\n>\n>> old\n>>> older");
+    }
+
+    #[test]
+    fn it_drops_quote_levels_beyond_the_maximum() {
+        let options = QuoteOptions {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(quote("a\n> b\n> > c\nd", options), "> a\n>> b\n> d");
+    }
+
+    #[test]
+    fn it_keeps_signatures_unless_asked_to_strip_them() {
+        assert_eq!(
+            quote("a\n-- \nme", QuoteOptions::default()),
+            "> a\n> -- \n> me"
+        );
+    }
+
+    #[test]
+    fn it_respects_format_flowed() {
+        let options = QuoteOptions {
+            flowed: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            quote("soft \r\n From here\r\n>quoted", options),
+            "> soft \r\n> From here\r\n>>quoted"
+        );
+    }
+
+    #[test]
+    fn it_splits_at_attribution_lines_in_several_languages() {
+        let body = "Gerne.\r\n\r\nAm 01.01.2024 um 10:00 schrieb Jane Doe\r\n<jane@example.com>:\r\n> Mittag?";
+        assert_eq!(split_quoted(body).0, "Gerne.");
+
+        let body = "Oui\nLe lun. 1 janv. 2024, Jane a écrit :\n> Midi ?";
+        assert_eq!(
+            split_quoted(body),
+            ("Oui", "Le lun. 1 janv. 2024, Jane a écrit :\n> Midi ?")
+        );
+    }
+
+    #[test]
+    fn it_splits_at_original_message_separators() {
+        let (new, history) = split_quoted("ok\n-----Original Message-----\nFrom: x");
+        assert_eq!(
+            (new, history),
+            ("ok", "-----Original Message-----\nFrom: x")
+        );
+
+        let (new, _) = split_quoted("ok\n________________________________\nFrom: x\nSent: y");
+        assert_eq!(new, "ok");
+    }
+
+    #[test]
+    fn it_only_treats_trailing_quotes_as_history() {
+        let inline = "> question one\nanswer one\n> question two\nanswer two\n";
+        assert_eq!(
+            split_quoted(inline),
+            ("> question one\nanswer one\n> question two\nanswer two", "")
+        );
+
+        let trailing = "answer\n\n> question\n>\n> more\n\n";
+        assert_eq!(
+            split_quoted(trailing),
+            ("answer", "> question\n>\n> more\n\n")
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use alloc::string::String;
+
+use super::{html, Mail};
+
+/// The metadata key under which [`record`] stores the URL of a tracking pixel, so audits can
+/// find the mails that were tracked.
+pub const METADATA_KEY: &str = "tracking-pixel";
+
+/// Inserts a 1×1 open-tracking pixel that loads the given URL into an HTML body. The pixel is
+/// placed before the closing `body` tag, or at the end if there is none.
+///
+/// Tracking is opt-in: generate a URL for every recipient, and [`record`] it on the mail.
+///
+/// ```
+/// use brief::mail::{tracking, HeaderMap, Mail};
+///
+/// let url = "https://t.example.com/open?r=42&m=7";
+/// let html = tracking::insert_pixel("<html><body><p>Hi!</p></body></html>", url);
+/// assert_eq!(
+///     html,
+///     "<html><body><p>Hi!</p>\
+///      <img src=\"https://t.example.com/open?r=42&amp;m=7\" width=\"1\" height=\"1\" alt=\"\" \
+///      style=\"display:block;border:0;width:1px;height:1px\"></body></html>"
+/// );
+///
+/// let mut mail = Mail::new(HeaderMap::new(), html.as_bytes());
+/// tracking::record(&mut mail, url);
+/// assert_eq!(mail.metadata()[tracking::METADATA_KEY], url);
+/// ```
+pub fn insert_pixel(html: &str, url: &str) -> String {
+    let at = html::tags(html)
+        .filter(|tag| tag.closing && tag.is("body"))
+        .last()
+        .map_or(html.len(), |tag| tag.span.start);
+
+    let mut out = String::with_capacity(html.len() + url.len() + 100);
+    out.push_str(&html[..at]);
+    out.push_str("<img src=\"");
+    html::push_escaped(&mut out, url);
+    out.push_str(
+        "\" width=\"1\" height=\"1\" alt=\"\" style=\"display:block;border:0;width:1px;height:1px\">",
+    );
+    out.push_str(&html[at..]);
+
+    out
+}
+
+/// Records on a mail that its HTML body has a tracking pixel with the given URL, under
+/// [`METADATA_KEY`]. The metadata isn't sent, so this is only for the application's audits.
+pub fn record(mail: &mut Mail<'_>, url: &str) {
+    mail.metadata_mut().insert(METADATA_KEY.into(), url.into());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::insert_pixel;
+
+    #[test]
+    fn it_inserts_the_pixel_before_the_last_closing_body_tag() {
+        let html = "<body><script>'</body>'</script><p>Hi</p></BODY >";
+        let out = insert_pixel(html, "https://t.example.com/\"x\"");
+
+        assert!(out.starts_with("<body><script>'</body>'</script><p>Hi</p><img src="));
+        assert!(out.contains("src=\"https://t.example.com/&quot;x&quot;\""));
+        assert!(out.ends_with("px\"></BODY >"));
+    }
+
+    #[test]
+    fn it_appends_the_pixel_without_a_body_tag() {
+        let out = insert_pixel("<p>Hi</p>", "https://t.example.com/");
+        assert!(out.starts_with("<p>Hi</p><img src=\"https://t.example.com/\""));
+    }
+}
@@ -0,0 +1,378 @@
+//! Content transfer encodings, which make a body fit the limits of SMTP.
+//!
+//! SMTP only carries lines of at most 998 octets, and without the `8BITMIME` extension only
+//! ASCII. A body that doesn't fit is encoded as quoted-printable, which keeps mostly ASCII text
+//! readable, or as base64, which suits binary content:
+//! ```
+//! use brief::mail::transfer_encoding::TransferEncoding;
+//!
+//! let html = "<p>".repeat(400);
+//! assert_eq!(TransferEncoding::choose(html.as_bytes()), TransferEncoding::QuotedPrintable);
+//! ```
+
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, string::String, vec::Vec};
+
+#[cfg(feature = "alloc")]
+use super::base64;
+use super::header::MAX_LINE_LENGTH;
+
+/// The longest line of quoted-printable or base64 text, without the CRLF.
+#[cfg(feature = "alloc")]
+const MAX_ENCODED_LINE_LENGTH: usize = 76;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseTransferEncodingError;
+
+impl fmt::Display for ParseTransferEncodingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown content transfer encoding")
+    }
+}
+
+impl core::error::Error for ParseTransferEncodingError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidBase64Error;
+
+impl fmt::Display for InvalidBase64Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid base64")
+    }
+}
+
+impl core::error::Error for InvalidBase64Error {}
+
+/// Represents the value of a `Content-Transfer-Encoding` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransferEncoding {
+    /// ASCII lines of at most 998 octets, sent as they are.
+    SevenBit,
+    /// Lines of at most 998 octets, sent as they are to servers with the `8BITMIME` extension.
+    EightBit,
+    QuotedPrintable,
+    Base64,
+}
+
+impl TransferEncoding {
+    /// Chooses the encoding for a body: 7bit for ASCII text that fits the line length limit,
+    /// quoted-printable for other text, and base64 for anything that isn't UTF-8 text. The body
+    /// can then be sent to any server.
+    ///
+    /// ```
+    /// use brief::mail::transfer_encoding::TransferEncoding;
+    ///
+    /// assert_eq!(TransferEncoding::choose(b"Hello"), TransferEncoding::SevenBit);
+    /// assert_eq!(
+    ///     TransferEncoding::choose("Grüße".as_bytes()),
+    ///     TransferEncoding::QuotedPrintable
+    /// );
+    /// assert_eq!(TransferEncoding::choose(b"\x89PNG"), TransferEncoding::Base64);
+    /// ```
+    pub fn choose(body: &[u8]) -> Self {
+        Self::choose_with(body, false)
+    }
+
+    /// Chooses the encoding for a body like [`TransferEncoding::choose`], but chooses 8bit for
+    /// non-ASCII text that fits the line length limit if `eight_bit` is true. Only servers with
+    /// the `8BITMIME` extension accept such a body.
+    ///
+    /// ```
+    /// use brief::mail::transfer_encoding::TransferEncoding;
+    ///
+    /// let body = "Grüße".as_bytes();
+    /// assert_eq!(TransferEncoding::choose_with(body, true), TransferEncoding::EightBit);
+    /// ```
+    pub fn choose_with(body: &[u8], eight_bit: bool) -> Self {
+        if body.contains(&0) || core::str::from_utf8(body).is_err() {
+            return Self::Base64;
+        }
+
+        let has_long_lines = body.split(|b| *b == b'\n').any(|line| {
+            let line = line.strip_suffix(b"\r").unwrap_or(line);
+            line.len() > MAX_LINE_LENGTH
+        });
+        match (has_long_lines, body.is_ascii()) {
+            (true, _) => Self::QuotedPrintable,
+            (false, true) => Self::SevenBit,
+            (false, false) if eight_bit => Self::EightBit,
+            (false, false) => Self::QuotedPrintable,
+        }
+    }
+
+    /// Returns the encoding as it is written in a `Content-Transfer-Encoding` header.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::SevenBit => "7bit",
+            Self::EightBit => "8bit",
+            Self::QuotedPrintable => "quoted-printable",
+            Self::Base64 => "base64",
+        }
+    }
+
+    /// Encodes a body, in lines of at most 76 characters for quoted-printable and base64. 7bit
+    /// and 8bit bodies are borrowed as they are.
+    ///
+    /// ```
+    /// use brief::mail::transfer_encoding::TransferEncoding;
+    ///
+    /// let html = "<p>".repeat(400);
+    /// let encoded = TransferEncoding::QuotedPrintable.encode(html.as_bytes());
+    ///
+    /// assert!(encoded.split(|b| *b == b'\n').all(|line| line.len() <= 77));
+    /// assert_eq!(
+    ///     TransferEncoding::QuotedPrintable.decode(&encoded).unwrap(),
+    ///     html.as_bytes()
+    /// );
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn encode<'a>(&self, body: &'a [u8]) -> Cow<'a, [u8]> {
+        if let Self::SevenBit | Self::EightBit = self {
+            return Cow::Borrowed(body);
+        }
+
+        let mut encoded = Vec::with_capacity(body.len() / 3 * 4 + 4);
+        let result: Result<(), core::convert::Infallible> = self.write(body, &mut |b| {
+            encoded.extend_from_slice(b);
+            Ok(())
+        });
+        let Ok(()) = result;
+
+        Cow::Owned(encoded)
+    }
+
+    /// Encodes a body a line at a time, so it is never held in memory as a whole.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn write<E>(
+        &self,
+        body: &[u8],
+        write: &mut impl FnMut(&[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        match self {
+            Self::SevenBit | Self::EightBit => write(body),
+            Self::QuotedPrintable => write_quoted_printable(body, write),
+            Self::Base64 => write_base64(body, write),
+        }
+    }
+
+    /// Decodes a body. Line breaks in base64 are ignored, and invalid escapes in
+    /// quoted-printable are kept as they are, so only invalid base64 fails.
+    #[cfg(feature = "alloc")]
+    pub fn decode<'a>(&self, body: &'a [u8]) -> Result<Cow<'a, [u8]>, InvalidBase64Error> {
+        match self {
+            Self::SevenBit | Self::EightBit => Ok(Cow::Borrowed(body)),
+            Self::QuotedPrintable => Ok(Cow::Owned(decode_quoted_printable(body))),
+            Self::Base64 => {
+                let text: Vec<u8> = body
+                    .iter()
+                    .copied()
+                    .filter(|b| !b.is_ascii_whitespace())
+                    .collect();
+                base64::decode(&text)
+                    .map(Cow::Owned)
+                    .ok_or(InvalidBase64Error)
+            }
+        }
+    }
+}
+
+impl core::str::FromStr for TransferEncoding {
+    type Err = ParseTransferEncodingError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let is = |other: &str| s.trim().eq_ignore_ascii_case(other);
+
+        match () {
+            _ if is("7bit") => Ok(Self::SevenBit),
+            _ if is("8bit") => Ok(Self::EightBit),
+            _ if is("quoted-printable") => Ok(Self::QuotedPrintable),
+            _ if is("base64") => Ok(Self::Base64),
+            _ => Err(ParseTransferEncodingError),
+        }
+    }
+}
+
+impl fmt::Display for TransferEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Encodes bytes as base64 in lines of at most 76 characters, separated by CRLF.
+#[cfg(feature = "alloc")]
+fn write_base64<E>(bytes: &[u8], write: &mut impl FnMut(&[u8]) -> Result<(), E>) -> Result<(), E> {
+    let mut line = String::with_capacity(MAX_ENCODED_LINE_LENGTH + 2);
+    for (i, chunk) in bytes.chunks(MAX_ENCODED_LINE_LENGTH / 4 * 3).enumerate() {
+        line.clear();
+        if i > 0 {
+            line.push_str("\r\n");
+        }
+        // writing to a string can't fail
+        let _ = base64::encode(chunk, &mut line);
+        write(line.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Encodes text as quoted-printable. Line breaks stay line breaks, as CRLF, and longer lines
+/// are split with soft line breaks.
+#[cfg(feature = "alloc")]
+fn write_quoted_printable<E>(
+    text: &[u8],
+    write: &mut impl FnMut(&[u8]) -> Result<(), E>,
+) -> Result<(), E> {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    let mut line = Vec::with_capacity(MAX_ENCODED_LINE_LENGTH + 2);
+    let mut i = 0;
+
+    while i < text.len() {
+        let b = text[i];
+        let line_break = match (b, text.get(i + 1)) {
+            (b'\r', Some(b'\n')) => 2,
+            (b'\n', _) => 1,
+            _ => 0,
+        };
+        if line_break > 0 {
+            line.extend_from_slice(b"\r\n");
+            write(&line)?;
+            line.clear();
+            i += line_break;
+            continue;
+        }
+
+        // whitespace at the end of a line is removed in transport, so it is escaped there
+        let at_line_end = matches!(text.get(i + 1), None | Some(b'\r' | b'\n'));
+        let is_literal = match b {
+            b' ' | b'\t' => !at_line_end,
+            b'=' => false,
+            b => b.is_ascii_graphic(),
+        };
+        let len = if is_literal { 1 } else { 3 };
+
+        // a line that continues needs room for its soft line break
+        let limit = match at_line_end {
+            true => MAX_ENCODED_LINE_LENGTH,
+            false => MAX_ENCODED_LINE_LENGTH - 1,
+        };
+        if line.len() + len > limit {
+            line.extend_from_slice(b"=\r\n");
+            write(&line)?;
+            line.clear();
+        }
+
+        match is_literal {
+            true => line.push(b),
+            false => {
+                line.extend_from_slice(&[b'=', HEX[usize::from(b >> 4)], HEX[usize::from(b & 15)]])
+            }
+        }
+        i += 1;
+    }
+
+    write(&line)
+}
+
+/// Decodes quoted-printable text, with CRLF line breaks.
+#[cfg(feature = "alloc")]
+fn decode_quoted_printable(text: &[u8]) -> Vec<u8> {
+    let mut decoded = Vec::with_capacity(text.len());
+    let mut lines = text.split(|b| *b == b'\n').peekable();
+
+    while let Some(line) = lines.next() {
+        // whitespace at the end of a line was added in transport
+        let line = line.trim_ascii_end();
+        let (line, is_soft_break) = match line.strip_suffix(b"=") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+
+        let mut i = 0;
+        while i < line.len() {
+            let escaped = match line[i] {
+                b'=' => line
+                    .get(i + 1..i + 3)
+                    .and_then(|hex| core::str::from_utf8(hex).ok())
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok()),
+                _ => None,
+            };
+            match escaped {
+                Some(b) => {
+                    decoded.push(b);
+                    i += 3;
+                }
+                None => {
+                    decoded.push(line[i]);
+                    i += 1;
+                }
+            }
+        }
+
+        if !is_soft_break && lines.peek().is_some() {
+            decoded.extend_from_slice(b"\r\n");
+        }
+    }
+
+    decoded
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use super::{decode_quoted_printable, TransferEncoding};
+
+    fn encode_quoted_printable(text: &[u8]) -> Vec<u8> {
+        TransferEncoding::QuotedPrintable.encode(text).into_owned()
+    }
+
+    #[test]
+    fn it_encodes_quoted_printable() {
+        assert_eq!(
+            encode_quoted_printable("Grüße = hi \nbye\t\r\n".as_bytes()),
+            b"Gr=C3=BC=C3=9Fe =3D hi=20\r\nbye=09\r\n"
+        );
+
+        let encoded = encode_quoted_printable(&[b'a'; 100]);
+        let lines: Vec<_> = encoded.split(|b| *b == b'\n').map(<[u8]>::len).collect();
+        assert_eq!(lines, [77, 25]);
+        assert_eq!(decode_quoted_printable(&encoded), [b'a'; 100]);
+
+        // an escape is never split by a soft line break
+        let text: String = core::iter::repeat_n('a', 72).chain(['ü']).collect();
+        let encoded = encode_quoted_printable(text.as_bytes());
+        assert!(encoded.starts_with(&[b'a'; 72]));
+        assert_eq!(&encoded[72..], b"=C3=\r\n=BC");
+    }
+
+    #[test]
+    fn it_decodes_quoted_printable() {
+        assert_eq!(
+            decode_quoted_printable(b"a=3Db  \r\nc=\r\nd=\nf=zz=4"),
+            b"a=b\r\ncdf=zz=4"
+        );
+    }
+
+    #[test]
+    fn it_round_trips_bodies() {
+        let binary: Vec<u8> = (0..=255).collect();
+        let long = "ä".repeat(600);
+
+        for body in [&b"hello\r\n"[..], long.as_bytes(), &binary] {
+            let encoding = TransferEncoding::choose(body);
+            let encoded = encoding.encode(body);
+            assert!(encoded.is_ascii());
+            assert_eq!(encoding.decode(&encoded).unwrap(), body);
+        }
+
+        assert!(TransferEncoding::Base64.decode(b"Zm9v\r\nYg==").is_ok());
+        assert!(TransferEncoding::Base64.decode(b"Zm9v!").is_err());
+        assert_eq!(
+            " Quoted-Printable".parse(),
+            Ok(TransferEncoding::QuotedPrintable)
+        );
+    }
+}
@@ -1,21 +1,60 @@
-use super::InvalidPartError;
+use super::{syntax::is_atext, Grammar, InvalidPartError, ValidationOptions};
 
 const FORBIDDEN_CHARS: [char; 12] = ['<', '>', '(', ')', '[', ']', '\\', ',', ';', ':', '@', '"'];
 
 pub fn validate_part(part: &str) -> Result<(), InvalidPartError> {
+    validate_part_with(part, ValidationOptions::strict())
+}
+
+/// Like [`validate_part`], but allows UTF-8 if the options do, and only the characters of atoms
+/// and dots in the HTML5 grammar.
+///
+/// ```
+/// use brief::mail::{validate_part, validate_part_with, ValidationOptions};
+///
+/// assert!(validate_part("jöran").is_err());
+/// assert!(validate_part_with("jöran", ValidationOptions::strict().allow_utf8(true)).is_ok());
+///
+/// assert!(validate_part("john doe").is_ok());
+/// assert!(validate_part_with("john doe", ValidationOptions::html5()).is_err());
+/// ```
+pub fn validate_part_with(part: &str, options: ValidationOptions) -> Result<(), InvalidPartError> {
     if part.is_empty() {
         return Err(InvalidPartError::IsEmpty);
     }
 
-    let c = part.chars().find(|c| !c.is_ascii());
-    if let Some(c) = c {
-        return Err(InvalidPartError::ContainsNonAsciiCharacter(c));
+    if !options.allows_utf8() {
+        let c = part.char_indices().find(|(_, c)| !c.is_ascii());
+        if let Some((at, ch)) = c {
+            return Err(InvalidPartError::ContainsNonAsciiCharacter { ch, at });
+        }
+    }
+
+    if options.grammar() == Grammar::Html5 {
+        let c = part
+            .char_indices()
+            .find(|(_, c)| c.is_ascii() && !is_atext(*c) && *c != '.');
+        if let Some((at, ch)) = c {
+            return Err(InvalidPartError::ContainsForbiddenCharacter { ch, at });
+        }
     }
 
-    let f = part.chars().find(|c| FORBIDDEN_CHARS.contains(c));
-    if let Some(f) = f {
-        return Err(InvalidPartError::ContainsForbiddenCharacter(f));
+    let f = part
+        .char_indices()
+        .find(|(_, c)| FORBIDDEN_CHARS.contains(c));
+    if let Some((at, ch)) = f {
+        return Err(InvalidPartError::ContainsForbiddenCharacter { ch, at });
     }
 
     Ok(())
 }
+
+/// Validates the display name of a mailbox. Only an empty name is invalid, since a name with
+/// special characters is written as a quoted string, and a name with non-ASCII or control
+/// characters as encoded words.
+pub(crate) fn validate_name(name: &str) -> Result<(), InvalidPartError> {
+    match name.is_empty() {
+        true => Err(InvalidPartError::IsEmpty),
+        false => Ok(()),
+    }
+}
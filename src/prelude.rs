@@ -0,0 +1,16 @@
+//! The types that are needed to build and send almost any mail, for a glob import:
+//! ```
+//! use brief::prelude::*;
+//!
+//! let alice = Address::try_from("alice@example.com").unwrap();
+//! let bob = Mailbox::try_from("Bob <bob@example.com>").unwrap();
+//! ```
+
+pub use crate::mail::{
+    Address, Header, Mailbox, MailboxList, ParseAddressError, ParseMailboxError,
+    ParseMailboxListError,
+};
+#[cfg(feature = "alloc")]
+pub use crate::mail::{BuildMailError, Mail, MailBuilder, OwnedAddress, OwnedMailbox};
+#[cfg(feature = "smtp")]
+pub use crate::smtp::{SmtpTransport, TransportError};
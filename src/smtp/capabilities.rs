@@ -0,0 +1,326 @@
+/// The reply code of a successful EHLO command.
+const OK: &str = "250";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseCapabilitiesError {
+    IsEmpty,
+    /// A line doesn't start with the `250` reply code.
+    UnexpectedReplyCode,
+    /// A line isn't followed by `-` or a space after the reply code, or a line after the last
+    /// one.
+    InvalidContinuation,
+    /// The `SIZE` keyword has a parameter that isn't a number.
+    InvalidSize,
+}
+
+impl core::fmt::Display for ParseCapabilitiesError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            Self::IsEmpty => "reply is empty",
+            Self::UnexpectedReplyCode => "reply has a code other than 250",
+            Self::InvalidContinuation => "reply has an invalid continuation",
+            Self::InvalidSize => "SIZE parameter is not a number",
+        })
+    }
+}
+
+impl core::error::Error for ParseCapabilitiesError {}
+
+/// An SMTP service extension that a server can advertise in its EHLO reply.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Extension {
+    /// `SIZE` (RFC 1870), see [`Capabilities::max_size`].
+    Size,
+    /// `PIPELINING` (RFC 2920)
+    Pipelining,
+    /// `8BITMIME` (RFC 6152)
+    EightBitMime,
+    /// `BINARYMIME` (RFC 3030)
+    BinaryMime,
+    /// `CHUNKING` (RFC 3030)
+    Chunking,
+    /// `DSN` (RFC 3461)
+    Dsn,
+    /// `SMTPUTF8` (RFC 6531)
+    SmtpUtf8,
+    /// `STARTTLS` (RFC 3207)
+    StartTls,
+    /// `ENHANCEDSTATUSCODES` (RFC 2034)
+    EnhancedStatusCodes,
+    /// `REQUIRETLS` (RFC 8689)
+    RequireTls,
+    /// `AUTH` (RFC 4954), see [`Capabilities::auth_mechanisms`].
+    Auth,
+}
+
+impl Extension {
+    const ALL: [Extension; 11] = [
+        Extension::Size,
+        Extension::Pipelining,
+        Extension::EightBitMime,
+        Extension::BinaryMime,
+        Extension::Chunking,
+        Extension::Dsn,
+        Extension::SmtpUtf8,
+        Extension::StartTls,
+        Extension::EnhancedStatusCodes,
+        Extension::RequireTls,
+        Extension::Auth,
+    ];
+
+    /// Returns the keyword of the extension, as it appears in the EHLO reply.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            Extension::Size => "SIZE",
+            Extension::Pipelining => "PIPELINING",
+            Extension::EightBitMime => "8BITMIME",
+            Extension::BinaryMime => "BINARYMIME",
+            Extension::Chunking => "CHUNKING",
+            Extension::Dsn => "DSN",
+            Extension::SmtpUtf8 => "SMTPUTF8",
+            Extension::StartTls => "STARTTLS",
+            Extension::EnhancedStatusCodes => "ENHANCEDSTATUSCODES",
+            Extension::RequireTls => "REQUIRETLS",
+            Extension::Auth => "AUTH",
+        }
+    }
+
+    fn bit(&self) -> u16 {
+        1 << *self as u16
+    }
+}
+
+/// The capabilities a server advertises in its reply to the EHLO command
+///
+/// ```
+/// use brief::smtp::{Capabilities, Extension};
+///
+/// let reply = "250-mx.example.com greets you\r\n\
+///              250-SIZE 35882577\r\n\
+///              250-AUTH LOGIN PLAIN\r\n\
+///              250 PIPELINING\r\n";
+/// let capabilities = Capabilities::parse(reply).unwrap();
+///
+/// assert_eq!(capabilities.domain(), "mx.example.com");
+/// assert!(capabilities.supports(Extension::Pipelining));
+/// assert!(!capabilities.supports(Extension::SmtpUtf8));
+/// assert_eq!(capabilities.max_size(), Some(35882577));
+/// assert!(capabilities.supports_auth("plain"));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities<'a> {
+    domain: &'a str,
+    extensions: u16,
+    max_size: Option<u64>,
+    auth: &'a str,
+    keywords: &'a str,
+}
+
+impl<'a> Capabilities<'a> {
+    /// Parses a multiline EHLO reply, including the reply codes. Lines can end in CRLF or LF.
+    pub fn parse(reply: &'a str) -> Result<Self, ParseCapabilitiesError> {
+        Self::parse_with(reply, true)
+    }
+
+    /// Parses an EHLO reply that was already read as a whole, ignoring invalid reply codes,
+    /// continuations and parameters, so a server that advertises an odd extension can still be
+    /// used. An empty reply has no capabilities.
+    #[cfg(feature = "smtp")]
+    pub(crate) fn parse_lenient(reply: &'a str) -> Self {
+        Self::parse_with(reply, false).unwrap_or(Capabilities {
+            domain: "",
+            extensions: 0,
+            max_size: None,
+            auth: "",
+            keywords: "",
+        })
+    }
+
+    fn parse_with(reply: &'a str, strict: bool) -> Result<Self, ParseCapabilitiesError> {
+        let last_line = |line: &str| match is_last(line) {
+            Err(_) if !strict => Ok(line.as_bytes().get(OK.len()) != Some(&b'-')),
+            result => result,
+        };
+
+        let mut lines = line_offsets(reply);
+        let (_, greeting) = lines.next().ok_or(ParseCapabilitiesError::IsEmpty)?;
+        let mut last = last_line(greeting)?;
+
+        let mut capabilities = Capabilities {
+            domain: text(greeting).split_whitespace().next().unwrap_or(""),
+            extensions: 0,
+            max_size: None,
+            auth: "",
+            keywords: "",
+        };
+
+        for (offset, line) in lines {
+            if last && strict {
+                return Err(ParseCapabilitiesError::InvalidContinuation);
+            }
+            last = last_line(line)?;
+            if capabilities.keywords.is_empty() {
+                capabilities.keywords = &reply[offset..];
+            }
+
+            let text = text(line);
+            let (keyword, parameters) = text
+                .split_once([' ', '='])
+                .map_or((text, ""), |(k, p)| (k, p.trim()));
+
+            let Some(extension) = Extension::ALL
+                .into_iter()
+                .find(|e| keyword.eq_ignore_ascii_case(e.keyword()))
+            else {
+                continue;
+            };
+
+            capabilities.extensions |= extension.bit();
+            match extension {
+                Extension::Size if !parameters.is_empty() => match parameters.parse() {
+                    // a size of zero means there is no limit
+                    Ok(size) => capabilities.max_size = (size > 0).then_some(size),
+                    Err(_) if strict => return Err(ParseCapabilitiesError::InvalidSize),
+                    Err(_) => {}
+                },
+                Extension::Auth => capabilities.auth = parameters,
+                _ => {}
+            }
+        }
+
+        match last || !strict {
+            true => Ok(capabilities),
+            false => Err(ParseCapabilitiesError::InvalidContinuation),
+        }
+    }
+
+    /// Returns the domain the server greeted with.
+    pub fn domain(&self) -> &'a str {
+        self.domain
+    }
+
+    pub fn supports(&self, extension: Extension) -> bool {
+        self.extensions & extension.bit() != 0
+    }
+
+    /// Returns the maximum message size in octets, or `None` if the server didn't declare one.
+    pub fn max_size(&self) -> Option<u64> {
+        self.max_size
+    }
+
+    /// Returns an iterator over the advertised SASL mechanisms, like `PLAIN`.
+    pub fn auth_mechanisms(&self) -> impl Iterator<Item = &'a str> {
+        self.auth.split_whitespace()
+    }
+
+    /// Returns whether the server supports the given SASL mechanism, ignoring case.
+    pub fn supports_auth(&self, mechanism: &str) -> bool {
+        self.auth_mechanisms()
+            .any(|m| m.eq_ignore_ascii_case(mechanism))
+    }
+
+    /// Returns an iterator over the lines after the greeting without their reply codes, for
+    /// extensions that don't have an [`Extension`] variant.
+    pub fn keywords(&self) -> impl Iterator<Item = &'a str> {
+        line_offsets(self.keywords)
+            .map(|(_, line)| text(line))
+            .filter(|text| !text.is_empty())
+    }
+}
+
+/// Returns an iterator over the lines of a reply with their byte offsets.
+fn line_offsets(reply: &str) -> impl Iterator<Item = (usize, &str)> {
+    reply
+        .split_inclusive('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some((start, line.trim_end_matches(['\r', '\n'])))
+        })
+        .filter(|(_, line)| !line.is_empty())
+}
+
+/// Returns the text of a line, after the reply code.
+fn text(line: &str) -> &str {
+    line.get(OK.len() + 1..).unwrap_or("").trim()
+}
+
+/// Returns whether a line is the last of the reply, checking its reply code.
+fn is_last(line: &str) -> Result<bool, ParseCapabilitiesError> {
+    if !line.starts_with(OK) {
+        return Err(ParseCapabilitiesError::UnexpectedReplyCode);
+    }
+
+    match line.as_bytes().get(OK.len()) {
+        Some(b'-') => Ok(false),
+        Some(b' ') | None => Ok(true),
+        Some(_) => Err(ParseCapabilitiesError::InvalidContinuation),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Capabilities, Extension, ParseCapabilitiesError};
+
+    #[test]
+    fn it_parses_extensions_and_parameters() {
+        let reply = "250-smtp.example.org Hello\n250-8BITMIME\n250-size\n250-AUTH=LOGIN\n250-X-CUSTOM 1\n250 CHUNKING";
+        let capabilities = Capabilities::parse(reply).unwrap();
+
+        assert!(capabilities.supports(Extension::EightBitMime));
+        assert!(capabilities.supports(Extension::Size));
+        assert!(capabilities.supports(Extension::Chunking));
+        assert!(!capabilities.supports(Extension::Dsn));
+        assert_eq!(capabilities.max_size(), None);
+        assert!(capabilities.supports_auth("LOGIN"));
+        assert_eq!(
+            capabilities.keywords().collect::<std::vec::Vec<_>>(),
+            ["8BITMIME", "size", "AUTH=LOGIN", "X-CUSTOM 1", "CHUNKING"]
+        );
+    }
+
+    #[test]
+    fn it_parses_a_reply_without_extensions() {
+        let capabilities = Capabilities::parse("250 mx.example.com\r\n").unwrap();
+
+        assert_eq!(capabilities.domain(), "mx.example.com");
+        assert_eq!(capabilities.keywords().count(), 0);
+    }
+
+    #[test]
+    fn it_fails_to_parse_invalid_replies() {
+        assert_eq!(
+            Capabilities::parse(""),
+            Err(ParseCapabilitiesError::IsEmpty)
+        );
+        assert_eq!(
+            Capabilities::parse("550 no"),
+            Err(ParseCapabilitiesError::UnexpectedReplyCode)
+        );
+        assert_eq!(
+            Capabilities::parse("250-mx\r\n250-DSN\r\n"),
+            Err(ParseCapabilitiesError::InvalidContinuation)
+        );
+        assert_eq!(
+            Capabilities::parse("250 mx\r\n250 DSN\r\n"),
+            Err(ParseCapabilitiesError::InvalidContinuation)
+        );
+        assert_eq!(
+            Capabilities::parse("250-mx\r\n250 SIZE big\r\n"),
+            Err(ParseCapabilitiesError::InvalidSize)
+        );
+    }
+
+    #[cfg(feature = "smtp")]
+    #[test]
+    fn it_parses_invalid_replies_leniently() {
+        let capabilities = Capabilities::parse_lenient("250-mx\r\n251-SIZE big\r\n250-DSN\r\n");
+        assert_eq!(capabilities.domain(), "mx");
+        assert!(capabilities.supports(Extension::Size));
+        assert!(capabilities.supports(Extension::Dsn));
+        assert_eq!(capabilities.max_size(), None);
+
+        assert_eq!(Capabilities::parse_lenient("").keywords().count(), 0);
+    }
+}
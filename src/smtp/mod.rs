@@ -0,0 +1,7 @@
+mod capabilities;
+#[cfg(feature = "smtp")]
+mod transport;
+
+pub use capabilities::{Capabilities, Extension, ParseCapabilitiesError};
+#[cfg(feature = "smtp")]
+pub use transport::{SmtpTransport, TransportError};
@@ -0,0 +1,538 @@
+use core::fmt;
+
+use alloc::{string::String, vec::Vec};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+};
+
+use super::{Capabilities, Extension};
+use crate::mail::{Address, Domain, Header, HeaderMap, Mail};
+
+/// The reply code of the greeting.
+const READY: u16 = 220;
+/// The reply code of the answer to QUIT.
+const CLOSING: u16 = 221;
+/// The reply code of a completed command.
+const OK: u16 = 250;
+/// The reply code of a recipient the server forwards the mail to.
+const FORWARDING: u16 = 251;
+/// The reply code of the answer to DATA, after which the mail is sent.
+const START_DATA: u16 = 354;
+
+#[derive(Debug)]
+pub enum TransportError {
+    Io(io::Error),
+    /// The server sent a line that doesn't start with a reply code.
+    InvalidReply,
+    /// The server didn't accept a command. Contains the reply code and the reply as it was sent.
+    Rejected {
+        code: u16,
+        reply: String,
+    },
+    /// The mail has neither a `Return-Path`, a `Sender` nor a `From` header to send it from.
+    NoSender,
+    /// The mail has no `To`, `Cc` or `Bcc` header to send it to.
+    NoRecipients,
+    /// The mail or its envelope contains UTF-8, and the server doesn't support SMTPUTF8.
+    Smtputf8Unsupported,
+    /// The body of the mail isn't ASCII, and the server doesn't support 8BITMIME.
+    EightBitMimeUnsupported,
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(_) => f.write_str("failed to talk to the server"),
+            Self::InvalidReply => f.write_str("server sent an invalid reply"),
+            Self::Rejected { code, .. } => write!(f, "server replied with code {code}"),
+            Self::NoSender => f.write_str("mail has no sender"),
+            Self::NoRecipients => f.write_str("mail has no recipients"),
+            Self::Smtputf8Unsupported => f.write_str("server doesn't support SMTPUTF8"),
+            Self::EightBitMimeUnsupported => f.write_str("server doesn't support 8BITMIME"),
+        }
+    }
+}
+
+impl core::error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TransportError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// A blocking connection to an SMTP relay, which sends mails over it
+///
+/// The transport speaks plain SMTP, without TLS or authentication, so it is meant for a relay
+/// on a trusted network, like a local Postfix. Mails are sent to the addresses in their `To`,
+/// `Cc` and `Bcc` headers, and the `Bcc` headers are left out of what is sent:
+/// ```no_run
+/// use brief::{
+///     mail::{Address, Domain, MailBuilder},
+///     smtp::SmtpTransport,
+/// };
+///
+/// let mut transport =
+///     SmtpTransport::connect("localhost:25", Domain::try_from("app.example.com").unwrap())?;
+///
+/// let builder = MailBuilder::new()
+///     .from(Address::try_from("alice@example.com").unwrap())
+///     .to(Address::try_from("bob@example.com").unwrap())
+///     .text_body("Hi Bob!");
+/// transport.send(&builder.build().unwrap())?;
+///
+/// transport.quit()?;
+/// # Ok::<(), brief::smtp::TransportError>(())
+/// ```
+#[derive(Debug)]
+pub struct SmtpTransport<S = TcpStream> {
+    stream: BufReader<S>,
+    /// The reply to EHLO, which the capabilities are parsed from.
+    ehlo: String,
+}
+
+impl SmtpTransport {
+    /// Connects to a relay and greets it with the domain of the client.
+    pub fn connect(address: impl ToSocketAddrs, hello: Domain<'_>) -> Result<Self, TransportError> {
+        Self::new(TcpStream::connect(address)?, hello)
+    }
+}
+
+impl<S: Read + Write> SmtpTransport<S> {
+    /// Starts a session over a stream that is already connected to the server, like a TLS
+    /// stream, by waiting for its greeting and sending EHLO with the domain of the client.
+    pub fn new(stream: S, hello: Domain<'_>) -> Result<Self, TransportError> {
+        let mut transport = Self {
+            stream: BufReader::new(stream),
+            ehlo: String::new(),
+        };
+
+        transport.expect(&[READY])?;
+        transport.ehlo = transport.command(format_args!("EHLO {}", hello.to_ascii()), &[OK])?;
+
+        Ok(transport)
+    }
+
+    /// Returns the capabilities the server advertised in its reply to EHLO. Extensions with
+    /// invalid parameters are still supported, but their parameters are ignored.
+    pub fn capabilities(&self) -> Capabilities<'_> {
+        Capabilities::parse_lenient(&self.ehlo)
+    }
+
+    /// Sends a mail from the address in its `Return-Path`, `Sender` or `From` header, in that
+    /// order, to the addresses in its `To`, `Cc` and `Bcc` headers. The `Bcc` and `Return-Path`
+    /// headers are left out of what is sent, since the server that delivers the mail adds the
+    /// `Return-Path` itself.
+    pub fn send(&mut self, mail: &Mail<'_>) -> Result<(), TransportError> {
+        let headers = mail.headers();
+        let from = sender(headers).ok_or(TransportError::NoSender)?;
+        let recipients = recipients(headers);
+        if recipients.is_empty() {
+            return Err(TransportError::NoRecipients);
+        }
+
+        let mut mail = mail.clone();
+        mail.headers_mut().remove("Bcc");
+        mail.headers_mut().remove("Return-Path");
+        self.send_envelope(Some(&from), &recipients, &mail)
+    }
+
+    /// Sends a mail from and to the given addresses, regardless of its headers. Without a
+    /// sender, the mail is sent with a null reverse-path, as bounces are, so nothing is sent
+    /// back if it can't be delivered. The mail is sent as it is, so it shouldn't contain `Bcc`
+    /// or `Return-Path` headers.
+    ///
+    /// If the server rejects the sender or a recipient, the transaction is reset, so the
+    /// transport can be used for the next mail.
+    pub fn send_envelope(
+        &mut self,
+        from: Option<&Address<'_>>,
+        recipients: &[Address<'_>],
+        mail: &Mail<'_>,
+    ) -> Result<(), TransportError> {
+        let capabilities = self.capabilities();
+        let mut parameters = String::new();
+        if mail.requires_smtputf8()
+            || from.is_some_and(Address::requires_smtputf8)
+            || recipients.iter().any(Address::requires_smtputf8)
+        {
+            if !capabilities.supports(Extension::SmtpUtf8) {
+                return Err(TransportError::Smtputf8Unsupported);
+            }
+            parameters.push_str(" SMTPUTF8");
+        }
+        if !mail.body().is_ascii() {
+            if !capabilities.supports(Extension::EightBitMime) {
+                return Err(TransportError::EightBitMimeUnsupported);
+            }
+            parameters.push_str(" BODY=8BITMIME");
+        }
+
+        let result = self.transaction(from, recipients, mail, &parameters);
+        if let Err(TransportError::Rejected { .. }) = result {
+            // a failed RSET leaves the transport as broken as it already is
+            let _ = self.command(format_args!("RSET"), &[OK]);
+        }
+
+        result
+    }
+
+    /// Ends the session.
+    pub fn quit(mut self) -> Result<(), TransportError> {
+        self.command(format_args!("QUIT"), &[CLOSING]).map(drop)
+    }
+
+    fn transaction(
+        &mut self,
+        from: Option<&Address<'_>>,
+        recipients: &[Address<'_>],
+        mail: &Mail<'_>,
+        parameters: &str,
+    ) -> Result<(), TransportError> {
+        match from {
+            Some(from) => self.command(format_args!("MAIL FROM:<{from}>{parameters}"), &[OK])?,
+            None => self.command(format_args!("MAIL FROM:<>{parameters}"), &[OK])?,
+        };
+        for recipient in recipients {
+            self.command(format_args!("RCPT TO:<{recipient}>"), &[OK, FORWARDING])?;
+        }
+        self.command(format_args!("DATA"), &[START_DATA])?;
+
+        let mut data = DotStuffed {
+            inner: self.stream.get_mut(),
+            line_start: true,
+        };
+        mail.write_to(&mut data)?;
+        if !data.line_start {
+            data.inner.write_all(b"\r\n")?;
+        }
+
+        self.command(format_args!("."), &[OK]).map(drop)
+    }
+
+    /// Sends a command and reads the reply, which must have one of the expected codes.
+    fn command(
+        &mut self,
+        command: fmt::Arguments<'_>,
+        expected: &[u16],
+    ) -> Result<String, TransportError> {
+        let stream = self.stream.get_mut();
+        stream.write_fmt(format_args!("{command}\r\n"))?;
+        stream.flush()?;
+
+        self.expect(expected)
+    }
+
+    /// Reads a reply, which must have one of the expected codes.
+    fn expect(&mut self, expected: &[u16]) -> Result<String, TransportError> {
+        let (code, reply) = self.read_reply()?;
+        match expected.contains(&code) {
+            true => Ok(reply),
+            false => Err(TransportError::Rejected { code, reply }),
+        }
+    }
+
+    /// Reads a reply with all its lines, returning its code and the reply as it was sent.
+    fn read_reply(&mut self) -> Result<(u16, String), TransportError> {
+        let mut reply = String::new();
+        loop {
+            let start = reply.len();
+            if self.stream.read_line(&mut reply)? == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+
+            let line = reply[start..].trim_end_matches(['\r', '\n']);
+            let code = match line.get(..3) {
+                Some(code) if code.bytes().all(|b| b.is_ascii_digit()) => code.parse().unwrap_or(0),
+                _ => return Err(TransportError::InvalidReply),
+            };
+
+            match line.as_bytes().get(3) {
+                Some(b'-') => continue,
+                Some(b' ') | None => return Ok((code, reply)),
+                Some(_) => return Err(TransportError::InvalidReply),
+            }
+        }
+    }
+}
+
+/// Returns the address a mail is sent from.
+fn sender<'a>(headers: &HeaderMap<'a>) -> Option<Address<'a>> {
+    headers
+        .iter()
+        .find_map(|h| match h {
+            Header::ReturnPath(address) => Some(address.clone()),
+            _ => None,
+        })
+        .or_else(|| {
+            headers.iter().find_map(|h| match h {
+                Header::Sender(mailbox) => Some(mailbox.address.clone()),
+                _ => None,
+            })
+        })
+        .or_else(|| {
+            headers.iter().find_map(|h| match h {
+                Header::From(list) => list.iter().next().map(|mailbox| mailbox.address),
+                _ => None,
+            })
+        })
+}
+
+/// Returns the addresses a mail is sent to, each once. The domains of addresses are compared
+/// case-insensitively.
+fn recipients<'a>(headers: &HeaderMap<'a>) -> Vec<Address<'a>> {
+    let mut recipients: Vec<Address<'a>> = Vec::new();
+    let mailboxes = headers
+        .iter()
+        .filter_map(|h| match h {
+            Header::To(list) | Header::Cc(list) | Header::Bcc(list) => Some(list.iter()),
+            _ => None,
+        })
+        .flatten();
+
+    for mailbox in mailboxes {
+        let address = mailbox.address;
+        let duplicate = recipients.iter().any(|r| {
+            r.user() == address.user() && r.domain().eq_ignore_ascii_case(address.domain())
+        });
+        if !duplicate {
+            recipients.push(address);
+        }
+    }
+
+    recipients
+}
+
+/// Writes the data of a mail, doubling the dot at the start of every line, so a line with a
+/// single dot doesn't end it early.
+struct DotStuffed<W> {
+    inner: W,
+    line_start: bool,
+}
+
+impl<W: Write> Write for DotStuffed<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(&first) = buf.first() else {
+            return Ok(0);
+        };
+
+        if self.line_start && first == b'.' {
+            self.inner.write_all(b".")?;
+        }
+
+        // write up to the end of the line, so the next write starts at the next line
+        let end = buf
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(buf.len(), |i| i + 1);
+        self.inner.write_all(&buf[..end])?;
+        self.line_start = buf[end - 1] == b'\n';
+
+        Ok(end)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+    use std::io::{self, Read, Write};
+
+    use super::{SmtpTransport, TransportError};
+    use crate::{
+        mail::{Address, Domain, Header, HeaderMap, Mail, MailboxList},
+        smtp::Extension,
+    };
+
+    /// A server that replies with a script, and records what the client sent.
+    struct Server {
+        replies: io::Cursor<&'static [u8]>,
+        received: Vec<u8>,
+    }
+
+    impl Server {
+        fn new(replies: &'static str) -> Self {
+            Self {
+                replies: io::Cursor::new(replies.as_bytes()),
+                received: Vec::new(),
+            }
+        }
+    }
+
+    impl Read for Server {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.replies.read(buf)
+        }
+    }
+
+    impl Write for Server {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.received.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn hello() -> Domain<'static> {
+        Domain::try_from("client.example.com").unwrap()
+    }
+
+    fn received(transport: SmtpTransport<Server>) -> String {
+        String::from_utf8(transport.stream.into_inner().received).unwrap()
+    }
+
+    #[test]
+    fn it_sends_a_mail() {
+        let server = Server::new(
+            "220 mx.example.com ready\r\n\
+             250-mx.example.com\r\n250 8BITMIME\r\n\
+             250 ok\r\n250 ok\r\n250 ok\r\n354 go ahead\r\n250 queued\r\n",
+        );
+        let mut transport = SmtpTransport::new(server, hello()).unwrap();
+        assert!(transport.capabilities().supports(Extension::EightBitMime));
+
+        let headers = HeaderMap::from_iter([
+            Header::ReturnPath("bounce@example.com".try_into().unwrap()),
+            Header::From("Alice <alice@example.com>".try_into().unwrap()),
+            Header::To("bob@example.com".try_into().unwrap()),
+            Header::Bcc("carol@example.com, bob@EXAMPLE.com".try_into().unwrap()),
+        ]);
+        let mail = Mail::new(headers, "Grüße\n.\n..two".as_bytes());
+        transport.send(&mail).unwrap();
+
+        assert_eq!(
+            received(transport),
+            "EHLO client.example.com\r\n\
+             MAIL FROM:<bounce@example.com> BODY=8BITMIME\r\n\
+             RCPT TO:<bob@example.com>\r\n\
+             RCPT TO:<carol@example.com>\r\n\
+             DATA\r\n\
+             From: Alice <alice@example.com>\r\n\
+             To: bob@example.com\r\n\
+             \r\n\
+             Grüße\r\n\
+             ..\r\n\
+             ...two\r\n\
+             .\r\n"
+        );
+    }
+
+    #[test]
+    fn it_sends_a_mail_with_a_null_reverse_path() {
+        let server = Server::new(
+            "220 ready\r\n250 mx.example.com\r\n\
+             250 ok\r\n250 ok\r\n354 go ahead\r\n250 queued\r\n",
+        );
+        let mut transport = SmtpTransport::new(server, hello()).unwrap();
+
+        let to = Address::try_from("alice@example.com").unwrap();
+        let headers = HeaderMap::from_iter([Header::To("alice@example.com".try_into().unwrap())]);
+        transport
+            .send_envelope(None, &[to], &Mail::new(headers, b"Undeliverable"))
+            .unwrap();
+
+        assert!(received(transport).ends_with(
+            "MAIL FROM:<>\r\n\
+             RCPT TO:<alice@example.com>\r\n\
+             DATA\r\n\
+             To: alice@example.com\r\n\
+             \r\n\
+             Undeliverable\r\n\
+             .\r\n"
+        ));
+    }
+
+    #[test]
+    fn it_resets_after_a_rejected_recipient() {
+        let server = Server::new(
+            "220 ready\r\n250 mx.example.com\r\n\
+             250 ok\r\n550 5.1.1 no such user\r\n250 reset\r\n",
+        );
+        let mut transport = SmtpTransport::new(server, hello()).unwrap();
+
+        let headers = HeaderMap::from_iter([
+            Header::ReturnPath("bounce@example.com".try_into().unwrap()),
+            Header::From("alice@example.com".try_into().unwrap()),
+            Header::To("nobody@example.com".try_into().unwrap()),
+        ]);
+        let Err(TransportError::Rejected { code, reply }) =
+            transport.send(&Mail::new(headers, b""))
+        else {
+            panic!("expected the recipient to be rejected");
+        };
+
+        assert_eq!(code, 550);
+        assert_eq!(reply, "550 5.1.1 no such user\r\n");
+        assert!(received(transport).ends_with(
+            "MAIL FROM:<bounce@example.com>\r\nRCPT TO:<nobody@example.com>\r\nRSET\r\n"
+        ));
+    }
+
+    #[test]
+    fn it_checks_the_mail_before_sending_it() {
+        let server = Server::new("220 ready\r\n250 mx.example.com\r\n");
+        let mut transport = SmtpTransport::new(server, hello()).unwrap();
+
+        let headers = HeaderMap::from_iter([Header::From("alice@example.com".try_into().unwrap())]);
+        assert!(matches!(
+            transport.send(&Mail::new(headers, b"")),
+            Err(TransportError::NoRecipients)
+        ));
+
+        let headers = HeaderMap::from_iter([
+            Header::From(MailboxList::parse_international("zoë@example.com").unwrap()),
+            Header::To("bob@example.com".try_into().unwrap()),
+        ]);
+        assert!(matches!(
+            transport.send(&Mail::new(headers, b"")),
+            Err(TransportError::Smtputf8Unsupported)
+        ));
+        assert_eq!(received(transport), "EHLO client.example.com\r\n");
+    }
+
+    #[test]
+    fn it_refuses_to_send_8bit_bodies_without_8bitmime() {
+        let server = Server::new("220 ready\r\n250-mx.example.com\r\n250 SIZE unlimited\r\n");
+        let mut transport = SmtpTransport::new(server, hello()).unwrap();
+        assert!(transport.capabilities().supports(Extension::Size));
+        assert_eq!(transport.capabilities().max_size(), None);
+
+        let headers = HeaderMap::from_iter([
+            Header::From("alice@example.com".try_into().unwrap()),
+            Header::To("bob@example.com".try_into().unwrap()),
+        ]);
+        assert!(matches!(
+            transport.send(&Mail::new(headers, "Grüße".as_bytes())),
+            Err(TransportError::EightBitMimeUnsupported)
+        ));
+        assert_eq!(received(transport), "EHLO client.example.com\r\n");
+    }
+
+    #[test]
+    fn it_fails_on_invalid_replies() {
+        let server = Server::new("220 ready\r\n250-mx.example.com\r\n25x DSN\r\n");
+        assert!(matches!(
+            SmtpTransport::new(server, hello()),
+            Err(TransportError::InvalidReply)
+        ));
+
+        let server = Server::new("554 go away\r\n");
+        assert!(matches!(
+            SmtpTransport::new(server, hello()),
+            Err(TransportError::Rejected { code: 554, .. })
+        ));
+    }
+}